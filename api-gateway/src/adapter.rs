@@ -0,0 +1,235 @@
+use crate::webhook::{path_event_type, BindingError};
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use nexus_event_fabric::CloudEvent;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Header naming the GitHub event, e.g. `push`
+pub const GITHUB_EVENT_HEADER: &str = "x-github-event";
+
+/// Header with the GitHub delivery ID, kept on redelivery
+pub const GITHUB_DELIVERY_HEADER: &str = "x-github-delivery";
+
+/// Webhook providers whose payloads are mapped to event attributes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// Type from `X-GitHub-Event`, ID from `X-GitHub-Delivery`
+    Github,
+    /// Type, ID and time from the Stripe event object
+    Stripe,
+    /// Type, ID and time from the Slack Events API envelope
+    Slack,
+    /// Attributes from the headers named in `mapping`
+    Generic,
+}
+
+impl Provider {
+    fn name(self) -> &'static str {
+        match self {
+            Provider::Github => "GitHub",
+            Provider::Stripe => "Stripe",
+            Provider::Slack => "Slack",
+            Provider::Generic => "generic",
+        }
+    }
+}
+
+/// Headers the generic adapter reads event attributes from
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenericMapping {
+    /// Header holding the event type; the type falls back to the path without it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_header: Option<String>,
+    /// Prefix of types read from `type_header`, default `com.nexus`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_header: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject_header: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_header: Option<String>,
+}
+
+/// Build the event for a provider payload
+///
+/// The event ID is the provider's delivery or event ID, so redeliveries of
+/// the same notification carry the same ID.
+pub(crate) fn adapt(
+    provider: Provider,
+    mapping: &GenericMapping,
+    path: &str,
+    headers: &HeaderMap,
+    data: Value,
+) -> Result<CloudEvent, BindingError> {
+    let invalid = |field: &str, message: &str| BindingError::Provider {
+        provider: provider.name(),
+        field: field.to_string(),
+        message: message.to_string(),
+    };
+
+    let mut event = match provider {
+        Provider::Github => {
+            let name = header(headers, GITHUB_EVENT_HEADER).ok_or_else(|| invalid("X-GitHub-Event", "missing header"))?;
+            let source = str_at(&data, "/repository/html_url").unwrap_or("https://github.com");
+            let mut event = CloudEvent::new(format!("com.github.{}", name), source);
+            if let Some(id) = header(headers, GITHUB_DELIVERY_HEADER) {
+                event.id = id.to_string();
+            }
+            // The branch or tag of pushes, the number of pull requests and issues
+            event.subject = str_at(&data, "/ref")
+                .map(str::to_string)
+                .or_else(|| data.pointer("/pull_request/number").map(|n| format!("pull/{}", n)))
+                .or_else(|| data.pointer("/issue/number").map(|n| format!("issues/{}", n)));
+            event
+        }
+        Provider::Stripe => {
+            let event_type = str_at(&data, "/type").ok_or_else(|| invalid("type", "missing event type"))?;
+            let source = match str_at(&data, "/account") {
+                Some(account) => format!("https://api.stripe.com/v1/accounts/{}", account),
+                None => "https://api.stripe.com".to_string(),
+            };
+            let mut event = CloudEvent::new(format!("com.stripe.{}", event_type), source);
+            if let Some(id) = str_at(&data, "/id") {
+                event.id = id.to_string();
+            }
+            event.subject = str_at(&data, "/data/object/id").map(str::to_string);
+            if let Some(time) = unix_time(data.get("created")) {
+                event.time = time;
+            }
+            event
+        }
+        Provider::Slack => {
+            // Events API callbacks wrap the event; other envelopes (url_verification, app_rate_limited) don't
+            let event_type = str_at(&data, "/event/type")
+                .or_else(|| str_at(&data, "/type"))
+                .ok_or_else(|| invalid("type", "missing event type"))?;
+            let source = match str_at(&data, "/team_id") {
+                Some(team) => format!("https://slack.com/team/{}", team),
+                None => "https://slack.com".to_string(),
+            };
+            let mut event = CloudEvent::new(format!("com.slack.{}", event_type), source);
+            if let Some(id) = str_at(&data, "/event_id") {
+                event.id = id.to_string();
+            }
+            event.subject = str_at(&data, "/event/channel").map(str::to_string);
+            if let Some(time) = unix_time(data.get("event_time")) {
+                event.time = time;
+            }
+            event
+        }
+        Provider::Generic => {
+            let event_type = match mapping.type_header.as_deref().and_then(|name| header(headers, name)) {
+                Some(name) => format!("{}.{}", mapping.type_prefix.as_deref().unwrap_or("com.nexus"), name),
+                None => path_event_type(path),
+            };
+            let source = mapping
+                .source_header
+                .as_deref()
+                .and_then(|name| header(headers, name))
+                .unwrap_or("/api/webhook");
+            let mut event = CloudEvent::new(event_type, source);
+            if let Some(id) = mapping.id_header.as_deref().and_then(|name| header(headers, name)) {
+                event.id = id.to_string();
+            }
+            event.subject = mapping
+                .subject_header
+                .as_deref()
+                .and_then(|name| header(headers, name))
+                .map(str::to_string);
+            event
+        }
+    };
+
+    event = event.with_data(data);
+    event.validate()?;
+    Ok(event)
+}
+
+/// The challenge of a Slack `url_verification` request, which must be echoed back
+pub(crate) fn slack_challenge(data: &Value) -> Option<&str> {
+    if str_at(data, "/type") == Some("url_verification") {
+        str_at(data, "/challenge")
+    } else {
+        None
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+fn str_at<'a>(data: &'a Value, pointer: &str) -> Option<&'a str> {
+    data.pointer(pointer).and_then(Value::as_str)
+}
+
+fn unix_time(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(value?.as_i64()?, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use serde_json::json;
+
+    #[test]
+    fn test_provider_attributes() {
+        let mut headers = HeaderMap::new();
+        headers.insert(GITHUB_EVENT_HEADER, HeaderValue::from_static("push"));
+        headers.insert(GITHUB_DELIVERY_HEADER, HeaderValue::from_static("72d3162e-cc78-11e3-81ab-4c9367dc0958"));
+        let push = json!({"ref": "refs/heads/main", "repository": {"html_url": "https://github.com/acme/app"}});
+        let event = adapt(Provider::Github, &GenericMapping::default(), "github", &headers, push).unwrap();
+        assert_eq!(event.event_type, "com.github.push");
+        assert_eq!(event.id, "72d3162e-cc78-11e3-81ab-4c9367dc0958");
+        assert_eq!(event.source, "https://github.com/acme/app");
+        assert_eq!(event.subject.as_deref(), Some("refs/heads/main"));
+
+        let invoice = json!({"id": "evt_1", "type": "invoice.paid", "created": 1700000000, "data": {"object": {"id": "in_1"}}});
+        let event = adapt(Provider::Stripe, &GenericMapping::default(), "stripe", &HeaderMap::new(), invoice).unwrap();
+        assert_eq!(event.event_type, "com.stripe.invoice.paid");
+        assert_eq!(event.id, "evt_1");
+        assert_eq!(event.subject.as_deref(), Some("in_1"));
+        assert_eq!(event.time.timestamp(), 1700000000);
+
+        let message = json!({"type": "event_callback", "team_id": "T1", "event_id": "Ev1", "event": {"type": "message", "channel": "C1"}});
+        let event = adapt(Provider::Slack, &GenericMapping::default(), "slack", &HeaderMap::new(), message).unwrap();
+        assert_eq!((event.event_type.as_str(), event.id.as_str()), ("com.slack.message", "Ev1"));
+        assert_eq!(event.source, "https://slack.com/team/T1");
+        assert_eq!(slack_challenge(&json!({"type": "url_verification", "challenge": "abc"})), Some("abc"));
+
+        assert!(matches!(
+            adapt(Provider::Github, &GenericMapping::default(), "github", &HeaderMap::new(), json!({})),
+            Err(BindingError::Provider { .. })
+        ));
+    }
+
+    #[test]
+    fn test_generic_header_mapping() {
+        let mapping = GenericMapping {
+            type_header: Some("x-acme-event".to_string()),
+            type_prefix: Some("com.acme".to_string()),
+            id_header: Some("x-acme-delivery".to_string()),
+            subject_header: Some("x-acme-account".to_string()),
+            source_header: None,
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("x-acme-event", HeaderValue::from_static("order.shipped"));
+        headers.insert("x-acme-delivery", HeaderValue::from_static("d-42"));
+        headers.insert("x-acme-account", HeaderValue::from_static("acct-7"));
+
+        let event = adapt(Provider::Generic, &mapping, "acme", &headers, json!({"order": 1})).unwrap();
+        assert_eq!(event.event_type, "com.acme.order.shipped");
+        assert_eq!(event.id, "d-42");
+        assert_eq!(event.subject.as_deref(), Some("acct-7"));
+
+        let event = adapt(Provider::Generic, &mapping, "acme/orders", &HeaderMap::new(), json!({})).unwrap();
+        assert_eq!(event.event_type, "com.nexus.acme.orders");
+    }
+}
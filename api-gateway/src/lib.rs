@@ -1,3 +1,9 @@
 pub mod webhook;
+pub mod signature;
+pub mod adapter;
+pub mod transform;
 
-pub use webhook::WebhookHandler;
+pub use webhook::{BindingError, BindingMode, Ingested, WebhookConfig, WebhookHandler};
+pub use signature::{SignatureError, SignatureScheme};
+pub use adapter::{GenericMapping, Provider};
+pub use transform::{Transform, TransformConfig, TransformError};
@@ -0,0 +1,172 @@
+use crate::webhook::WebhookConfig;
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::Duration;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Header GitHub signs deliveries with, `sha256=<hex>`
+pub const GITHUB_SIGNATURE_HEADER: &str = "x-hub-signature-256";
+
+/// Header Stripe signs deliveries with, `t=<unix time>,v1=<hex>[,v1=<hex>...]`
+pub const STRIPE_SIGNATURE_HEADER: &str = "stripe-signature";
+
+/// Default header of the generic HMAC scheme
+pub const DEFAULT_SIGNATURE_HEADER: &str = "x-signature";
+
+/// How a webhook provider signs its requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureScheme {
+    /// HMAC-SHA256 of the body in `X-Hub-Signature-256`
+    Github,
+    /// HMAC-SHA256 of `<timestamp>.<body>` in `Stripe-Signature`
+    Stripe,
+    /// Hex HMAC-SHA256 of the body in a configurable header, optionally prefixed with `sha256=`
+    Hmac,
+}
+
+/// Reasons a signed webhook request is rejected
+#[derive(Debug, thiserror::Error)]
+pub enum SignatureError {
+    #[error("Missing signature header '{0}'")]
+    Missing(String),
+    #[error("Malformed signature header: {0}")]
+    Malformed(String),
+    #[error("Signature timestamp is outside the {0:?} tolerance")]
+    Expired(Duration),
+    #[error("Signature does not match the request body")]
+    Mismatch,
+}
+
+/// Check a request's signature as of `now`
+pub(crate) fn verify(
+    scheme: SignatureScheme,
+    config: &WebhookConfig,
+    secret: &[u8],
+    headers: &HeaderMap,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Result<(), SignatureError> {
+    match scheme {
+        SignatureScheme::Github => {
+            let value = header(headers, GITHUB_SIGNATURE_HEADER)?;
+            let hex = value
+                .strip_prefix("sha256=")
+                .ok_or_else(|| SignatureError::Malformed("expected 'sha256=<hex>'".to_string()))?;
+            check(secret, &[body], hex)
+        }
+        SignatureScheme::Stripe => {
+            let value = header(headers, STRIPE_SIGNATURE_HEADER)?;
+            let mut timestamp = None;
+            let mut signatures = Vec::new();
+            for part in value.split(',') {
+                match part.trim().split_once('=') {
+                    Some(("t", t)) => timestamp = t.parse::<i64>().ok(),
+                    Some(("v1", signature)) => signatures.push(signature),
+                    _ => {}
+                }
+            }
+            let timestamp = timestamp
+                .ok_or_else(|| SignatureError::Malformed("missing or invalid 't=' timestamp".to_string()))?;
+            if signatures.is_empty() {
+                return Err(SignatureError::Malformed("missing 'v1=' signature".to_string()));
+            }
+            if now.timestamp().abs_diff(timestamp) > config.tolerance.as_secs() {
+                return Err(SignatureError::Expired(config.tolerance));
+            }
+
+            // Stripe may send several signatures while its secret is rolled
+            let signed_prefix = format!("{}.", timestamp);
+            signatures
+                .iter()
+                .find_map(|signature| check(secret, &[signed_prefix.as_bytes(), body], signature).ok())
+                .ok_or(SignatureError::Mismatch)
+        }
+        SignatureScheme::Hmac => {
+            let name = config.header.as_deref().unwrap_or(DEFAULT_SIGNATURE_HEADER);
+            let value = header(headers, name)?;
+            check(secret, &[body], value.strip_prefix("sha256=").unwrap_or(value))
+        }
+    }
+}
+
+/// Compare the HMAC of the message parts with a hex signature, in constant time
+fn check(secret: &[u8], parts: &[&[u8]], hex_signature: &str) -> Result<(), SignatureError> {
+    let signature = hex::decode(hex_signature.trim())
+        .map_err(|_| SignatureError::Malformed("signature is not hex".to_string()))?;
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.verify_slice(&signature).map_err(|_| SignatureError::Mismatch)
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, SignatureError> {
+    headers
+        .get(name)
+        .ok_or_else(|| SignatureError::Missing(name.to_string()))?
+        .to_str()
+        .map_err(|_| SignatureError::Malformed("header is not valid ASCII".to_string()))
+}
+
+/// Hex HMAC-SHA256 of a message, as the schemes above expect it
+pub fn sign(secret: &[u8], message: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn check_signature(scheme: SignatureScheme, name: &'static str, value: &str, body: &[u8], now: DateTime<Utc>) -> Result<(), SignatureError> {
+        let config: WebhookConfig = serde_json::from_value(serde_json::json!({"path": "provider"})).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        verify(scheme, &config, b"whsec", &headers, body, now)
+    }
+
+    #[test]
+    fn test_github_and_hmac_signatures() {
+        let body = br#"{"ref":"refs/heads/main"}"#;
+        let signature = sign(b"whsec", body);
+        let now = Utc::now();
+
+        let github = format!("sha256={}", signature);
+        check_signature(SignatureScheme::Github, GITHUB_SIGNATURE_HEADER, &github, body, now).unwrap();
+        assert!(matches!(
+            check_signature(SignatureScheme::Github, GITHUB_SIGNATURE_HEADER, &github, b"{}", now),
+            Err(SignatureError::Mismatch)
+        ));
+        assert!(matches!(
+            check_signature(SignatureScheme::Github, "x-other", &github, body, now),
+            Err(SignatureError::Missing(_))
+        ));
+
+        check_signature(SignatureScheme::Hmac, DEFAULT_SIGNATURE_HEADER, &signature, body, now).unwrap();
+    }
+
+    #[test]
+    fn test_stripe_signature_tolerance() {
+        let body = br#"{"type":"invoice.paid"}"#;
+        let signed_at = Utc::now();
+        let signature = sign(b"whsec", format!("{}.{}", signed_at.timestamp(), std::str::from_utf8(body).unwrap()).as_bytes());
+        let value = format!("t={},v1={},v1={}", signed_at.timestamp(), "00".repeat(32), signature);
+
+        check_signature(SignatureScheme::Stripe, STRIPE_SIGNATURE_HEADER, &value, body, signed_at).unwrap();
+        assert!(matches!(
+            check_signature(SignatureScheme::Stripe, STRIPE_SIGNATURE_HEADER, &value, body, signed_at + chrono::Duration::minutes(6)),
+            Err(SignatureError::Expired(_))
+        ));
+        assert!(matches!(
+            check_signature(SignatureScheme::Stripe, STRIPE_SIGNATURE_HEADER, &value, b"{}", signed_at),
+            Err(SignatureError::Mismatch)
+        ));
+    }
+}
@@ -0,0 +1,246 @@
+use axum::http::HeaderMap;
+use nexus_event_fabric::UpcastOp;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Reshaping of a webhook payload, as configured under `transform` in a webhook path's settings
+///
+/// Steps run in this order: `select`, `rename`, `remove`, `set`. Field names
+/// in `rename`, `remove` and `set` are dotted paths such as `customer.email`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransformConfig {
+    /// New data built from JSONPath expressions, by output field; omit to keep the payload
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub select: BTreeMap<String, String>,
+    /// Fields to move, old path to new path
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rename: BTreeMap<String, String>,
+    /// Fields to drop
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove: Vec<String>,
+    /// Static fields to add, overwriting existing ones
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub set: BTreeMap<String, Value>,
+    /// Request headers copied to extension attributes, header name to extension name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extensions: BTreeMap<String, String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransformError {
+    #[error("Invalid JSONPath '{path}': {message}")]
+    InvalidPath { path: String, message: String },
+    #[error("{0}")]
+    Apply(String),
+}
+
+/// One step of a JSONPath expression
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    /// `[*]` or `.*`: every element of an array or value of an object
+    Wildcard,
+}
+
+/// The JSONPath subset used by projections: `$`, `.key`, `['key']`, `[0]`, `[*]` and `.*`
+///
+/// Expressions with a wildcard select an array of all matches; others select
+/// a single value, or nothing when the path is missing.
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<Self, TransformError> {
+        let invalid = |message: &str| TransformError::InvalidPath {
+            path: path.to_string(),
+            message: message.to_string(),
+        };
+        let mut rest = path
+            .trim()
+            .strip_prefix('$')
+            .ok_or_else(|| invalid("must start with '$'"))?;
+
+        let mut segments = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                let key = &after[..end];
+                segments.push(match key {
+                    "" => return Err(invalid("empty field name")),
+                    "*" => Segment::Wildcard,
+                    key => Segment::Key(key.to_string()),
+                });
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(|| invalid("unclosed '['"))?;
+                let inner = after[..end].trim();
+                segments.push(if inner == "*" {
+                    Segment::Wildcard
+                } else if let Some(key) = inner
+                    .strip_prefix('\'')
+                    .and_then(|k| k.strip_suffix('\''))
+                    .or_else(|| inner.strip_prefix('"').and_then(|k| k.strip_suffix('"')))
+                {
+                    Segment::Key(key.to_string())
+                } else {
+                    Segment::Index(inner.parse().map_err(|_| invalid("expected an index, '*' or a quoted name"))?)
+                });
+                rest = &after[end + 1..];
+            } else {
+                return Err(invalid("expected '.' or '['"));
+            }
+        }
+        Ok(Self { segments })
+    }
+
+    /// The selected value, `None` if the path leads nowhere
+    pub fn select(&self, data: &Value) -> Option<Value> {
+        let mut matches = vec![data];
+        for segment in &self.segments {
+            matches = matches
+                .into_iter()
+                .flat_map(|value| -> Vec<&Value> {
+                    match (segment, value) {
+                        (Segment::Key(key), _) => value.get(key).into_iter().collect(),
+                        (Segment::Index(index), _) => value.get(index).into_iter().collect(),
+                        (Segment::Wildcard, Value::Array(items)) => items.iter().collect(),
+                        (Segment::Wildcard, Value::Object(fields)) => fields.values().collect(),
+                        (Segment::Wildcard, _) => vec![],
+                    }
+                })
+                .collect();
+        }
+
+        if self.segments.contains(&Segment::Wildcard) {
+            Some(Value::Array(matches.into_iter().cloned().collect()))
+        } else {
+            matches.first().map(|value| (*value).clone())
+        }
+    }
+}
+
+/// A compiled [`TransformConfig`]
+#[derive(Debug, Clone)]
+pub struct Transform {
+    select: Vec<(String, JsonPath)>,
+    operations: Vec<UpcastOp>,
+    extensions: BTreeMap<String, String>,
+}
+
+/// Data and extension attributes produced by a transform
+#[derive(Debug)]
+pub struct Transformed {
+    pub data: Value,
+    pub extensions: Vec<(String, Value)>,
+}
+
+impl Transform {
+    pub fn new(config: &TransformConfig) -> Result<Self, TransformError> {
+        let select = config
+            .select
+            .iter()
+            .map(|(field, path)| Ok((field.clone(), JsonPath::parse(path)?)))
+            .collect::<Result<_, TransformError>>()?;
+
+        let renames = config.rename.iter().map(|(from, to)| UpcastOp::Rename {
+            from: from.clone(),
+            to: to.clone(),
+        });
+        let removals = config.remove.iter().map(|path| UpcastOp::Remove { path: path.clone() });
+        let sets = config.set.iter().map(|(path, value)| UpcastOp::Set {
+            path: path.clone(),
+            value: value.clone(),
+        });
+
+        Ok(Self {
+            select,
+            operations: renames.chain(removals).chain(sets).collect(),
+            extensions: config.extensions.clone(),
+        })
+    }
+
+    pub fn apply(&self, headers: &HeaderMap, data: Value) -> Result<Transformed, TransformError> {
+        let mut data = if self.select.is_empty() {
+            data
+        } else {
+            let mut projected = Value::Object(Map::new());
+            for (field, path) in &self.select {
+                if let Some(value) = path.select(&data) {
+                    UpcastOp::Set {
+                        path: field.clone(),
+                        value,
+                    }
+                    .apply(&mut projected)
+                    .map_err(|e| TransformError::Apply(e.to_string()))?;
+                }
+            }
+            projected
+        };
+
+        for operation in &self.operations {
+            operation
+                .apply(&mut data)
+                .map_err(|e| TransformError::Apply(e.to_string()))?;
+        }
+
+        let extensions = self
+            .extensions
+            .iter()
+            .filter_map(|(header, extension)| {
+                let value = headers.get(header.as_str())?.to_str().ok()?;
+                Some((extension.clone(), Value::String(value.to_string())))
+            })
+            .collect();
+
+        Ok(Transformed { data, extensions })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_path() {
+        let data = json!({"order": {"id": 7, "items": [{"sku": "a"}, {"sku": "b"}], "ship-to": {"zip": "1000"}}});
+        let select = |path: &str| JsonPath::parse(path).unwrap().select(&data);
+
+        assert_eq!(select("$.order.id"), Some(json!(7)));
+        assert_eq!(select("$.order.items[1].sku"), Some(json!("b")));
+        assert_eq!(select("$.order.items[*].sku"), Some(json!(["a", "b"])));
+        assert_eq!(select("$.order['ship-to'].zip"), Some(json!("1000")));
+        assert_eq!(select("$.order.missing"), None);
+        assert!(JsonPath::parse("order.id").is_err());
+        assert!(JsonPath::parse("$.items[x]").is_err());
+    }
+
+    #[test]
+    fn test_transform_steps() {
+        let config: TransformConfig = serde_json::from_value(json!({
+            "select": {"order_id": "$.order.id", "customer.mail": "$.buyer.email", "skus": "$.order.items[*].sku"},
+            "rename": {"customer.mail": "customer.email"},
+            "remove": ["skus"],
+            "set": {"channel": "web"},
+            "extensions": {"x-tenant-id": "tenant"}
+        }))
+        .unwrap();
+        let transform = Transform::new(&config).unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-tenant-id", HeaderValue::from_static("acme"));
+        let payload = json!({"order": {"id": 7, "items": [{"sku": "a"}]}, "buyer": {"email": "ada@example.com"}, "internal": true});
+        let transformed = transform.apply(&headers, payload).unwrap();
+
+        assert_eq!(
+            transformed.data,
+            json!({"order_id": 7, "customer": {"email": "ada@example.com"}, "channel": "web"})
+        );
+        assert_eq!(transformed.extensions, vec![("tenant".to_string(), json!("acme"))]);
+    }
+}
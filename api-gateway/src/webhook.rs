@@ -1,39 +1,328 @@
-use axum::http::HeaderMap;
+use crate::adapter::{self, GenericMapping, Provider};
+use crate::signature::{self, SignatureError, SignatureScheme};
+use crate::transform::{Transform, TransformConfig, TransformError};
+use axum::http::{header, HeaderMap};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use nexus_event_fabric::cloudevents::is_json_content_type;
+use nexus_event_fabric::stream_config::{deserialize_duration, serialize_duration};
+use nexus_event_fabric::{CloudEvent, ValidationError};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::time::Duration;
+use tracing::debug;
+
+/// Content type of a structured-mode CloudEvent
+pub const STRUCTURED_CONTENT_TYPE: &str = "application/cloudevents+json";
+
+/// Content type of a batch of structured-mode CloudEvents
+pub const BATCH_CONTENT_TYPE: &str = "application/cloudevents-batch+json";
+
+/// Prefix of binary-mode attribute headers
+const HEADER_PREFIX: &str = "ce-";
+
+/// Errors decoding a request under the CloudEvents HTTP protocol binding
+#[derive(Debug, thiserror::Error)]
+pub enum BindingError {
+    #[error("Missing required CloudEvents attribute '{0}'")]
+    MissingAttribute(String),
+    #[error("Invalid CloudEvent: {0}")]
+    Invalid(#[from] ValidationError),
+    #[error("Invalid value for '{name}': {message}")]
+    InvalidAttribute { name: String, message: String },
+    #[error("Invalid request body: {0}")]
+    InvalidBody(String),
+    #[error("Transform failed: {0}")]
+    Transform(#[from] TransformError),
+    #[error("Invalid {provider} webhook, '{field}': {message}")]
+    Provider {
+        provider: &'static str,
+        field: String,
+        message: String,
+    },
+}
+
+/// Settings of a webhook path, as configured in the `webhooks` section of nexus.yaml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// Path under `/webhook/`, e.g. `github`; sub-paths are covered too
+    pub path: String,
+    /// Provider whose payloads are mapped to event attributes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<Provider>,
+    /// Headers read by the `generic` provider
+    #[serde(default)]
+    pub mapping: GenericMapping,
+    /// Reshaping of plain JSON payloads before they become event data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformConfig>,
+    /// Scheme requests must be signed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SignatureScheme>,
+    /// Shared signing secret; prefer `secret_env` to keep it out of the file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Environment variable holding the signing secret
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_env: Option<String>,
+    /// Signature header of the `hmac` scheme
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    /// Maximum age of a Stripe signature timestamp
+    #[serde(
+        default = "default_tolerance",
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub tolerance: Duration,
+}
+
+fn default_tolerance() -> Duration {
+    Duration::from_secs(5 * 60)
+}
+
+impl WebhookConfig {
+    /// The signing secret, read from the environment when `secret_env` is set
+    pub fn resolve_secret(&self) -> anyhow::Result<String> {
+        match (&self.secret_env, &self.secret) {
+            (Some(var), _) => std::env::var(var)
+                .map_err(|_| anyhow::anyhow!("Webhook '{}' secret variable {} is not set", self.path, var)),
+            (None, Some(secret)) => Ok(secret.clone()),
+            (None, None) => anyhow::bail!("Webhook '{}' needs a secret or secret_env", self.path),
+        }
+    }
+
+    /// Whether the config covers a webhook path
+    fn covers(&self, path: &str) -> bool {
+        let own = self.path.trim_matches('/');
+        let path = path.trim_matches('/');
+        path == own || path.strip_prefix(own).is_some_and(|rest| rest.starts_with('/'))
+    }
+}
+
+/// A configured webhook path with its resolved secret and compiled transform
+struct Webhook {
+    config: WebhookConfig,
+    secret: Option<Vec<u8>>,
+    transform: Option<Transform>,
+}
+
+/// How the request carries its event(s)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingMode {
+    /// Attributes in `ce-*` headers, the body is the event data
+    Binary,
+    /// The body is a whole CloudEvent (`application/cloudevents+json`)
+    Structured,
+    /// The body is a JSON array of CloudEvents (`application/cloudevents-batch+json`)
+    Batch,
+    /// Payload without CloudEvents attributes
+    Plain,
+}
+
+/// Result of decoding an ingestion request
+#[derive(Debug)]
+pub enum Ingested {
+    /// Events with producer-supplied attributes
+    Events(Vec<CloudEvent>),
+    /// Plain JSON the caller wraps into an event of its own
+    Plain(Value),
+    /// Non-JSON body (XML, form posts, images...) with its content type
+    Raw { content_type: String, body: Bytes },
+}
 
 /// Handles HTTP webhook ingestion and converts to CloudEvents
-pub struct WebhookHandler;
+///
+/// Implements the CloudEvents HTTP protocol binding
+/// (https://github.com/cloudevents/spec/blob/v1.0.2/cloudevents/bindings/http-protocol-binding.md)
+/// for binary, structured and batch mode. Configured paths can require a
+/// signature (see [`Self::verify`]) and map provider payloads to event
+/// attributes.
+pub struct WebhookHandler {
+    webhooks: Vec<Webhook>,
+}
 
 impl WebhookHandler {
     pub fn new() -> Self {
-        Self
+        Self { webhooks: vec![] }
+    }
+
+    /// Apply the signature and provider settings of the configured webhook paths
+    pub fn with_webhooks(mut self, webhooks: Vec<WebhookConfig>) -> anyhow::Result<Self> {
+        for config in webhooks {
+            let secret = match config.signature {
+                Some(_) => Some(config.resolve_secret()?.into_bytes()),
+                None => None,
+            };
+            let transform = config
+                .transform
+                .as_ref()
+                .map(Transform::new)
+                .transpose()
+                .map_err(|e| anyhow::anyhow!("Webhook '{}': {}", config.path, e))?;
+            self.webhooks.push(Webhook { config, secret, transform });
+        }
+        Ok(self)
+    }
+
+    /// The most specific webhook config covering a path
+    fn webhook(&self, path: &str) -> Option<&Webhook> {
+        self.webhooks
+            .iter()
+            .filter(|webhook| webhook.config.covers(path))
+            .max_by_key(|webhook| webhook.config.path.trim_matches('/').len())
+    }
+
+    /// Whether requests to a path must be signed
+    pub fn is_signed(&self, path: &str) -> bool {
+        self.webhook(path).is_some_and(|webhook| webhook.config.signature.is_some())
+    }
+
+    /// The provider adapter of a path, if any
+    pub fn provider(&self, path: &str) -> Option<Provider> {
+        self.webhook(path)?.config.provider
+    }
+
+    /// Check the signature of a request to a webhook path
+    ///
+    /// Paths without a configured secret are accepted as they are. The body
+    /// must be the exact bytes received, before any parsing.
+    pub fn verify(&self, path: &str, headers: &HeaderMap, body: &Bytes) -> Result<(), SignatureError> {
+        let Some(webhook) = self.webhook(path) else {
+            return Ok(());
+        };
+        match (webhook.config.signature, &webhook.secret) {
+            (Some(scheme), Some(secret)) => signature::verify(scheme, &webhook.config, secret, headers, body, Utc::now()),
+            _ => Ok(()),
+        }
+    }
+
+    /// The challenge to answer a Slack `url_verification` request on a Slack path with
+    pub fn slack_challenge(&self, path: &str, headers: &HeaderMap, body: &Bytes) -> Option<String> {
+        if self.provider(path) != Some(Provider::Slack) || self.mode(headers) != BindingMode::Plain {
+            return None;
+        }
+        let data: Value = serde_json::from_slice(body).ok()?;
+        adapter::slack_challenge(&data).map(str::to_string)
+    }
+
+    /// Detect the binding mode of a request from its headers
+    pub fn mode(&self, headers: &HeaderMap) -> BindingMode {
+        match media_type(headers).as_deref() {
+            Some(STRUCTURED_CONTENT_TYPE) => BindingMode::Structured,
+            Some(BATCH_CONTENT_TYPE) => BindingMode::Batch,
+            _ if headers.contains_key("ce-specversion") => BindingMode::Binary,
+            _ => BindingMode::Plain,
+        }
+    }
+
+    /// Decode a request body according to its binding mode
+    pub fn decode(&self, headers: &HeaderMap, body: &Bytes) -> Result<Ingested, BindingError> {
+        match self.mode(headers) {
+            BindingMode::Binary => Ok(Ingested::Events(vec![self.decode_binary(headers, body)?])),
+            BindingMode::Structured => {
+                let value = parse_json(body)?;
+                Ok(Ingested::Events(vec![decode_structured(value)?]))
+            }
+            BindingMode::Batch => match parse_json(body)? {
+                Value::Array(items) => Ok(Ingested::Events(
+                    items
+                        .into_iter()
+                        .map(decode_structured)
+                        .collect::<Result<_, _>>()?,
+                )),
+                _ => Err(BindingError::InvalidBody(
+                    "batch mode requires a JSON array of events".to_string(),
+                )),
+            },
+            BindingMode::Plain => {
+                if let Some(content_type) = content_type(headers).filter(|ct| !is_json_content_type(ct)) {
+                    return Ok(Ingested::Raw {
+                        content_type,
+                        body: body.clone(),
+                    });
+                }
+                let value = if body.is_empty() {
+                    Value::Object(Default::default())
+                } else {
+                    parse_json(body)?
+                };
+                Ok(Ingested::Plain(value))
+            }
+        }
+    }
+
+    /// Build an event from `ce-*` headers, with the body as its data
+    fn decode_binary(&self, headers: &HeaderMap, body: &Bytes) -> Result<CloudEvent, BindingError> {
+        let mut attributes = serde_json::Map::new();
+        for (name, value) in headers {
+            let Some(attribute) = name.as_str().strip_prefix(HEADER_PREFIX) else {
+                continue;
+            };
+            let value = value.to_str().map_err(|_| BindingError::InvalidAttribute {
+                name: attribute.to_string(),
+                message: "header is not valid ASCII".to_string(),
+            })?;
+            attributes.insert(attribute.to_string(), Value::String(percent_decode(attribute, value)?));
+        }
+
+        let content_type = content_type(headers);
+        let json_body = content_type.as_deref().is_none_or(is_json_content_type);
+
+        if let Some(content_type) = &content_type {
+            attributes.insert("datacontenttype".to_string(), Value::String(content_type.clone()));
+        }
+        if json_body && !body.is_empty() {
+            attributes.insert("data".to_string(), parse_json(body)?);
+        }
+
+        let event = decode_structured(Value::Object(attributes))?;
+        match content_type {
+            // Other media types keep their exact bytes in `data_base64`
+            Some(content_type) if !json_body && !body.is_empty() => {
+                Ok(event.with_binary_data(body, content_type))
+            }
+            _ => Ok(event),
+        }
     }
 
     /// Convert HTTP request to CloudEvent format
-    pub fn to_cloud_event(
+    ///
+    /// Plain JSON on a path with a provider gets its attributes from the
+    /// provider's payload and headers. Other requests without CloudEvents
+    /// attributes get the type `com.nexus.<path>` and the webhook source.
+    /// A path's transform then reshapes the data of plain JSON payloads.
+    pub fn to_cloud_events(
         &self,
         path: &str,
-        _headers: &HeaderMap,
-        body: Value,
-    ) -> Result<serde_json::Value, anyhow::Error> {
-        // Extract event type from path
-        let event_type = path
-            .strip_prefix("/events/")
-            .unwrap_or("unknown")
-            .replace('/', ".");
-
-        // Build CloudEvent
-        let event = serde_json::json!({
-            "specversion": "1.0",
-            "type": format!("com.nexus.{}", event_type),
-            "source": "/api/webhook",
-            "id": uuid::Uuid::new_v4().to_string(),
-            "time": chrono::Utc::now().to_rfc3339(),
-            "datacontenttype": "application/json",
-            "data": body
-        });
-
-        Ok(event)
+        headers: &HeaderMap,
+        body: &Bytes,
+    ) -> Result<Vec<CloudEvent>, BindingError> {
+        match self.decode(headers, body)? {
+            Ingested::Events(events) => Ok(events),
+            Ingested::Plain(data) => {
+                let webhook = self.webhook(path);
+                // Providers read their attributes from the payload as received
+                let transformed = match webhook.and_then(|webhook| webhook.transform.as_ref()) {
+                    Some(transform) => Some(transform.apply(headers, data.clone())?),
+                    None => None,
+                };
+                let mut event = match webhook.and_then(|webhook| webhook.config.provider) {
+                    Some(provider) => adapter::adapt(provider, &webhook.unwrap().config.mapping, path, headers, data)?,
+                    None => CloudEvent::new(path_event_type(path), "/api/webhook").with_data(data),
+                };
+                if let Some(transformed) = transformed {
+                    event.data = Some(transformed.data);
+                    event.extensions.extend(transformed.extensions);
+                    event.validate()?;
+                }
+                strip_reserved(&mut event);
+                Ok(vec![event])
+            }
+            Ingested::Raw { content_type, body } => Ok(vec![
+                CloudEvent::new(path_event_type(path), "/api/webhook").with_binary_data(&body, content_type)
+            ]),
+        }
     }
 }
 
@@ -42,3 +331,212 @@ impl Default for WebhookHandler {
         Self::new()
     }
 }
+
+/// Event type for a webhook path, e.g. `user/created` -> `com.nexus.user.created`
+pub(crate) fn path_event_type(path: &str) -> String {
+    format!("com.nexus.{}", path.trim_matches('/').replace('/', "."))
+}
+
+/// Decode a structured-mode event, checking the required attributes
+fn decode_structured(value: Value) -> Result<CloudEvent, BindingError> {
+    let Value::Object(mut attributes) = value else {
+        return Err(BindingError::InvalidBody("an event must be a JSON object".to_string()));
+    };
+
+    for name in ["specversion", "id", "source", "type"] {
+        required_str(&attributes, name)?;
+    }
+
+    // `time` is optional in CloudEvents; default it to the time of receipt
+    match attributes.get("time") {
+        None => {
+            attributes.insert("time".to_string(), Value::String(Utc::now().to_rfc3339()));
+        }
+        Some(Value::String(time)) if DateTime::parse_from_rfc3339(time).is_ok() => {}
+        Some(_) => {
+            return Err(BindingError::InvalidAttribute {
+                name: "time".to_string(),
+                message: "must be an RFC 3339 timestamp".to_string(),
+            })
+        }
+    }
+
+    let mut event: CloudEvent = serde_json::from_value(Value::Object(attributes))
+        .map_err(|e| BindingError::InvalidBody(e.to_string()))?;
+    event.validate()?;
+    strip_reserved(&mut event);
+    Ok(event)
+}
+
+/// Drop the reserved `nexus*` extensions a sender set on an event
+fn strip_reserved(event: &mut CloudEvent) {
+    let stripped = event.strip_reserved_extensions();
+    if !stripped.is_empty() {
+        debug!(event_id = %event.id, extensions = ?stripped, "Dropped reserved extensions from ingested event");
+    }
+}
+
+fn required_str<'a>(
+    attributes: &'a serde_json::Map<String, Value>,
+    name: &str,
+) -> Result<&'a str, BindingError> {
+    match attributes.get(name) {
+        Some(Value::String(value)) => Ok(value),
+        Some(_) => Err(BindingError::InvalidAttribute {
+            name: name.to_string(),
+            message: "must be a string".to_string(),
+        }),
+        None => Err(BindingError::MissingAttribute(name.to_string())),
+    }
+}
+
+fn parse_json(body: &Bytes) -> Result<Value, BindingError> {
+    serde_json::from_slice(body).map_err(|e| BindingError::InvalidBody(e.to_string()))
+}
+
+/// Media type of the request without parameters, lowercased
+fn media_type(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    let media_type = value.split(';').next()?.trim();
+    Some(media_type.to_ascii_lowercase())
+}
+
+fn content_type(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    Some(value.to_string())
+}
+
+/// Undo the percent-encoding binary mode applies to header values
+fn percent_decode(name: &str, value: &str) -> Result<String, BindingError> {
+    let invalid = || BindingError::InvalidAttribute {
+        name: name.to_string(),
+        message: "invalid percent-encoding".to_string(),
+    };
+
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3).ok_or_else(invalid)?;
+            decoded.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_binary_mode() {
+        let mut headers = HeaderMap::new();
+        headers.insert("ce-specversion", HeaderValue::from_static("1.0"));
+        headers.insert("ce-type", HeaderValue::from_static("com.example.order.created"));
+        headers.insert("ce-source", HeaderValue::from_static("/orders"));
+        headers.insert("ce-id", HeaderValue::from_static("order-1"));
+        headers.insert("ce-time", HeaderValue::from_static("2024-01-02T03:04:05Z"));
+        headers.insert("ce-tenant", HeaderValue::from_static("acme%20corp"));
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        let handler = WebhookHandler::new();
+        let body = Bytes::from_static(br#"{"total": 10}"#);
+        let events = handler.to_cloud_events("orders", &headers, &body).unwrap();
+
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.id, "order-1");
+        assert_eq!(event.event_type, "com.example.order.created");
+        assert_eq!(event.source, "/orders");
+        assert_eq!(event.time.to_rfc3339(), "2024-01-02T03:04:05+00:00");
+        assert_eq!(event.extensions["tenant"], "acme corp");
+        assert_eq!(event.data.as_ref().unwrap()["total"], 10);
+    }
+
+    #[test]
+    fn test_reserved_extensions_are_dropped() {
+        use nexus_event_fabric::crypto::{is_encrypted, EncryptionRule, Encryptor, Keyring, DATA_KEY_EXTENSION};
+
+        let mut headers = HeaderMap::new();
+        headers.insert("ce-specversion", HeaderValue::from_static("1.0"));
+        headers.insert("ce-type", HeaderValue::from_static("com.nexus.user.created"));
+        headers.insert("ce-source", HeaderValue::from_static("/users"));
+        headers.insert("ce-id", HeaderValue::from_static("user-1"));
+        headers.insert("ce-nexusdek", HeaderValue::from_static("forged"));
+        headers.insert("ce-nexuserased", HeaderValue::from_static("true"));
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let body = Bytes::from_static(br#"{"email": "ada@example.com"}"#);
+        let event = WebhookHandler::new().to_cloud_events("users", &headers, &body).unwrap().remove(0);
+        assert!(event.extensions.is_empty());
+
+        // A forged data key no longer makes the event look encrypted already
+        let rule = EncryptionRule {
+            event_type: "com.nexus.user.*".to_string(),
+            paths: vec!["email".to_string()],
+            subject_key: None,
+        };
+        let encrypted = Encryptor::new(Keyring::generate(), vec![rule]).encrypt(&event).unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert_ne!(encrypted.extensions[DATA_KEY_EXTENSION], "forged");
+        assert_ne!(encrypted.data.as_ref().unwrap()["email"], "ada@example.com");
+    }
+
+    #[test]
+    fn test_structured_and_batch_mode() {
+        let handler = WebhookHandler::new();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/cloudevents+json; charset=utf-8"),
+        );
+        let body = Bytes::from_static(
+            br#"{"specversion":"1.0","type":"t","source":"/s","id":"1","traceparent":"00-abc"}"#,
+        );
+        let events = handler.to_cloud_events("ignored", &headers, &body).unwrap();
+        assert_eq!(events[0].id, "1");
+        assert_eq!(events[0].extensions["traceparent"], "00-abc");
+
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(BATCH_CONTENT_TYPE));
+        let body = Bytes::from_static(
+            br#"[{"specversion":"1.0","type":"t","source":"/s","id":"1"},
+                 {"specversion":"1.0","type":"t","source":"/s","id":"2"}]"#,
+        );
+        assert_eq!(handler.to_cloud_events("ignored", &headers, &body).unwrap().len(), 2);
+
+        let body = Bytes::from_static(br#"[{"specversion":"1.0","type":"t","id":"1"}]"#);
+        assert!(matches!(
+            handler.to_cloud_events("ignored", &headers, &body),
+            Err(BindingError::MissingAttribute(name)) if name == "source"
+        ));
+
+        let body = Bytes::from_static(br#"[{"specversion":"0.3","type":"t","source":"/s","id":"1"}]"#);
+        assert!(matches!(
+            handler.to_cloud_events("ignored", &headers, &body),
+            Err(BindingError::Invalid(ValidationError::UnsupportedVersion(_)))
+        ));
+    }
+
+    #[test]
+    fn test_plain_json_uses_path() {
+        let handler = WebhookHandler::new();
+        let body = Bytes::from_static(br#"{"user": "alice"}"#);
+        let events = handler.to_cloud_events("user/created", &HeaderMap::new(), &body).unwrap();
+
+        assert_eq!(events[0].event_type, "com.nexus.user.created");
+        assert_eq!(events[0].source, "/api/webhook");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/xml"));
+        let body = Bytes::from_static(b"<user>alice</user>");
+        let events = handler.to_cloud_events("user/created", &headers, &body).unwrap();
+        assert_eq!(events[0].datacontenttype.as_deref(), Some("application/xml"));
+        assert_eq!(events[0].data_bytes().unwrap().unwrap(), b"<user>alice</user>");
+    }
+}
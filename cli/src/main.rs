@@ -1,7 +1,15 @@
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
+use bytes::Bytes;
 use clap::{Parser, Subcommand};
 use colored::Colorize;
-use nexus_core::{AppState, NexusConfig, Server};
-use nexus_event_fabric::NatsClient;
+use nexus_api_gateway::WebhookHandler;
+use nexus_core::config::EventBackendKind;
+use nexus_core::archiver::spawn_archiver;
+use nexus_core::partition::{replica_ordinal, replica_partitions};
+use nexus_core::worker::spawn_function_workers;
+use nexus_core::{AppState, Dispatch, NexusConfig, SchemaRegistry, Server};
+use nexus_event_fabric::{ndjson, JetStreamBackend, Keyring, NatsClient};
+use std::io::Write;
 use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -34,6 +42,68 @@ async fn get_event_by_id(event_id: &str) -> anyhow::Result<serde_json::Value> {
     Ok(data)
 }
 
+/// Replay an event, optionally to a single function
+async fn replay_event(event_id: &str, function: Option<&str>) -> anyhow::Result<serde_json::Value> {
+    let url = format!("http://localhost:8080/replay/{}", event_id);
+    let client = reqwest::Client::new();
+    let mut request = client.post(&url);
+    if let Some(function) = function {
+        request = request.query(&[("function", function)]);
+    }
+    let response = request.send().await?;
+    
+    if !response.status().is_success() {
+        anyhow::bail!("Server returned status: {}", response.status());
+    }
+    
+    let data = response.json().await?;
+    Ok(data)
+}
+
+/// Download events as NDJSON
+async fn export_events(event_type: Option<&str>, limit: Option<usize>) -> anyhow::Result<Vec<u8>> {
+    let url = "http://localhost:8080/events/export";
+    let client = reqwest::Client::new();
+    let mut request = client.get(url);
+    if let Some(event_type) = event_type {
+        request = request.query(&[("type", event_type)]);
+    }
+    if let Some(limit) = limit {
+        request = request.query(&[("limit", limit)]);
+    }
+    let response = request.send().await?;
+    
+    if !response.status().is_success() {
+        anyhow::bail!("Server returned status: {}", response.status());
+    }
+    
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// Upload an NDJSON archive, gzipped or not
+async fn import_events(archive: Vec<u8>) -> anyhow::Result<serde_json::Value> {
+    let url = "http://localhost:8080/events/import";
+    let client = reqwest::Client::new();
+    let response = client
+        .post(url)
+        .header("Content-Type", ndjson::NDJSON_CONTENT_TYPE)
+        .body(archive)
+        .send()
+        .await?;
+    
+    if !response.status().is_success() {
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        match body["error"]["message"].as_str() {
+            Some(message) => anyhow::bail!("Server returned status {}: {}", status, message),
+            None => anyhow::bail!("Server returned status: {}", status),
+        }
+    }
+    
+    let data = response.json().await?;
+    Ok(data)
+}
+
 /// Get system metrics
 async fn get_metrics() -> anyhow::Result<serde_json::Value> {
     let url = "http://localhost:8080/metrics";
@@ -71,12 +141,48 @@ enum Commands {
         /// Enable verbose logging
         #[arg(short, long)]
         verbose: bool,
+        
+        /// Only accept and store events; `nexus worker` processes run the functions
+        #[arg(long)]
+        ingest_only: bool,
+    },
+    
+    /// Run functions from JetStream, sharing the work with other worker replicas
+    Worker {
+        /// Port for the health and metrics endpoints
+        #[arg(short, long, default_value = "8081")]
+        port: u16,
+        
+        /// Path to nexus.yaml configuration
+        #[arg(short, long, default_value = "nexus.yaml")]
+        config: String,
+        
+        /// Enable verbose logging
+        #[arg(short, long)]
+        verbose: bool,
+        
+        /// Consumer partitions this replica runs, e.g. `0,1` (default: all of them)
+        #[arg(long, value_delimiter = ',', conflicts_with = "replica")]
+        partitions: Vec<u32>,
+        
+        /// Ordinal or StatefulSet pod name of this replica, e.g. `nexus-worker-1`;
+        /// runs every `--replicas`-th partition starting at the ordinal
+        #[arg(long, requires = "replicas")]
+        replica: Option<String>,
+        
+        /// Number of worker replicas sharing the partitions
+        #[arg(long, requires = "replica")]
+        replicas: Option<u32>,
     },
     
     /// Replay an event by ID
     Replay {
         /// Event ID to replay
         event_id: String,
+        
+        /// Replay to this function only, without re-publishing the event
+        #[arg(short, long)]
+        function: Option<String>,
     },
     
     /// Create a new function from template
@@ -89,8 +195,11 @@ enum Commands {
         lang: String,
     },
     
-    /// View recent events
+    /// View recent events, or export and import them
     Events {
+        #[command(subcommand)]
+        action: Option<EventsAction>,
+        
         /// Event ID to get (optional)
         event_id: Option<String>,
         
@@ -102,6 +211,29 @@ enum Commands {
     /// View system metrics
     Metrics,
     
+    /// Move events stored under old underscore subjects to dotted subjects
+    MigrateSubjects {
+        /// Path to nexus.yaml configuration
+        #[arg(short, long, default_value = "nexus.yaml")]
+        config: String,
+    },
+    
+    /// Manage the keyring used to encrypt event data
+    Keys {
+        #[command(subcommand)]
+        action: KeysAction,
+        
+        /// Keyring file
+        #[arg(short, long, default_value = ".nexus/keyring.json", global = true)]
+        keyring: String,
+    },
+    
+    /// Try webhook transforms and provider adapters
+    Transform {
+        #[command(subcommand)]
+        action: TransformAction,
+    },
+    
     /// View function logs
     Logs {
         /// Function name
@@ -113,12 +245,73 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum KeysAction {
+    /// Create a keyring with one key
+    Init,
+    
+    /// Add a key and encrypt new events with it; older keys stay for decryption
+    Rotate,
+    
+    /// List key IDs
+    List,
+}
+
+#[derive(Subcommand)]
+enum TransformAction {
+    /// Print the events a webhook path would publish for sample payloads, without a server
+    Test {
+        /// Webhook path, as in /webhook/<path>
+        path: String,
+        
+        /// JSON payload files
+        #[arg(required = true)]
+        payloads: Vec<String>,
+        
+        /// Request header as `Name: value`, e.g. `X-GitHub-Event: push` (repeatable)
+        #[arg(short = 'H', long = "header")]
+        headers: Vec<String>,
+        
+        /// Path to nexus.yaml configuration
+        #[arg(short, long, default_value = "nexus.yaml")]
+        config: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum EventsAction {
+    /// Export stored events as NDJSON
+    Export {
+        /// File to write; standard output if omitted
+        #[arg(short, long)]
+        output: Option<String>,
+        
+        /// Only export events of this type (patterns with `*` and `>` allowed)
+        #[arg(short = 't', long = "type")]
+        event_type: Option<String>,
+        
+        /// Maximum number of events to export
+        #[arg(short, long)]
+        limit: Option<usize>,
+        
+        /// Compress with gzip (implied by an output file ending in .gz)
+        #[arg(short, long)]
+        gzip: bool,
+    },
+    
+    /// Import events from an NDJSON file (plain or gzipped), skipping IDs already stored
+    Import {
+        /// File to read
+        file: String,
+    },
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Dev { port, config, verbose } => {
+        Commands::Dev { port, config, verbose, ingest_only } => {
             println!("{}", "🚀 Nexus Functions - Development Server".bright_cyan().bold());
             println!();
             
@@ -150,48 +343,47 @@ async fn main() -> anyhow::Result<()> {
                 }
             } else {
                 println!("{} No nexus.yaml found, using default configuration", "⚠".yellow());
-                NexusConfig {
-                    version: "v1".to_string(),
-                    functions: vec![],
-                }
+                NexusConfig::default()
             };
             
-            // Initialize NATS client
-            println!("{} Starting embedded NATS JetStream...", "✓".green());
             let nats_client = Arc::new(RwLock::new(NatsClient::new()));
             
-            // Connect to NATS (embedded mode - will connect to local NATS if available)
-            let nats_url = std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
-            
-            {
+            if nexus_config.events.backend == EventBackendKind::Memory {
+                println!("{} Using in-memory event store (events are lost on restart)", "⚠".yellow());
+            } else if nexus_config.events.backend == EventBackendKind::File {
+                println!("{} Opening embedded event log at {}...", "✓".green(), nexus_config.events.path);
+            } else {
+                // Connect to an external NATS server
+                println!("{} Connecting to NATS JetStream...", "✓".green());
+                
+                let nats_url = std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
+                
                 let mut client = nats_client.write().await;
                 match client.connect_with_retry(&nats_url, 5).await {
                     Ok(_) => {
                         println!("{} Connected to NATS at {}", "✓".green(), nats_url);
                         
-                        // Create default stream
-                        if let Err(e) = client.create_stream("events").await {
-                            println!("{} Warning: Failed to create stream: {}", "⚠".yellow(), e);
-                            println!("{} Event replay may not be available", "⚠".yellow());
-                        } else {
-                            println!("{} JetStream stream 'events' ready", "✓".green());
+                        // Create or update the configured streams
+                        for stream in nexus_config.all_streams() {
+                            if let Err(e) = client.create_stream(&stream).await {
+                                println!("{} Warning: Failed to set up stream '{}': {}", "⚠".yellow(), stream.name, e);
+                                println!("{} Event replay may not be available", "⚠".yellow());
+                            } else {
+                                println!("{} JetStream stream '{}' ready", "✓".green(), stream.name);
+                            }
                         }
                     }
                     Err(e) => {
                         println!("{} Could not connect to NATS: {}", "⚠".yellow(), e);
-                        println!("{} Continuing without event persistence (events will not be replayed)", "⚠".yellow());
+                        if nexus_config.events.backend == EventBackendKind::Auto {
+                            println!("{} Continuing with in-memory event store (events are lost on restart)", "⚠".yellow());
+                        }
                     }
                 }
             }
             
-            println!("{} Serving HTTP on http://localhost:{}...", "✓".green(), port);
-            println!();
-            println!("{}", "Ready to receive events! 🎉".bright_green());
-            println!("Press Ctrl+C to stop");
-            println!();
-            
             // Create application state
-            let app_state = match AppState::new(nexus_config, nats_client) {
+            let app_state = match AppState::new(nexus_config, nats_client).await {
                 Ok(state) => state,
                 Err(e) => {
                     eprintln!("{} Failed to create application state: {}", "✗".red(), e);
@@ -199,6 +391,36 @@ async fn main() -> anyhow::Result<()> {
                 }
             };
             
+            println!("{} Event backend: {}", "✓".green(), app_state.event_backend.name());
+            
+            if ingest_only && app_state.dispatch != Dispatch::Durable {
+                eprintln!("{} --ingest-only requires the JetStream event backend", "✗".red());
+                std::process::exit(1);
+            }
+            
+            // Functions run from durable consumers when events are stored in JetStream
+            if ingest_only {
+                println!("{} Ingest-only mode: functions are run by `nexus worker`", "✓".green());
+            } else if app_state.dispatch == Dispatch::Durable {
+                match spawn_function_workers(&app_state, None).await {
+                    Ok(workers) => println!("{} Started {} durable function worker(s)", "✓".green(), workers.len()),
+                    Err(e) => {
+                        eprintln!("{} Failed to start function workers: {}", "✗".red(), e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            
+            if spawn_archiver(&app_state).is_some() {
+                println!("{} Archiving aged events to {}", "✓".green(), app_state.config.archive.path);
+            }
+            
+            println!("{} Serving HTTP on http://localhost:{}...", "✓".green(), port);
+            println!();
+            println!("{}", "Ready to receive events! 🎉".bright_green());
+            println!("Press Ctrl+C to stop");
+            println!();
+            
             // Start the server
             let server = Server::new(port, app_state);
             
@@ -216,10 +438,96 @@ async fn main() -> anyhow::Result<()> {
             }
         }
         
-        Commands::Replay { event_id } => {
-            println!("{} Replaying event {}...", "⟳".cyan(), event_id);
-            // TODO: Implement replay logic
-            println!("{} Replay not yet implemented", "⚠".yellow());
+        Commands::Worker { port, config, verbose, partitions, replica, replicas } => {
+            println!("{}", "🚀 Nexus Functions - Worker".bright_cyan().bold());
+            println!();
+            
+            if verbose {
+                std::env::set_var("RUST_LOG", "debug");
+            } else {
+                std::env::set_var("RUST_LOG", "info");
+            }
+            
+            nexus_observability::init_tracing()?;
+            
+            let config_path = Path::new(&config);
+            let nexus_config = if config_path.exists() {
+                println!("{} Loading configuration from {}...", "✓".green(), config);
+                NexusConfig::from_file(config_path)?
+            } else {
+                println!("{} No nexus.yaml found, using default configuration", "⚠".yellow());
+                NexusConfig::default()
+            };
+            let stream = nexus_config.event_stream();
+            let partitions = match (replica, replicas) {
+                (Some(replica), Some(replicas)) => {
+                    let ordinal = replica_ordinal(&replica)
+                        .ok_or_else(|| anyhow::anyhow!("Cannot read a replica ordinal from '{}'", replica))?;
+                    replica_partitions(ordinal, replicas, nexus_config.consumers.partitions)?
+                }
+                _ => partitions,
+            };
+            
+            // Workers always consume from JetStream
+            let nats_url = std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
+            let nats_client = Arc::new(RwLock::new(NatsClient::new()));
+            {
+                let mut client = nats_client.write().await;
+                client.connect_with_retry(&nats_url, 5).await?;
+                println!("{} Connected to NATS at {}", "✓".green(), nats_url);
+                
+                for stream in nexus_config.all_streams() {
+                    client.create_stream(&stream).await?;
+                }
+            }
+            
+            let backend = Arc::new(
+                JetStreamBackend::new(nats_client.clone(), stream.name.clone())
+                    .with_format(stream.format)
+                    .with_partitions(nexus_config.consumers.partitions),
+            );
+            let app_state = AppState::with_backend(nexus_config, nats_client, backend)?;
+            
+            // Replicas bind the same durable consumers, so JetStream splits events between
+            // them; replicas given disjoint partitions also keep each key on one replica
+            let workers = spawn_function_workers(&app_state, Some(&partitions)).await?;
+            println!("{} Started {} function worker(s) on stream '{}'", "✓".green(), workers.len(), stream.name);
+            println!("{} Health endpoint on http://localhost:{}/health", "✓".green(), port);
+            println!("Press Ctrl+C to stop");
+            println!();
+            
+            let server = Server::new(port, app_state);
+            
+            tokio::select! {
+                result = server.run_health_only() => {
+                    if let Err(e) = result {
+                        eprintln!("{} Server error: {}", "✗".red(), e);
+                        std::process::exit(1);
+                    }
+                }
+                _ = tokio::signal::ctrl_c() => {
+                    println!();
+                    println!("{} Shutting down gracefully...", "✓".yellow());
+                }
+            }
+        }
+        
+        Commands::Replay { event_id, function } => {
+            match &function {
+                Some(name) => println!("{} Replaying event {} to function {}...", "⟳".cyan(), event_id, name),
+                None => println!("{} Replaying event {}...", "⟳".cyan(), event_id),
+            }
+            
+            match replay_event(&event_id, function.as_deref()).await {
+                Ok(result) => {
+                    println!("{} {}", "✓".green(), result["message"].as_str().unwrap_or("Event replayed"));
+                }
+                Err(e) => {
+                    eprintln!("{} Failed to replay event: {}", "✗".red(), e);
+                    eprintln!("{} Make sure the server is running and the event ID is correct", "💡".yellow());
+                    std::process::exit(1);
+                }
+            }
         }
         
         Commands::New { name, lang } => {
@@ -228,7 +536,64 @@ async fn main() -> anyhow::Result<()> {
             println!("{} Template generation not yet implemented", "⚠".yellow());
         }
         
-        Commands::Events { event_id, limit } => {
+        Commands::Events { action: Some(EventsAction::Export { output, event_type, limit, gzip }), .. } => {
+            let gzip = gzip || output.as_deref().is_some_and(|path| path.ends_with(".gz"));
+            
+            let archive = match export_events(event_type.as_deref(), limit).await {
+                Ok(archive) => archive,
+                Err(e) => {
+                    eprintln!("{} Failed to export events: {}", "✗".red(), e);
+                    eprintln!("{} Make sure the server is running on http://localhost:8080", "💡".yellow());
+                    std::process::exit(1);
+                }
+            };
+            let count = archive.iter().filter(|b| **b == b'\n').count();
+            let archive = if gzip { ndjson::gzip(&archive)? } else { archive };
+            
+            // Status goes to stderr so the archive can be piped
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, &archive)?;
+                    eprintln!("{} Exported {} event(s) to {}", "✓".green(), count, path);
+                }
+                None => {
+                    std::io::stdout().write_all(&archive)?;
+                    eprintln!("{} Exported {} event(s)", "✓".green(), count);
+                }
+            }
+        }
+        
+        Commands::Events { action: Some(EventsAction::Import { file }), .. } => {
+            let archive = std::fs::read(&file)?;
+            
+            // Validate locally first for a readable error with the line number
+            let events = match ndjson::decode(&archive) {
+                Ok(events) => events,
+                Err(e) => {
+                    eprintln!("{} {} is not a valid event archive: {:#}", "✗".red(), file, e);
+                    std::process::exit(1);
+                }
+            };
+            println!("{} Importing {} event(s) from {}...", "⟳".cyan(), events.len(), file);
+            
+            match import_events(archive).await {
+                Ok(result) => {
+                    println!(
+                        "{} Imported {} event(s), skipped {} already stored",
+                        "✓".green(),
+                        result["imported"].as_u64().unwrap_or(0),
+                        result["skipped"].as_u64().unwrap_or(0)
+                    );
+                }
+                Err(e) => {
+                    eprintln!("{} Failed to import events: {}", "✗".red(), e);
+                    eprintln!("{} Make sure the server is running on http://localhost:8080", "💡".yellow());
+                    std::process::exit(1);
+                }
+            }
+        }
+        
+        Commands::Events { action: None, event_id, limit } => {
             // If event ID is provided, get that specific event
             if let Some(id) = event_id {
                 println!("{} Fetching event {}...", "📋".cyan(), id);
@@ -325,7 +690,7 @@ async fn main() -> anyhow::Result<()> {
                         println!("  Succeeded:    {}", functions["succeeded"].as_u64().unwrap_or(0));
                         println!("  Failed:       {}", functions["failed"].as_u64().unwrap_or(0).to_string().bright_red());
                         println!("  Success Rate: {}%", format!("{:.2}", functions["success_rate"].as_f64().unwrap_or(0.0)).bright_green());
-                        println!("  Avg Time:     {}ms", format!("{:.2}", functions["avg_execution_time_ms"].as_f64().unwrap_or(0.0)));
+                        println!("  Avg Time:     {:.2}ms", functions["avg_execution_time_ms"].as_f64().unwrap_or(0.0));
                         println!();
                     }
                     
@@ -350,6 +715,106 @@ async fn main() -> anyhow::Result<()> {
             }
         }
         
+        Commands::MigrateSubjects { config } => {
+            let config_path = Path::new(&config);
+            let nexus_config = if config_path.exists() {
+                NexusConfig::from_file(config_path)?
+            } else {
+                NexusConfig::default()
+            };
+            let stream = nexus_config.event_stream();
+            
+            let nats_url = std::env::var("NATS_URL").unwrap_or_else(|_| "nats://localhost:4222".to_string());
+            let nats_client = Arc::new(RwLock::new(NatsClient::new()));
+            {
+                let mut client = nats_client.write().await;
+                client.connect_with_retry(&nats_url, 5).await?;
+                println!("{} Connected to NATS at {}", "✓".green(), nats_url);
+                
+                // Widen the stream subjects to `events.>` before re-publishing
+                client.create_stream(&stream).await?;
+            }
+            
+            println!("{} Migrating events in stream '{}'...", "⟳".cyan(), stream.name);
+            let backend = JetStreamBackend::new(nats_client, stream.name.clone()).with_partitions(nexus_config.consumers.partitions);
+            let migrated = backend.migrate_legacy_subjects().await?;
+            println!("{} Migrated {} event(s) to dotted subjects", "✓".green(), migrated);
+        }
+        
+        Commands::Keys { action, keyring } => match action {
+            KeysAction::Init => {
+                if Path::new(&keyring).exists() {
+                    eprintln!("{} {} already exists; use `nexus keys rotate` to add a key", "✗".red(), keyring);
+                    std::process::exit(1);
+                }
+                let ring = Keyring::generate();
+                ring.save(&keyring)?;
+                println!("{} Created keyring {} with key {}", "✓".green(), keyring, ring.active_key_id());
+            }
+            KeysAction::Rotate => {
+                let mut ring = Keyring::load(&keyring)?;
+                let id = ring.rotate();
+                ring.save(&keyring)?;
+                println!("{} New events are encrypted with key {}", "✓".green(), id);
+                println!("{} Restart running servers and workers to pick up the key", "💡".yellow());
+            }
+            KeysAction::List => {
+                let ring = Keyring::load(&keyring)?;
+                for id in ring.key_ids() {
+                    let marker = if id == ring.active_key_id() { " (active)" } else { "" };
+                    println!("  {} {}{}", "→".cyan(), id, marker);
+                }
+            }
+        },
+        
+        Commands::Transform { action: TransformAction::Test { path, payloads, headers, config } } => {
+            let nexus_config = NexusConfig::from_file(&config)?;
+            // Signatures are not checked here, so their secrets need not be set
+            let mut webhooks = nexus_config.webhooks.clone();
+            for webhook in &mut webhooks {
+                webhook.signature = None;
+            }
+            let handler = WebhookHandler::new().with_webhooks(webhooks)?;
+            let schemas = SchemaRegistry::from_config(&nexus_config.schemas)?;
+            
+            let mut header_map = HeaderMap::new();
+            header_map.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            for raw in &headers {
+                let Some((name, value)) = raw.split_once(':') else {
+                    anyhow::bail!("Invalid header '{}', expected 'Name: value'", raw);
+                };
+                header_map.insert(HeaderName::try_from(name.trim())?, HeaderValue::from_str(value.trim())?);
+            }
+            
+            let mut failed = 0;
+            for payload in &payloads {
+                let body = Bytes::from(std::fs::read(payload)?);
+                let result = handler.to_cloud_events(&path, &header_map, &body).map_err(anyhow::Error::from).and_then(|mut events| {
+                    for event in &mut events {
+                        schemas.validate(event)?;
+                    }
+                    Ok(events)
+                });
+                match result {
+                    Ok(events) => {
+                        println!("{} {}", "✓".green(), payload);
+                        for event in events {
+                            println!("{}", serde_json::to_string_pretty(&event)?);
+                        }
+                    }
+                    Err(e) => {
+                        failed += 1;
+                        println!("{} {}: {}", "✗".red(), payload, e);
+                    }
+                }
+            }
+            
+            if failed > 0 {
+                eprintln!("{} {} of {} payload(s) failed", "✗".red(), failed, payloads.len());
+                std::process::exit(1);
+            }
+        }
+        
         Commands::Logs { function, follow } => {
             println!("{} Viewing logs for function: {}...", "📜".cyan(), function);
             if follow {
@@ -0,0 +1,28 @@
+use crate::state::AppState;
+use chrono::Utc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Start the task copying aged events to the archive, if the archive is enabled
+///
+/// Run it in one process per event stream; archivers in several processes
+/// would write the same events to separate segments.
+pub fn spawn_archiver(state: &AppState) -> Option<JoinHandle<()>> {
+    let archive = state.archive.clone()?;
+    let backend = state.event_backend.clone();
+    let config = state.config.archive.clone();
+    let after = chrono::Duration::from_std(config.after).unwrap_or(chrono::Duration::MAX);
+
+    info!(path = %config.path, "Archiving events older than {:?}", config.after);
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval);
+        loop {
+            interval.tick().await;
+
+            let cutoff = Utc::now().checked_sub_signed(after).unwrap_or(chrono::DateTime::<Utc>::MIN_UTC);
+            if let Err(e) = archive.archive_aged(backend.as_ref(), cutoff).await {
+                warn!(error = %e, "Failed to archive events");
+            }
+        }
+    }))
+}
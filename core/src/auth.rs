@@ -0,0 +1,368 @@
+use crate::errors::NexusError;
+use crate::ratelimit::RateLimit;
+use anyhow::{Context, Result};
+use axum::body::Body;
+use axum::extract::{MatchedPath, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use nexus_api_gateway::WebhookHandler;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::info;
+
+/// Header carrying an API key, as an alternative to `Authorization: Bearer`
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Permission needed for a group of routes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Publish events
+    Ingest,
+    /// Read events, schemas and metrics
+    Read,
+    /// Replay and execute stored events
+    Replay,
+    /// Everything, including imports, schema changes and erasure
+    Admin,
+}
+
+impl Scope {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "ingest" => Some(Scope::Ingest),
+            "read" => Some(Scope::Read),
+            "replay" => Some(Scope::Replay),
+            "admin" => Some(Scope::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// Authentication settings, as configured in the `auth` section of nexus.yaml
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwt: Option<JwtConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyConfig {
+    /// Name of the key's owner, used in logs
+    pub name: String,
+    pub key: String,
+    pub scopes: Vec<Scope>,
+    /// Request rate of this key, instead of `rate_limits.per_credential`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    /// Ingestion requests allowed per UTC day
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_quota: Option<u64>,
+}
+
+/// Accepted JWTs; scopes come from the `scope` (space separated) or `scopes` (array) claim
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JwtConfig {
+    /// Shared secret for HS256 tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hs256_secret: Option<String>,
+    /// Local JWKS file with the public keys for RS256 tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwks: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audience: Option<String>,
+}
+
+/// The authenticated caller
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub name: String,
+    pub scopes: HashSet<Scope>,
+    pub rate_limit: Option<RateLimit>,
+    pub daily_quota: Option<u64>,
+}
+
+impl Principal {
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.contains(&Scope::Admin) || self.scopes.contains(&scope)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("Missing credentials; send an API key or a bearer token")]
+    MissingCredentials,
+    #[error("Invalid API key")]
+    InvalidApiKey,
+    #[error("Invalid token: {0}")]
+    InvalidToken(String),
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    sub: Option<String>,
+    scope: Option<String>,
+    #[serde(default)]
+    scopes: Vec<String>,
+}
+
+/// Checks API keys and JWTs against the configuration
+pub struct Authenticator {
+    api_keys: Vec<ApiKeyConfig>,
+    hs256: Option<DecodingKey>,
+    jwks: Vec<(Option<String>, DecodingKey)>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    signed_webhooks: Option<Arc<WebhookHandler>>,
+}
+
+impl Authenticator {
+    pub fn from_config(config: &AuthConfig) -> Result<Self> {
+        let jwt = config.jwt.clone().unwrap_or_default();
+        let jwks = match &jwt.jwks {
+            Some(path) => {
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read JWKS file {}", path))?;
+                let set: JwkSet = serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse JWKS file {}", path))?;
+                set.keys
+                    .iter()
+                    .map(|jwk| Ok((jwk.common.key_id.clone(), DecodingKey::from_jwk(jwk)?)))
+                    .collect::<Result<Vec<_>>>()
+                    .with_context(|| format!("Unsupported key in JWKS file {}", path))?
+            }
+            None => vec![],
+        };
+
+        Ok(Self {
+            api_keys: config.api_keys.clone(),
+            hs256: jwt.hs256_secret.as_deref().map(|secret| DecodingKey::from_secret(secret.as_bytes())),
+            jwks,
+            issuer: jwt.issuer,
+            audience: jwt.audience,
+            signed_webhooks: None,
+        })
+    }
+
+    /// Let webhook paths with a configured secret through without credentials;
+    /// their signature is checked by the webhook handler instead
+    pub fn with_signed_webhooks(mut self, webhooks: Arc<WebhookHandler>) -> Self {
+        self.signed_webhooks = Some(webhooks);
+        self
+    }
+
+    fn is_signed_webhook(&self, path: &str) -> bool {
+        let Some(webhooks) = &self.signed_webhooks else {
+            return false;
+        };
+        path.strip_prefix("/webhook/").is_some_and(|path| webhooks.is_signed(path))
+    }
+
+    /// Identify the caller from `Authorization: Bearer` or `X-API-Key`
+    pub fn authenticate(&self, headers: &HeaderMap) -> Result<Principal, AuthError> {
+        let bearer = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        let api_key = headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok());
+
+        match (bearer, api_key) {
+            (Some(token), _) if token.matches('.').count() == 2 => self.verify_jwt(token),
+            (Some(key), _) | (None, Some(key)) => self.verify_api_key(key),
+            (None, None) => Err(AuthError::MissingCredentials),
+        }
+    }
+
+    fn verify_api_key(&self, key: &str) -> Result<Principal, AuthError> {
+        self.api_keys
+            .iter()
+            .find(|candidate| constant_time_eq(candidate.key.as_bytes(), key.as_bytes()))
+            .map(|candidate| Principal {
+                name: candidate.name.clone(),
+                scopes: candidate.scopes.iter().copied().collect(),
+                rate_limit: candidate.rate_limit,
+                daily_quota: candidate.daily_quota,
+            })
+            .ok_or(AuthError::InvalidApiKey)
+    }
+
+    fn verify_jwt(&self, token: &str) -> Result<Principal, AuthError> {
+        let invalid = |e: jsonwebtoken::errors::Error| AuthError::InvalidToken(e.to_string());
+        let header = jsonwebtoken::decode_header(token).map_err(invalid)?;
+
+        let key = match header.alg {
+            Algorithm::HS256 => self.hs256.as_ref(),
+            Algorithm::RS256 => self
+                .jwks
+                .iter()
+                .find(|(kid, _)| header.kid.is_none() || kid == &header.kid)
+                .map(|(_, key)| key),
+            other => return Err(AuthError::InvalidToken(format!("unsupported algorithm {:?}", other))),
+        }
+        .ok_or_else(|| AuthError::InvalidToken("no key configured for this token".to_string()))?;
+
+        let mut validation = Validation::new(header.alg);
+        match &self.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+
+        let claims = jsonwebtoken::decode::<Claims>(token, key, &validation).map_err(invalid)?.claims;
+        let scopes = claims
+            .scope
+            .as_deref()
+            .unwrap_or_default()
+            .split_whitespace()
+            .chain(claims.scopes.iter().map(String::as_str))
+            .filter_map(Scope::parse)
+            .collect();
+        Ok(Principal {
+            name: claims.sub.unwrap_or_else(|| "jwt".to_string()),
+            scopes,
+            rate_limit: None,
+            daily_quota: None,
+        })
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Scope needed for a route, `None` for public routes
+pub fn required_scope(method: &Method, path: &str) -> Option<Scope> {
+    match (method, path) {
+        (_, "/health") => None,
+        (&Method::POST, "/events" | "/webhook/*path") => Some(Scope::Ingest),
+        (&Method::POST, "/replay/:event_id" | "/execute/:event_id") => Some(Scope::Replay),
+        (&Method::GET, "/metrics" | "/events" | "/events/export" | "/events/:event_id" | "/schemas/:event_type") => {
+            Some(Scope::Read)
+        }
+        _ => Some(Scope::Admin),
+    }
+}
+
+/// Middleware rejecting requests without the scope their route needs
+///
+/// Install with `route_layer`, so the matched route is known. The principal
+/// is added to the request extensions.
+pub async fn authorize(
+    State(authenticator): State<Arc<Authenticator>>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let Some(scope) = required_scope(request.method(), &path) else {
+        return next.run(request).await;
+    };
+    if authenticator.is_signed_webhook(request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let principal = match authenticator.authenticate(request.headers()) {
+        Ok(principal) => principal,
+        Err(e) => {
+            info!(path = %path, error = %e, "Rejected unauthenticated request");
+            let mut response = NexusError::Unauthorized { message: e.to_string() }.into_response();
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            return response;
+        }
+    };
+
+    if !principal.allows(scope) {
+        info!(path = %path, principal = %principal.name, ?scope, "Rejected request lacking scope");
+        return NexusError::Forbidden {
+            message: format!("'{}' lacks the {:?} scope", principal.name, scope).to_lowercase(),
+        }
+        .into_response();
+    }
+
+    request.extensions_mut().insert(principal);
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    fn authenticator() -> Authenticator {
+        Authenticator::from_config(&AuthConfig {
+            enabled: true,
+            api_keys: vec![ApiKeyConfig {
+                name: "ci".to_string(),
+                key: "secret-key".to_string(),
+                scopes: vec![Scope::Ingest],
+                rate_limit: None,
+                daily_quota: None,
+            }],
+            jwt: Some(JwtConfig {
+                hs256_secret: Some("jwt-secret".to_string()),
+                issuer: Some("nexus-test".to_string()),
+                ..Default::default()
+            }),
+        })
+        .unwrap()
+    }
+
+    fn headers(name: header::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_api_keys_and_jwts() {
+        let auth = authenticator();
+
+        let ci = auth.authenticate(&headers(API_KEY_HEADER.parse().unwrap(), "secret-key")).unwrap();
+        assert!(ci.allows(Scope::Ingest));
+        assert!(!ci.allows(Scope::Replay));
+        assert!(matches!(
+            auth.authenticate(&headers(header::AUTHORIZATION, "Bearer wrong")),
+            Err(AuthError::InvalidApiKey)
+        ));
+        assert!(matches!(auth.authenticate(&HeaderMap::new()), Err(AuthError::MissingCredentials)));
+
+        let claims = serde_json::json!({"sub": "ops", "iss": "nexus-test", "exp": 4102444800u64, "scope": "read replay"});
+        let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(b"jwt-secret")).unwrap();
+        let ops = auth.authenticate(&headers(header::AUTHORIZATION, &format!("Bearer {}", token))).unwrap();
+        assert_eq!(ops.name, "ops");
+        assert!(ops.allows(Scope::Replay) && !ops.allows(Scope::Ingest));
+
+        let forged = encode(&Header::default(), &claims, &EncodingKey::from_secret(b"other")).unwrap();
+        assert!(matches!(
+            auth.authenticate(&headers(header::AUTHORIZATION, &format!("Bearer {}", forged))),
+            Err(AuthError::InvalidToken(_))
+        ));
+    }
+
+    #[test]
+    fn test_required_scope() {
+        assert_eq!(required_scope(&Method::GET, "/health"), None);
+        assert_eq!(required_scope(&Method::POST, "/webhook/*path"), Some(Scope::Ingest));
+        assert_eq!(required_scope(&Method::GET, "/events"), Some(Scope::Read));
+        assert_eq!(required_scope(&Method::POST, "/replay/:event_id"), Some(Scope::Replay));
+        assert_eq!(required_scope(&Method::POST, "/events/import"), Some(Scope::Admin));
+        assert_eq!(required_scope(&Method::DELETE, "/subjects/:subject"), Some(Scope::Admin));
+    }
+}
@@ -0,0 +1,167 @@
+use crate::errors::NexusError;
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use http_body_util::Limited;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+/// Body limit of routes without one of their own, as in axum
+pub const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Body limit of `POST /events/import` unless configured otherwise
+pub const IMPORT_BODY_LIMIT: usize = 256 * 1024 * 1024;
+
+/// Maximum request body sizes, as configured in the `body_limits` section of nexus.yaml
+///
+/// Sizes are byte counts or strings such as `512KB`, `10MB` or `1GiB`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodyLimitConfig {
+    #[serde(default = "default_body_limit", deserialize_with = "deserialize_size")]
+    pub default: usize,
+    /// Limits of request path prefixes; the longest match applies
+    #[serde(default)]
+    pub routes: Vec<RouteBodyLimit>,
+}
+
+impl Default for BodyLimitConfig {
+    fn default() -> Self {
+        Self {
+            default: DEFAULT_BODY_LIMIT,
+            routes: vec![],
+        }
+    }
+}
+
+fn default_body_limit() -> usize {
+    DEFAULT_BODY_LIMIT
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteBodyLimit {
+    /// Request path prefix, e.g. `/webhook/github`
+    pub path: String,
+    #[serde(deserialize_with = "deserialize_size")]
+    pub max: usize,
+}
+
+/// Parse a size such as `1048576`, `512KB`, `10MB` or `1GiB` (units are powers of 1024)
+pub fn parse_size(value: &str) -> anyhow::Result<usize> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: usize = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid size '{}'", value))?;
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "KB" | "KIB" => 1024,
+        "MB" | "MIB" => 1024 * 1024,
+        "GB" | "GIB" => 1024 * 1024 * 1024,
+        _ => anyhow::bail!("Invalid size unit in '{}'", value),
+    };
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| anyhow::anyhow!("Size '{}' is too large", value))
+}
+
+fn deserialize_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(usize),
+        Text(String),
+    }
+    match Size::deserialize(deserializer)? {
+        Size::Bytes(bytes) => Ok(bytes),
+        Size::Text(text) => parse_size(&text).map_err(serde::de::Error::custom),
+    }
+}
+
+/// The limit a request body was read under, for rejection messages
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimit(pub usize);
+
+/// Body limits by path
+pub struct BodyLimits {
+    config: BodyLimitConfig,
+}
+
+impl BodyLimits {
+    pub fn new(config: BodyLimitConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn limit_for(&self, path: &str) -> usize {
+        let configured = self
+            .config
+            .routes
+            .iter()
+            .filter(|route| {
+                let own = route.path.trim_end_matches('/');
+                path == own || path.strip_prefix(own).is_some_and(|rest| rest.starts_with('/'))
+            })
+            .max_by_key(|route| route.path.len())
+            .map(|route| route.max);
+        match configured {
+            Some(limit) => limit,
+            None if path == "/events/import" => IMPORT_BODY_LIMIT,
+            None => self.config.default,
+        }
+    }
+}
+
+/// Middleware capping request bodies at the limit of their path
+///
+/// Requests declaring a larger `Content-Length` are rejected up front;
+/// others fail when the extractor reads past the limit. Install it with
+/// axum's own limit disabled (`DefaultBodyLimit::disable()`).
+pub async fn limit_body(State(limits): State<Arc<BodyLimits>>, request: Request, next: Next) -> Response {
+    let limit = limits.limit_for(request.uri().path());
+
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if let Some(length) = declared.filter(|length| *length > limit) {
+        info!(path = %request.uri().path(), length, limit, "Rejected oversized request body");
+        return NexusError::InvalidInput {
+            field: "body".to_string(),
+            message: format!("Request body of {} bytes exceeds the limit of {} bytes", length, limit),
+        }
+        .into_response();
+    }
+
+    let (mut parts, body) = request.into_parts();
+    parts.extensions.insert(BodyLimit(limit));
+    next.run(Request::from_parts(parts, Body::new(Limited::new(body, limit)))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizes_and_route_limits() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("10MB").unwrap(), 10 * 1024 * 1024);
+        assert_eq!(parse_size("1 GiB").unwrap(), 1024 * 1024 * 1024);
+        assert!(parse_size("10XB").is_err());
+
+        let config: BodyLimitConfig = serde_json::from_value(serde_json::json!({
+            "default": "64KB",
+            "routes": [{"path": "/webhook", "max": "1MB"}, {"path": "/webhook/github", "max": 26214400}]
+        }))
+        .unwrap();
+        let limits = BodyLimits::new(config);
+        assert_eq!(limits.limit_for("/events"), 64 * 1024);
+        assert_eq!(limits.limit_for("/webhook/stripe"), 1024 * 1024);
+        assert_eq!(limits.limit_for("/webhook/github/push"), 25 * 1024 * 1024);
+        assert_eq!(limits.limit_for("/webhooks"), 64 * 1024);
+        assert_eq!(limits.limit_for("/events/import"), IMPORT_BODY_LIMIT);
+    }
+}
@@ -1,11 +1,109 @@
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use anyhow::{Context, Result};
+use nexus_event_fabric::format::WireFormat;
+use nexus_event_fabric::subject::all_events_filter;
+use crate::auth::AuthConfig;
+use crate::body_limit::BodyLimitConfig;
+use crate::ratelimit::{RateLimit, RateLimitConfig};
+use nexus_api_gateway::{Transform, WebhookConfig};
+use nexus_event_fabric::{ArchiveConfig, ConsumerConfig, EncryptionConfig, StreamConfig, Upcaster, Upcasters};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NexusConfig {
     pub version: String,
     pub functions: Vec<FunctionConfig>,
+    #[serde(default)]
+    pub events: EventsConfig,
+    /// JetStream stream definitions, created or updated at startup
+    #[serde(default)]
+    pub streams: Vec<StreamConfig>,
+    /// Durable consumer settings for function workers
+    #[serde(default)]
+    pub consumers: ConsumerConfig,
+    /// JSON Schemas that ingested event data is validated against
+    #[serde(default)]
+    pub schemas: Vec<SchemaConfig>,
+    /// Steps bringing stored events to the latest schema version on replay and execution
+    #[serde(default)]
+    pub upcasters: Vec<Upcaster>,
+    /// Copies of aged events kept beyond the stream's retention
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+    /// Encryption of event data at rest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<EncryptionConfig>,
+    /// API keys and JWT settings for the HTTP API
+    #[serde(default)]
+    pub auth: AuthConfig,
+    /// Secrets webhook requests must be signed with, per path
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    /// Request rate limits per route and per credential
+    #[serde(default)]
+    pub rate_limits: RateLimitConfig,
+    /// Maximum request body sizes, by default and per route
+    #[serde(default)]
+    pub body_limits: BodyLimitConfig,
+}
+
+impl Default for NexusConfig {
+    fn default() -> Self {
+        Self {
+            version: "v1".to_string(),
+            functions: vec![],
+            events: EventsConfig::default(),
+            streams: vec![],
+            consumers: ConsumerConfig::default(),
+            schemas: vec![],
+            upcasters: vec![],
+            archive: ArchiveConfig::default(),
+            encryption: None,
+            auth: AuthConfig::default(),
+            webhooks: vec![],
+            rate_limits: RateLimitConfig::default(),
+            body_limits: BodyLimitConfig::default(),
+        }
+    }
+}
+
+/// Where published events are stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventsConfig {
+    #[serde(default)]
+    pub backend: EventBackendKind,
+    /// Directory of the embedded event log (`file` backend)
+    #[serde(default = "default_events_path")]
+    pub path: String,
+    /// Stream events are published to
+    #[serde(default = "default_events_stream")]
+    pub stream: String,
+    /// Flush every append to disk before acknowledging it (`file` backend)
+    #[serde(default)]
+    pub fsync: bool,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            backend: EventBackendKind::default(),
+            path: default_events_path(),
+            stream: default_events_stream(),
+            fsync: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventBackendKind {
+    /// JetStream when NATS is connected, in-memory otherwise
+    #[default]
+    Auto,
+    Jetstream,
+    Memory,
+    /// Embedded persistent log on local disk
+    File,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +118,22 @@ pub struct FunctionConfig {
     pub memory: String,
     #[serde(default)]
     pub env: std::collections::HashMap<String, String>,
+    /// What the guest receives on stdin
+    #[serde(default)]
+    pub input: InputMode,
+}
+
+/// How an event is handed to a function
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputMode {
+    /// The whole CloudEvent as JSON
+    #[default]
+    Event,
+    /// Only the event data in its original bytes; attributes are passed as
+    /// `CE_*` environment variables (`CE_TYPE`, `CE_DATACONTENTTYPE`, ...),
+    /// extensions included (`CE_NEXUSREPLAY`)
+    Data,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +142,20 @@ pub struct TriggerConfig {
     pub http: Option<HttpTrigger>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub nats: Option<NatsTrigger>,
+    /// JSON path to the partition key, e.g. `$.data.user_id`; events with the
+    /// same key are executed one at a time, in order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partition_key: Option<String>,
+}
+
+/// A JSON Schema file for the `data` of one event type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaConfig {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    #[serde(default = "default_schema_version")]
+    pub version: u32,
+    pub path: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +177,18 @@ fn default_memory() -> String {
     "128Mi".to_string()
 }
 
+fn default_schema_version() -> u32 {
+    1
+}
+
+fn default_events_path() -> String {
+    ".nexus/events".to_string()
+}
+
+fn default_events_stream() -> String {
+    "events".to_string()
+}
+
 impl NexusConfig {
     /// Load configuration from a YAML file
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
@@ -59,6 +199,7 @@ impl NexusConfig {
     }
     
     /// Parse configuration from a YAML string
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(content: &str) -> Result<Self> {
         let config: NexusConfig = serde_yaml::from_str(content)
             .context("Failed to parse configuration YAML")?;
@@ -87,8 +228,161 @@ impl NexusConfig {
             func.validate()?;
         }
         
+        // Validate streams
+        let mut stream_names = std::collections::HashSet::new();
+        for stream in &self.streams {
+            if !stream_names.insert(&stream.name) {
+                anyhow::bail!("Duplicate stream name: {}", stream.name);
+            }
+            stream.validate()?;
+        }
+        
+        // Validate schemas
+        let mut schema_versions = std::collections::HashSet::new();
+        for schema in &self.schemas {
+            if schema.event_type.is_empty() || schema.path.is_empty() {
+                anyhow::bail!("Schemas need a non-empty type and path");
+            }
+            if schema.version == 0 {
+                anyhow::bail!("Schema for '{}' has version 0, versions start at 1", schema.event_type);
+            }
+            if !schema_versions.insert((&schema.event_type, schema.version)) {
+                anyhow::bail!("Duplicate schema: {} version {}", schema.event_type, schema.version);
+            }
+        }
+        
+        Upcasters::new(self.upcasters.clone())?;
+        
+        if let Some(encryption) = &self.encryption {
+            if encryption.keyring.is_empty() {
+                anyhow::bail!("Encryption needs a keyring path");
+            }
+            if encryption.rules.iter().any(|r| {
+                r.event_type.is_empty()
+                    || r.paths.iter().any(|p| p.is_empty())
+                    || r.subject_key.as_deref().is_some_and(str::is_empty)
+            }) {
+                anyhow::bail!("Encryption rules need a type, non-empty paths and a non-empty subject_key");
+            }
+        }
+        
+        if self.auth.enabled {
+            let jwt = self.auth.jwt.as_ref();
+            if self.auth.api_keys.is_empty() && jwt.is_none_or(|j| j.hs256_secret.is_none() && j.jwks.is_none()) {
+                anyhow::bail!("Auth is enabled but no API keys, hs256_secret or jwks are configured");
+            }
+            if self.auth.api_keys.iter().any(|k| k.key.is_empty() || k.scopes.is_empty()) {
+                anyhow::bail!("API keys need a non-empty key and at least one scope");
+            }
+        }
+        
+        let valid_limit = |limit: &RateLimit| limit.rate.is_finite() && limit.rate > 0.0 && limit.burst >= 1;
+        if self.rate_limits.routes.iter().any(|route| !route.path.starts_with('/') || !valid_limit(&route.limit))
+            || self.rate_limits.per_credential.as_ref().is_some_and(|limit| !valid_limit(limit))
+            || self.rate_limits.per_ip.as_ref().is_some_and(|limit| !valid_limit(limit))
+            || self.auth.api_keys.iter().filter_map(|key| key.rate_limit.as_ref()).any(|limit| !valid_limit(limit))
+        {
+            anyhow::bail!("Rate limits need a positive rate and burst, and route paths must start with '/'");
+        }
+        
+        if self.body_limits.default == 0
+            || self.body_limits.routes.iter().any(|route| !route.path.starts_with('/') || route.max == 0)
+        {
+            anyhow::bail!("Body limits must be positive, and route paths must start with '/'");
+        }
+        
+        let mut webhook_paths = std::collections::HashSet::new();
+        for webhook in &self.webhooks {
+            if webhook.path.trim_matches('/').is_empty() {
+                anyhow::bail!("Webhooks need a non-empty path");
+            }
+            if webhook.signature.is_some() && webhook.secret.is_none() && webhook.secret_env.is_none() {
+                anyhow::bail!("Webhook '{}' needs a secret or secret_env", webhook.path);
+            }
+            if webhook.signature.is_none() && webhook.provider.is_none() && webhook.transform.is_none() {
+                anyhow::bail!("Webhook '{}' needs a signature, a provider or a transform", webhook.path);
+            }
+            if let Some(transform) = &webhook.transform {
+                Transform::new(transform).with_context(|| format!("Webhook '{}' has an invalid transform", webhook.path))?;
+            }
+            if !webhook_paths.insert(webhook.path.trim_matches('/')) {
+                anyhow::bail!("Duplicate webhook path: {}", webhook.path);
+            }
+        }
+        
+        if self.consumers.batch_size == 0 || self.consumers.max_deliver == 0 || self.consumers.partitions == 0 {
+            anyhow::bail!("Consumer batch_size, max_deliver and partitions must be non-zero");
+        }
+        // Events are published to one partition subject for all functions
+        if self.consumers.partitions > 1 && self.functions.iter().any(|f| f.on.partition_key != self.functions[0].on.partition_key) {
+            anyhow::bail!("With consumers.partitions above 1, all functions must use the same partition_key");
+        }
+        
+        // Events are published to dotted subjects under `events.`
+        let event_stream = self.event_stream();
+        if let Some(stream) = self.streams.iter().find(|s| s.name != event_stream.name && s.format != WireFormat::Json) {
+            anyhow::bail!(
+                "Stream '{}' sets a format, but events are only stored in the event stream '{}'",
+                stream.name,
+                event_stream.name
+            );
+        }
+        if self.archive.enabled {
+            if self.archive.interval.is_zero() {
+                anyhow::bail!("Archive interval must be non-zero");
+            }
+            // Archive runs must catch events before the stream drops them
+            let max_age = event_stream.max_age;
+            if !max_age.is_zero() && self.archive.after + self.archive.interval >= max_age {
+                anyhow::bail!(
+                    "Archive after + interval must be below the max_age of stream '{}'",
+                    event_stream.name
+                );
+            }
+        }
+        
+        if !event_stream.effective_subjects().contains(&all_events_filter()) {
+            anyhow::bail!(
+                "Stream '{}' must capture '{}' to receive events",
+                event_stream.name,
+                all_events_filter()
+            );
+        }
+        
         Ok(())
     }
+    
+    /// Settings of the stream events are published to
+    pub fn event_stream(&self) -> StreamConfig {
+        let mut stream = self
+            .streams
+            .iter()
+            .find(|s| s.name == self.events.stream)
+            .cloned()
+            .unwrap_or_else(|| StreamConfig::named(&self.events.stream));
+        
+        if stream.subjects.is_empty() {
+            stream.subjects = vec![all_events_filter()];
+        }
+        stream
+    }
+    
+    /// Partition key path events are published to their partition by
+    pub fn publish_partition_key(&self) -> Option<String> {
+        self.functions.first().and_then(|f| f.on.partition_key.clone())
+    }
+
+    /// All streams to create at startup, including the event stream
+    pub fn all_streams(&self) -> Vec<StreamConfig> {
+        let mut streams: Vec<StreamConfig> = self
+            .streams
+            .iter()
+            .filter(|s| s.name != self.events.stream)
+            .cloned()
+            .collect();
+        streams.push(self.event_stream());
+        streams
+    }
 }
 
 impl FunctionConfig {
@@ -117,6 +411,12 @@ impl FunctionConfig {
             );
         }
         
+        if let Some(path) = &self.on.partition_key {
+            if path.trim_start_matches("$.").is_empty() {
+                anyhow::bail!("Function '{}' has an empty partition_key path", self.name);
+            }
+        }
+        
         Ok(())
     }
 }
@@ -156,6 +456,62 @@ functions: []
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_event_backend_selection() {
+        let config = NexusConfig::from_str("version: v1\nfunctions: []\n").unwrap();
+        assert_eq!(config.events.backend, EventBackendKind::Auto);
+
+        let yaml = r#"
+version: v1
+functions: []
+events:
+  backend: memory
+"#;
+        let config = NexusConfig::from_str(yaml).unwrap();
+        assert_eq!(config.events.backend, EventBackendKind::Memory);
+        assert_eq!(config.events.path, ".nexus/events");
+    }
+
+    #[test]
+    fn test_stream_settings() {
+        let yaml = r#"
+version: v1
+functions: []
+streams:
+  - name: events
+    max_messages: 5000
+    max_age: 24h
+    storage: memory
+    discard: new
+"#;
+        let config = NexusConfig::from_str(yaml).unwrap();
+        let stream = config.event_stream();
+        assert_eq!(stream.max_messages, 5000);
+        assert_eq!(stream.max_age, std::time::Duration::from_secs(24 * 60 * 60));
+        assert_eq!(stream.effective_subjects(), vec!["events.>".to_string()]);
+        assert_eq!(stream.replicas, 1);
+        assert_eq!(config.all_streams().len(), 1);
+
+        let default = NexusConfig::default();
+        assert_eq!(default.all_streams()[0].effective_subjects(), vec!["events.>".to_string()]);
+
+        // Only the event stream stores events, so only it has a format
+        let other = "version: v1\nfunctions: []\nstreams:\n  - {name: events, format: avro}\n  - {name: audit, format: protobuf}\n";
+        assert!(NexusConfig::from_str(other).is_err());
+    }
+
+    #[test]
+    fn test_event_stream_must_capture_dotted_subjects() {
+        let yaml = r#"
+version: v1
+functions: []
+streams:
+  - name: events
+    subjects: ["events.*"]
+"#;
+        assert!(NexusConfig::from_str(yaml).is_err());
+    }
+
     #[test]
     fn test_duplicate_function_names() {
         let yaml = r#"
@@ -179,4 +535,36 @@ functions:
         let result = NexusConfig::from_str(yaml);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_rate_limits_need_positive_rate_and_burst() {
+        let config = |limits: &str| format!("version: v1\nfunctions: []\n{}", limits);
+        let invalid = [
+            "rate_limits:\n  routes:\n    - {path: /events, rate: 0, burst: 5}\n",
+            "rate_limits:\n  routes:\n    - {path: /events, rate: -1, burst: 5}\n",
+            "rate_limits:\n  per_credential: {rate: 1, burst: 0}\n",
+            "rate_limits:\n  per_credential: {rate: .nan, burst: 1}\n",
+            "rate_limits:\n  per_ip: {rate: 0, burst: 1}\n",
+            "auth:\n  enabled: true\n  api_keys:\n    - {name: ci, key: k, scopes: [ingest], rate_limit: {rate: 0, burst: 1}}\n",
+        ];
+        for limits in invalid {
+            assert!(NexusConfig::from_str(&config(limits)).is_err(), "accepted {}", limits);
+        }
+        assert!(NexusConfig::from_str(&config("rate_limits:\n  per_credential: {rate: 0.5, burst: 1}\n")).is_ok());
+        let key = "auth:\n  enabled: true\n  api_keys:\n    - {name: ci, key: k, scopes: [ingest], rate_limit: {rate: 2, burst: 1}}\n";
+        assert!(NexusConfig::from_str(&config(key)).is_ok());
+    }
+
+    #[test]
+    fn test_partitioned_functions_share_partition_key() {
+        let config = |partitions: u32, second_key: &str| {
+            format!(
+                "version: v1\nconsumers:\n  partitions: {}\nfunctions:\n  - {{name: a, on: {{nats: {{subject: x.y}}, partition_key: $.data.user}}, runtime: wasi-preview1, code: ./a.wasm}}\n  - {{name: b, on: {{nats: {{subject: x.y}}{}}}, runtime: wasi-preview1, code: ./b.wasm}}\n",
+                partitions, second_key
+            )
+        };
+        assert!(NexusConfig::from_str(&config(4, ", partition_key: $.data.user")).is_ok());
+        assert!(NexusConfig::from_str(&config(4, "")).is_err());
+        assert!(NexusConfig::from_str(&config(1, "")).is_ok());
+    }
 }
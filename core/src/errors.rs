@@ -1,5 +1,8 @@
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use nexus_observability::get_trace_id;
 use serde::{Deserialize, Serialize};
-use std::fmt;
 
 /// Standard error response format
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,88 +19,84 @@ pub struct ErrorDetail {
 }
 
 /// Application error types
-#[derive(Debug)]
+///
+/// Handlers return these directly: as a response, an error becomes its
+/// status code and an [`ErrorResponse`] carrying the request's trace ID.
+#[derive(Debug, thiserror::Error)]
 pub enum NexusError {
+    #[error("{resource} not found: {id}")]
     NotFound { resource: String, id: String },
+    #[error("Invalid input for {field}: {message}")]
     InvalidInput { field: String, message: String },
+    #[error("Unauthorized: {message}")]
+    Unauthorized { message: String },
+    #[error("Forbidden: {message}")]
+    Forbidden { message: String },
+    #[error("Rate limited: {message}")]
+    RateLimited { message: String, retry_after_secs: u64 },
+    #[error("Configuration error: {message}")]
     ConfigError { message: String },
+    #[error("NATS error: {message}")]
     NatsError { message: String },
+    #[error("WASM execution error in {function}: {message}")]
     WasmError { function: String, message: String },
+    #[error("Internal error: {message}")]
     InternalError { message: String },
 }
 
-impl fmt::Display for NexusError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl NexusError {
+    /// Code clients match on; stable across releases
+    pub fn code(&self) -> &'static str {
         match self {
-            NexusError::NotFound { resource, id } => {
-                write!(f, "{} not found: {}", resource, id)
-            }
-            NexusError::InvalidInput { field, message } => {
-                write!(f, "Invalid input for {}: {}", field, message)
-            }
-            NexusError::ConfigError { message } => {
-                write!(f, "Configuration error: {}", message)
-            }
-            NexusError::NatsError { message } => {
-                write!(f, "NATS error: {}", message)
-            }
-            NexusError::WasmError { function, message } => {
-                write!(f, "WASM execution error in {}: {}", function, message)
-            }
-            NexusError::InternalError { message } => {
-                write!(f, "Internal error: {}", message)
-            }
+            NexusError::NotFound { .. } => "NOT_FOUND",
+            NexusError::InvalidInput { .. } => "INVALID_INPUT",
+            NexusError::Unauthorized { .. } => "UNAUTHORIZED",
+            NexusError::Forbidden { .. } => "FORBIDDEN",
+            NexusError::RateLimited { .. } => "RATE_LIMITED",
+            NexusError::ConfigError { .. } => "CONFIG_ERROR",
+            NexusError::NatsError { .. } => "NATS_ERROR",
+            NexusError::WasmError { .. } => "WASM_ERROR",
+            NexusError::InternalError { .. } => "INTERNAL_ERROR",
         }
     }
-}
-
-impl std::error::Error for NexusError {}
 
-impl NexusError {
     pub fn to_response(&self, trace_id: Option<String>) -> ErrorResponse {
-        let (code, message, details) = match self {
+        let (message, details) = match self {
             NexusError::NotFound { resource, id } => (
-                "NOT_FOUND".to_string(),
-                format!("{} not found: {}", resource, id),
+                self.to_string(),
                 Some(serde_json::json!({
                     "resource": resource,
                     "id": id
                 })),
             ),
             NexusError::InvalidInput { field, message } => (
-                "INVALID_INPUT".to_string(),
                 message.clone(),
                 Some(serde_json::json!({
                     "field": field
                 })),
             ),
-            NexusError::ConfigError { message } => (
-                "CONFIG_ERROR".to_string(),
-                message.clone(),
-                None,
-            ),
-            NexusError::NatsError { message } => (
-                "NATS_ERROR".to_string(),
+            NexusError::RateLimited { message, retry_after_secs } => (
                 message.clone(),
-                None,
+                Some(serde_json::json!({
+                    "retry_after_secs": retry_after_secs
+                })),
             ),
             NexusError::WasmError { function, message } => (
-                "WASM_ERROR".to_string(),
                 message.clone(),
                 Some(serde_json::json!({
                     "function": function
                 })),
             ),
-            NexusError::InternalError { message } => (
-                "INTERNAL_ERROR".to_string(),
-                message.clone(),
-                None,
-            ),
+            NexusError::Unauthorized { message }
+            | NexusError::Forbidden { message }
+            | NexusError::ConfigError { message }
+            | NexusError::NatsError { message }
+            | NexusError::InternalError { message } => (message.clone(), None),
         };
 
         ErrorResponse {
             error: ErrorDetail {
-                code,
+                code: self.code().to_string(),
                 message,
                 details,
             },
@@ -105,24 +104,55 @@ impl NexusError {
         }
     }
 
-    pub fn status_code(&self) -> axum::http::StatusCode {
+    pub fn status_code(&self) -> StatusCode {
         match self {
-            NexusError::NotFound { .. } => axum::http::StatusCode::NOT_FOUND,
-            NexusError::InvalidInput { .. } => axum::http::StatusCode::BAD_REQUEST,
-            NexusError::ConfigError { .. } => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            NexusError::NatsError { .. } => axum::http::StatusCode::SERVICE_UNAVAILABLE,
-            NexusError::WasmError { .. } => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            NexusError::InternalError { .. } => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            NexusError::NotFound { .. } => StatusCode::NOT_FOUND,
+            NexusError::InvalidInput { .. } => StatusCode::BAD_REQUEST,
+            NexusError::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
+            NexusError::Forbidden { .. } => StatusCode::FORBIDDEN,
+            NexusError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            NexusError::ConfigError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            NexusError::NatsError { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            NexusError::WasmError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            NexusError::InternalError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for NexusError {
+    fn into_response(self) -> Response {
+        let mut response = (self.status_code(), Json(self.to_response(get_trace_id()))).into_response();
+        if let NexusError::RateLimited { retry_after_secs, .. } = self {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs));
         }
+        response
     }
 }
 
-/// Helper to create error responses
-pub fn error_response(
-    error: NexusError,
-    trace_id: Option<String>,
-) -> (axum::http::StatusCode, axum::Json<ErrorResponse>) {
-    let status = error.status_code();
-    let response = error.to_response(trace_id);
-    (status, axum::Json(response))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nexus_observability::{scope, RequestContext};
+
+    #[tokio::test]
+    async fn test_error_response_carries_code_and_trace_id() {
+        let ctx = RequestContext::new();
+        let response = scope(ctx.clone(), async {
+            NexusError::RateLimited {
+                message: "Rate limit exceeded, retry in 3s".to_string(),
+                retry_after_secs: 3,
+            }
+            .into_response()
+        })
+        .await;
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "3");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.error.code, "RATE_LIMITED");
+        assert_eq!(body.trace_id, Some(ctx.trace_id));
+    }
 }
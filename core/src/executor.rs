@@ -1,4 +1,5 @@
-use crate::config::NexusConfig;
+use crate::config::{FunctionConfig, InputMode, NexusConfig};
+use crate::partition::resolve_key;
 use anyhow::{Context, Result};
 use nexus_event_fabric::CloudEvent;
 use nexus_runtime::WasmExecutor;
@@ -51,15 +52,27 @@ impl FunctionExecutor {
             module_bytes.len()
         );
 
-        // Prepare input (serialize CloudEvent to JSON)
-        let input = event
-            .to_json_bytes()
-            .context("Failed to serialize CloudEvent")?;
+        // Prepare input (the CloudEvent as JSON, or the raw data with attributes in the environment)
+        let (input, env) = match function.input {
+            InputMode::Event => {
+                let input = event
+                    .to_json_bytes()
+                    .context("Failed to serialize CloudEvent")?;
+                (input, vec![])
+            }
+            InputMode::Data => {
+                let input = event
+                    .data_bytes()
+                    .context("Failed to decode event data")?
+                    .unwrap_or_default();
+                (input, attribute_env(event))
+            }
+        };
 
         // Execute WASM module
         let output = self
             .wasm_executor
-            .execute(&module_bytes, &input)
+            .execute_with_env(&module_bytes, &input, &env)
             .await
             .with_context(|| format!("Failed to execute function '{}'", function_name))?;
 
@@ -72,29 +85,42 @@ impl FunctionExecutor {
         Ok(output)
     }
 
+    /// Whether a function with the given name is configured
+    pub fn has_function(&self, function_name: &str) -> bool {
+        self.config.functions.iter().any(|f| f.name == function_name)
+    }
+
+    /// Whether the named function is triggered by an event type
+    pub fn function_matches(&self, function_name: &str, event_type: &str) -> bool {
+        self.config
+            .functions
+            .iter()
+            .any(|func| func.name == function_name && trigger_matches(func, event_type))
+    }
+
+    /// Partition key of an event for a function
+    ///
+    /// Uses the trigger's `partition_key` path when configured, falling back
+    /// to the event's `partitionkey` extension.
+    pub fn partition_key(&self, function_name: &str, event: &CloudEvent) -> Option<String> {
+        let path = self
+            .config
+            .functions
+            .iter()
+            .find(|f| f.name == function_name)
+            .and_then(|f| f.on.partition_key.as_deref());
+
+        path.and_then(|path| resolve_key(event, path))
+            .or_else(|| event.partition_key())
+    }
+
     /// Find functions that should be triggered by an event
     pub fn find_matching_functions(&self, event_type: &str) -> Vec<String> {
         self.config
             .functions
             .iter()
-            .filter_map(|func| {
-                // Check if trigger matches this event type
-                let matches = if func.on.http.is_some() {
-                    // HTTP triggers - match all for MVP (can be refined later)
-                    true
-                } else if let Some(nats) = &func.on.nats {
-                    // NATS triggers match based on subject pattern
-                    event_type.contains(&nats.subject) || nats.subject.contains(event_type)
-                } else {
-                    false
-                };
-
-                if matches {
-                    Some(func.name.clone())
-                } else {
-                    None
-                }
-            })
+            .filter(|func| trigger_matches(func, event_type))
+            .map(|func| func.name.clone())
             .collect()
     }
 
@@ -132,10 +158,55 @@ impl FunctionExecutor {
     }
 }
 
+/// CloudEvents attributes as `CE_*` environment variables
+///
+/// Extensions are included too, e.g. `nexusreplay` as `CE_NEXUSREPLAY=true`;
+/// values other than strings are passed as JSON.
+fn attribute_env(event: &CloudEvent) -> Vec<(String, String)> {
+    let mut env = vec![
+        ("CE_SPECVERSION".to_string(), event.specversion.clone()),
+        ("CE_ID".to_string(), event.id.clone()),
+        ("CE_TYPE".to_string(), event.event_type.clone()),
+        ("CE_SOURCE".to_string(), event.source.clone()),
+        ("CE_TIME".to_string(), event.time.to_rfc3339()),
+    ];
+    let optional = [
+        ("CE_SUBJECT", &event.subject),
+        ("CE_DATACONTENTTYPE", &event.datacontenttype),
+        ("CE_DATASCHEMA", &event.dataschema),
+    ];
+    for (name, value) in optional {
+        if let Some(value) = value {
+            env.push((name.to_string(), value.clone()));
+        }
+    }
+    let mut extensions: Vec<_> = event.extensions.iter().collect();
+    extensions.sort_by_key(|(name, _)| name.as_str());
+    for (name, value) in extensions {
+        let value = match value {
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        env.push((format!("CE_{}", name.to_uppercase()), value));
+    }
+    env
+}
+
+fn trigger_matches(func: &FunctionConfig, event_type: &str) -> bool {
+    if func.on.http.is_some() {
+        // HTTP triggers - match all for MVP (can be refined later)
+        true
+    } else if let Some(nats) = &func.on.nats {
+        // NATS triggers match based on subject pattern
+        event_type.contains(&nats.subject) || nats.subject.contains(event_type)
+    } else {
+        false
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{FunctionConfig, TriggerConfig};
 
     fn create_test_config() -> NexusConfig {
         use crate::config::{HttpTrigger, TriggerConfig};
@@ -150,13 +221,16 @@ mod tests {
                         path: "/test".to_string(),
                     }),
                     nats: None,
+                    partition_key: None,
                 },
                 runtime: "wasi-preview1".to_string(),
                 code: "./test.wasm".to_string(),
                 timeout: "5s".to_string(),
                 memory: "128Mi".to_string(),
                 env: std::collections::HashMap::new(),
+                input: Default::default(),
             }],
+            ..Default::default()
         }
     }
 
@@ -169,4 +243,54 @@ mod tests {
         assert_eq!(matches.len(), 1);
         assert_eq!(matches[0], "test-func");
     }
+
+    #[test]
+    fn test_has_function() {
+        let config = Arc::new(create_test_config());
+        let executor = FunctionExecutor::new(config).unwrap();
+
+        assert!(executor.has_function("test-func"));
+        assert!(!executor.has_function("missing-func"));
+    }
+
+    #[test]
+    fn test_function_matches() {
+        let config = Arc::new(create_test_config());
+        let executor = FunctionExecutor::new(config).unwrap();
+
+        assert!(executor.function_matches("test-func", "com.nexus.test.event"));
+        assert!(!executor.function_matches("missing-func", "com.nexus.test.event"));
+    }
+
+    #[test]
+    fn test_partition_key() {
+        let mut config = create_test_config();
+        let executor = FunctionExecutor::new(Arc::new(config.clone())).unwrap();
+        let event = CloudEvent::new("com.nexus.test.event", "/test")
+            .with_data(serde_json::json!({"user_id": "u-1"}))
+            .with_extension("partitionkey", serde_json::json!("ext"));
+        assert_eq!(executor.partition_key("test-func", &event).unwrap(), "ext");
+
+        config.functions[0].on.partition_key = Some("$.data.user_id".to_string());
+        let executor = FunctionExecutor::new(Arc::new(config)).unwrap();
+        assert_eq!(executor.partition_key("test-func", &event).unwrap(), "u-1");
+    }
+
+    #[test]
+    fn test_attribute_env() {
+        let event = CloudEvent::new("com.nexus.upload", "/test").with_binary_data(b"<a/>", "application/xml");
+        let env = attribute_env(&event);
+
+        assert!(env.contains(&("CE_TYPE".to_string(), "com.nexus.upload".to_string())));
+        assert!(env.contains(&("CE_DATACONTENTTYPE".to_string(), "application/xml".to_string())));
+        assert!(!env.iter().any(|(name, _)| name == "CE_SUBJECT"));
+
+        // Data mode guests tell replays apart by the extension
+        let replay = CloudEvent::new("com.nexus.upload", "/test")
+            .with_extension("tenant", serde_json::json!("acme"))
+            .as_replay();
+        let env = attribute_env(&replay);
+        assert!(env.contains(&("CE_NEXUSREPLAY".to_string(), "true".to_string())));
+        assert!(env.contains(&("CE_TENANT".to_string(), "acme".to_string())));
+    }
 }
@@ -0,0 +1,197 @@
+use crate::body_limit::BodyLimit;
+use crate::errors::NexusError;
+use axum::async_trait;
+use axum::body::Bytes;
+use axum::extract::path::ErrorKind;
+use axum::extract::rejection::PathRejection;
+use axum::extract::{FromRequest, FromRequestParts, Path, Request};
+use axum::http::request::Parts;
+use axum::http::{header, StatusCode};
+use serde::de::DeserializeOwned;
+
+/// Rejection of the extractors below, used in place of axum's plain-text rejections
+///
+/// Every parse or size error becomes [`NexusError::InvalidInput`], naming the
+/// offending field: `body` or a path into the JSON body such as
+/// `items[0].amount`, a query parameter, or a path parameter.
+fn invalid(field: impl Into<String>, message: impl Into<String>) -> NexusError {
+    NexusError::InvalidInput {
+        field: field.into(),
+        message: message.into(),
+    }
+}
+
+/// Field of a deserialization error: `fallback` at the top level, else the path to the failing value
+fn error_field(path: &serde_path_to_error::Path, fallback: &str) -> String {
+    match path.to_string().as_str() {
+        "." => fallback.to_string(),
+        path => path.to_string(),
+    }
+}
+
+/// The raw request body, read up to the route's body limit
+pub struct ApiBytes(pub Bytes);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequest<S> for ApiBytes {
+    type Rejection = NexusError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let limit = req.extensions().get::<BodyLimit>().copied();
+        match Bytes::from_request(req, state).await {
+            Ok(bytes) => Ok(Self(bytes)),
+            Err(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => Err(invalid(
+                "body",
+                match limit {
+                    Some(BodyLimit(limit)) => format!("Request body exceeds the limit of {} bytes", limit),
+                    None => "Request body is too large".to_string(),
+                },
+            )),
+            Err(e) => Err(invalid("body", e.body_text())),
+        }
+    }
+}
+
+/// A JSON body, with the path to the first invalid field on error
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T: DeserializeOwned, S: Send + Sync> FromRequest<S> for ApiJson<T> {
+    type Rejection = NexusError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let json = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim().to_ascii_lowercase())
+            .is_some_and(|v| v == "application/json" || (v.starts_with("application/") && v.ends_with("+json")));
+        if !json {
+            return Err(invalid("content-type", "Expected request with `Content-Type: application/json`"));
+        }
+
+        let ApiBytes(body) = ApiBytes::from_request(req, state).await?;
+        let deserializer = &mut serde_json::Deserializer::from_slice(&body);
+        serde_path_to_error::deserialize(deserializer)
+            .map(Self)
+            .map_err(|e| {
+                // Malformed JSON has no meaningful field path
+                let field = if e.inner().is_data() {
+                    error_field(e.path(), "body")
+                } else {
+                    "body".to_string()
+                };
+                invalid(field, e.inner().to_string())
+            })
+    }
+}
+
+/// Query parameters, naming the parameter that failed to parse
+pub struct ApiQuery<T>(pub T);
+
+#[async_trait]
+impl<T: DeserializeOwned, S: Send + Sync> FromRequestParts<S> for ApiQuery<T> {
+    type Rejection = NexusError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query = parts.uri.query().unwrap_or_default();
+        let deserializer = serde_urlencoded::Deserializer::new(form_urlencoded::parse(query.as_bytes()));
+        serde_path_to_error::deserialize(deserializer)
+            .map(Self)
+            .map_err(|e| invalid(error_field(e.path(), "query"), e.inner().to_string()))
+    }
+}
+
+/// Path parameters, naming the parameter that failed to parse
+pub struct ApiPath<T>(pub T);
+
+#[async_trait]
+impl<T: DeserializeOwned + Send, S: Send + Sync> FromRequestParts<S> for ApiPath<T> {
+    type Rejection = NexusError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Path::<T>::from_request_parts(parts, state).await {
+            Ok(Path(value)) => Ok(Self(value)),
+            Err(PathRejection::FailedToDeserializePathParams(e)) => {
+                let field = match e.kind() {
+                    ErrorKind::ParseErrorAtKey { key, .. } | ErrorKind::InvalidUtf8InPathParam { key } => key.clone(),
+                    _ => "path".to_string(),
+                };
+                Err(invalid(field, e.body_text()))
+            }
+            Err(e) => Err(NexusError::InternalError { message: e.body_text() }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use http_body_util::Limited;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Order {
+        id: String,
+        items: Vec<Item>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Item {
+        amount: u32,
+    }
+
+    fn field(rejection: &NexusError) -> &str {
+        assert_eq!(rejection.status_code(), StatusCode::BAD_REQUEST);
+        match rejection {
+            NexusError::InvalidInput { field, .. } => field,
+            other => panic!("unexpected rejection {}", other),
+        }
+    }
+
+    fn json_request(body: &'static str) -> Request {
+        Request::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_json_and_query_rejections_name_fields() {
+        let ok = ApiJson::<Order>::from_request(json_request(r#"{"id": "o1", "items": [{"amount": 3}]}"#), &()).await;
+        assert!(ok.is_ok());
+
+        let rejection = ApiJson::<Order>::from_request(json_request(r#"{"id": "o1", "items": [{"amount": "x"}]}"#), &())
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(field(&rejection), "items[0].amount");
+
+        let rejection = ApiJson::<Order>::from_request(json_request("{not json"), &()).await.err().unwrap();
+        assert_eq!(field(&rejection), "body");
+
+        let request = Request::builder().uri("/events?limit=abc").body(Body::empty()).unwrap();
+        let (mut parts, _) = request.into_parts();
+        #[derive(Debug, Deserialize)]
+        struct Page {
+            #[allow(dead_code)]
+            limit: Option<u32>,
+        }
+        let rejection = ApiQuery::<Page>::from_request_parts(&mut parts, &()).await.err().unwrap();
+        assert_eq!(field(&rejection), "limit");
+    }
+
+    #[tokio::test]
+    async fn test_body_over_limit() {
+        let mut request = Request::new(Body::new(Limited::new(Body::from(vec![b'x'; 64]), 16)));
+        request.extensions_mut().insert(BodyLimit(16));
+
+        let rejection = ApiBytes::from_request(request, &()).await.err().unwrap();
+        assert_eq!(field(&rejection), "body");
+        assert!(rejection.to_string().contains("16 bytes"));
+    }
+}
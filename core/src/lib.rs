@@ -4,10 +4,20 @@ pub mod state;
 pub mod executor;
 pub mod errors;
 pub mod metrics;
+pub mod worker;
+pub mod partition;
+pub mod schema;
+pub mod archiver;
+pub mod auth;
+pub mod ratelimit;
+pub mod body_limit;
+pub mod extract;
 
 pub use config::NexusConfig;
 pub use server::Server;
-pub use state::AppState;
+pub use state::{AppState, Dispatch};
 pub use executor::FunctionExecutor;
 pub use errors::{NexusError, ErrorResponse};
 pub use metrics::{MetricsCollector, Metrics};
+pub use partition::PartitionDispatcher;
+pub use schema::SchemaRegistry;
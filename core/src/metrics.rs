@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::RwLock;
@@ -21,6 +22,15 @@ pub struct MetricsData {
     pub functions_succeeded: u64,
     pub functions_failed: u64,
     pub total_execution_time_ms: u64,
+    pub lanes_expired: u64,
+    
+    // Webhook metrics
+    pub webhooks_verified: u64,
+    pub webhooks_rejected: u64,
+    
+    // Rate limit metrics
+    pub requests_rate_limited: u64,
+    pub requests_over_quota: u64,
     
     // System metrics
     pub uptime_seconds: u64,
@@ -31,6 +41,8 @@ pub struct MetricsData {
 pub struct Metrics {
     pub events: EventMetrics,
     pub functions: FunctionMetrics,
+    pub webhooks: WebhookMetrics,
+    pub rate_limits: RateLimitMetrics,
     pub system: SystemMetrics,
 }
 
@@ -49,6 +61,26 @@ pub struct FunctionMetrics {
     pub failed: u64,
     pub success_rate: f64,
     pub avg_execution_time_ms: f64,
+    /// Partition key lanes released because a failed event was not redelivered in time
+    pub lanes_expired: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookMetrics {
+    /// Requests with a valid signature
+    pub verified: u64,
+    /// Requests rejected for a missing, expired or invalid signature
+    pub rejected: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitMetrics {
+    /// Requests rejected by a route or credential rate limit
+    pub limited: u64,
+    /// Requests rejected by a daily ingestion quota
+    pub over_quota: u64,
+    /// Ingestion requests per credential so far today (UTC)
+    pub ingested_today: BTreeMap<String, u64>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -68,6 +100,11 @@ impl MetricsCollector {
                 functions_succeeded: 0,
                 functions_failed: 0,
                 total_execution_time_ms: 0,
+                lanes_expired: 0,
+                webhooks_verified: 0,
+                webhooks_rejected: 0,
+                requests_rate_limited: 0,
+                requests_over_quota: 0,
                 uptime_seconds: 0,
                 nats_connected: false,
             })),
@@ -101,6 +138,30 @@ impl MetricsCollector {
         }
     }
 
+    pub async fn increment_lanes_expired(&self) {
+        let mut data = self.data.write().await;
+        data.lanes_expired += 1;
+    }
+
+    pub async fn record_webhook_signature(&self, valid: bool) {
+        let mut data = self.data.write().await;
+        if valid {
+            data.webhooks_verified += 1;
+        } else {
+            data.webhooks_rejected += 1;
+        }
+    }
+
+    pub async fn increment_rate_limited(&self) {
+        let mut data = self.data.write().await;
+        data.requests_rate_limited += 1;
+    }
+
+    pub async fn increment_over_quota(&self) {
+        let mut data = self.data.write().await;
+        data.requests_over_quota += 1;
+    }
+
     pub async fn set_nats_connected(&self, connected: bool) {
         let mut data = self.data.write().await;
         data.nats_connected = connected;
@@ -146,6 +207,16 @@ impl MetricsCollector {
                 failed: data.functions_failed,
                 success_rate: function_success_rate,
                 avg_execution_time_ms: avg_execution_time,
+                lanes_expired: data.lanes_expired,
+            },
+            webhooks: WebhookMetrics {
+                verified: data.webhooks_verified,
+                rejected: data.webhooks_rejected,
+            },
+            rate_limits: RateLimitMetrics {
+                limited: data.requests_rate_limited,
+                over_quota: data.requests_over_quota,
+                ingested_today: BTreeMap::new(),
             },
             system: SystemMetrics {
                 uptime_seconds: data.uptime_seconds,
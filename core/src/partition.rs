@@ -0,0 +1,328 @@
+pub use nexus_event_fabric::cloudevents::resolve_key;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::{mpsc, oneshot};
+
+type Job = Pin<Box<dyn Future<Output = ()> + Send>>;
+type Lanes = Arc<Mutex<HashMap<String, mpsc::UnboundedSender<Job>>>>;
+
+/// Runs jobs with the same lane key one at a time, in dispatch order
+///
+/// Each active key gets a lane task that drains its queue and exits once the
+/// queue is empty, so idle keys hold no resources. Jobs without a key and
+/// jobs on different keys run in parallel.
+#[derive(Clone, Default)]
+pub struct PartitionDispatcher {
+    lanes: Lanes,
+}
+
+impl PartitionDispatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a job on a lane (or run it right away without one)
+    ///
+    /// The returned receiver resolves when the job has finished.
+    pub fn dispatch<F>(&self, lane: Option<String>, job: F) -> oneshot::Receiver<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let (done_tx, done_rx) = oneshot::channel();
+        let job: Job = Box::pin(async move {
+            job.await;
+            let _ = done_tx.send(());
+        });
+
+        let Some(lane) = lane else {
+            tokio::spawn(job);
+            return done_rx;
+        };
+
+        let mut lanes = self.lanes.lock().unwrap();
+        let job = match lanes.get(&lane) {
+            Some(sender) => match sender.send(job) {
+                Ok(()) => return done_rx,
+                // The lane task is gone (it panicked), start a new one
+                Err(mpsc::error::SendError(job)) => job,
+            },
+            None => job,
+        };
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let _ = sender.send(job);
+        lanes.insert(lane.clone(), sender);
+        tokio::spawn(run_lane(self.lanes.clone(), lane, receiver));
+
+        done_rx
+    }
+
+    /// Number of keys with queued or running jobs
+    pub fn active_lanes(&self) -> usize {
+        self.lanes.lock().unwrap().len()
+    }
+}
+
+async fn run_lane(lanes: Lanes, lane: String, mut receiver: mpsc::UnboundedReceiver<Job>) {
+    loop {
+        while let Ok(job) = receiver.try_recv() {
+            job.await;
+        }
+
+        // Dispatch enqueues under the same lock, so nothing can slip in
+        // between the final check and removing the lane
+        let mut active = lanes.lock().unwrap();
+        match receiver.try_recv() {
+            Ok(job) => {
+                drop(active);
+                job.await;
+            }
+            Err(_) => {
+                active.remove(&lane);
+                return;
+            }
+        }
+    }
+}
+
+/// Partitions run by one of `replicas` worker replicas: every `replicas`-th
+/// partition, starting at the replica's ordinal
+pub fn replica_partitions(ordinal: u32, replicas: u32, partitions: u32) -> anyhow::Result<Vec<u32>> {
+    if replicas == 0 || ordinal >= replicas {
+        anyhow::bail!("Replica {} is not one of {} replicas", ordinal, replicas);
+    }
+    if replicas > partitions {
+        anyhow::bail!(
+            "{} replicas need at least as many consumer partitions, consumers.partitions is {}",
+            replicas,
+            partitions
+        );
+    }
+    Ok((ordinal..partitions).step_by(replicas as usize).collect())
+}
+
+/// Ordinal of a replica given as a number or a StatefulSet pod name, e.g. `nexus-worker-2`
+pub fn replica_ordinal(replica: &str) -> Option<u32> {
+    replica.rsplit('-').next()?.parse().ok()
+}
+
+/// Lane key for executing a function on an event with the given partition key
+pub fn lane_for(function_name: &str, partition_key: Option<String>) -> Option<String> {
+    partition_key.map(|key| format!("{}/{}", function_name, key))
+}
+
+/// Holds a lane's deliveries back while an earlier one awaits redelivery
+///
+/// A failed event is redelivered after a delay, while deliveries with the
+/// same key keep arriving. The gate blocks the lane at the failed event's
+/// stream sequence: only that event may run, later ones are held until it
+/// succeeds or is given up, then released one at a time in sequence order.
+/// Lanes run one job at a time, so calls for a lane never interleave.
+///
+/// The redelivery may never come back to this process: another replica on
+/// the same consumer may get it, or retention may delete it. A block
+/// therefore lasts until a deadline, after which [`LaneGate::expire`] opens
+/// the lane and hands back the held deliveries.
+pub struct LaneGate<T> {
+    lanes: Mutex<HashMap<String, Blocked<T>>>,
+}
+
+struct Blocked<T> {
+    /// Sequence of the one delivery that may run
+    head: u64,
+    held: BTreeMap<u64, T>,
+    /// When the lane opens even if the head was not redelivered
+    until: Instant,
+}
+
+impl<T> Default for LaneGate<T> {
+    fn default() -> Self {
+        Self {
+            lanes: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T> LaneGate<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give back a delivery that may run now, or hold it behind the lane's blocked event
+    pub fn admit(&self, lane: &str, sequence: u64, delivery: T) -> Option<T> {
+        let mut lanes = self.lanes.lock().unwrap();
+        match lanes.get_mut(lane) {
+            Some(blocked) if blocked.head != sequence => {
+                // A redelivery of a held event replaces the stale delivery
+                blocked.held.insert(sequence, delivery);
+                None
+            }
+            _ => Some(delivery),
+        }
+    }
+
+    /// Block the lane until `until`: the delivery at `sequence` failed and will be redelivered
+    pub fn block(&self, lane: &str, sequence: u64, until: Instant) {
+        let mut lanes = self.lanes.lock().unwrap();
+        let blocked = lanes.entry(lane.to_string()).or_insert_with(|| Blocked {
+            head: sequence,
+            held: BTreeMap::new(),
+            until,
+        });
+        blocked.until = until;
+    }
+
+    /// Open the lanes whose deadline passed, returning their held deliveries by lane
+    pub fn expire(&self, now: Instant) -> Vec<(String, Vec<T>)> {
+        let mut lanes = self.lanes.lock().unwrap();
+        let expired: Vec<String> = lanes
+            .iter()
+            .filter(|(_, blocked)| blocked.until <= now)
+            .map(|(lane, _)| lane.clone())
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|lane| {
+                let blocked = lanes.remove(&lane)?;
+                Some((lane, blocked.held.into_values().collect()))
+            })
+            .collect()
+    }
+
+    /// The delivery at `sequence` was acknowledged or given up; take the next held one
+    ///
+    /// The lane stays blocked at the returned delivery until it settles in turn,
+    /// and is open again once nothing is held.
+    pub fn settle(&self, lane: &str, sequence: u64) -> Option<(u64, T)> {
+        let mut lanes = self.lanes.lock().unwrap();
+        let blocked = lanes.get_mut(lane).filter(|blocked| blocked.head == sequence)?;
+        match blocked.held.pop_first() {
+            Some((next, delivery)) => {
+                blocked.head = next;
+                Some((next, delivery))
+            }
+            None => {
+                lanes.remove(lane);
+                None
+            }
+        }
+    }
+
+    /// Deliveries currently held, e.g. to keep extending their ack wait
+    pub fn held(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        let lanes = self.lanes.lock().unwrap();
+        lanes.values().flat_map(|blocked| blocked.held.values().cloned()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_replicas_split_partitions() {
+        assert_eq!(replica_ordinal("nexus-worker-2"), Some(2));
+        assert_eq!(replica_ordinal("1"), Some(1));
+        assert_eq!(replica_ordinal("nexus-worker"), None);
+
+        assert_eq!(replica_partitions(0, 2, 5).unwrap(), vec![0, 2, 4]);
+        assert_eq!(replica_partitions(1, 2, 5).unwrap(), vec![1, 3]);
+        assert!(replica_partitions(2, 2, 5).is_err());
+        assert!(replica_partitions(0, 3, 2).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_same_key_runs_in_order() {
+        let dispatcher = PartitionDispatcher::new();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let mut done = Vec::new();
+        for i in 0..5u64 {
+            let order = order.clone();
+            done.push(dispatcher.dispatch(Some("user-1".to_string()), async move {
+                // Earlier jobs sleep longer, so parallel execution would reverse the order
+                tokio::time::sleep(Duration::from_millis(25 - i * 5)).await;
+                order.lock().unwrap().push(i);
+            }));
+        }
+        for rx in done {
+            rx.await.unwrap();
+        }
+
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2, 3, 4]);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(dispatcher.active_lanes(), 0);
+    }
+
+    /// Run deliveries `(sequence, key)` the way a worker does, failing each sequence in `failures` once
+    fn run_batch(gate: &LaneGate<u64>, batch: &[(u64, &str)], failures: &mut Vec<u64>, ran: &mut Vec<u64>) {
+        for &(sequence, key) in batch {
+            let Some(mut delivery) = gate.admit(key, sequence, sequence) else {
+                continue;
+            };
+            loop {
+                if let Some(pos) = failures.iter().position(|f| *f == delivery) {
+                    failures.remove(pos);
+                    gate.block(key, delivery, Instant::now() + Duration::from_secs(60));
+                    break;
+                }
+                ran.push(delivery);
+                match gate.settle(key, delivery) {
+                    Some((_, next)) => delivery = next,
+                    None => break,
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_failed_event_holds_its_lane_until_redelivered() {
+        let gate = LaneGate::new();
+        let mut failures = vec![1];
+        let mut ran = Vec::new();
+
+        // 1 fails and is redelivered later; 2 shares its key, 3 does not
+        run_batch(&gate, &[(1, "user-1"), (2, "user-1"), (3, "user-2")], &mut failures, &mut ran);
+        assert_eq!(ran, vec![3]);
+        assert_eq!(gate.held(), vec![2]);
+
+        // A later delivery of the key is held too, behind 2
+        run_batch(&gate, &[(4, "user-1")], &mut failures, &mut ran);
+        assert_eq!(ran, vec![3]);
+
+        // The redelivery of 1 runs, then releases 2 and 4 in order
+        run_batch(&gate, &[(1, "user-1")], &mut failures, &mut ran);
+        assert_eq!(ran, vec![3, 1, 2, 4]);
+        assert!(gate.held().is_empty());
+
+        run_batch(&gate, &[(5, "user-1")], &mut failures, &mut ran);
+        assert_eq!(ran, vec![3, 1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn test_blocked_lane_opens_when_redelivery_never_arrives() {
+        let gate = LaneGate::new();
+        let mut failures = vec![1];
+        let mut ran = Vec::new();
+
+        // 1 fails and its redelivery goes to another replica
+        run_batch(&gate, &[(1, "user-1"), (2, "user-1")], &mut failures, &mut ran);
+        assert_eq!(gate.held(), vec![2]);
+        assert!(gate.expire(Instant::now()).is_empty());
+
+        let expired = gate.expire(Instant::now() + Duration::from_secs(61));
+        assert_eq!(expired, vec![("user-1".to_string(), vec![2])]);
+        assert!(gate.held().is_empty());
+
+        // The lane runs new deliveries again
+        run_batch(&gate, &[(3, "user-1")], &mut failures, &mut ran);
+        assert_eq!(ran, vec![3]);
+    }
+}
@@ -0,0 +1,392 @@
+use crate::auth::{required_scope, AuthConfig, Principal, Scope};
+use crate::errors::NexusError;
+use crate::state::AppState;
+use axum::body::Body;
+use axum::extract::{ConnectInfo, MatchedPath, State};
+use axum::http::{HeaderMap, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// A token bucket: `rate` requests per second on average, in bursts of up to `burst`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub rate: f64,
+    pub burst: u32,
+}
+
+/// Request rate limits, as configured in the `rate_limits` section of nexus.yaml
+///
+/// API keys can carry their own `rate_limit` and a `daily_quota` in the
+/// `auth` section.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Limits shared by all callers of a path prefix
+    #[serde(default)]
+    pub routes: Vec<RouteRateLimit>,
+    /// Limit of each authenticated caller without a limit of its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_credential: Option<RateLimit>,
+    /// Limit of each client IP address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_ip: Option<RateLimit>,
+    /// Proxies in front of the server; requests from them are limited by the
+    /// client address in their `X-Forwarded-For` header instead
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteRateLimit {
+    /// Request path prefix, e.g. `/webhook` or `/webhook/github`
+    pub path: String,
+    #[serde(flatten)]
+    pub limit: RateLimit,
+}
+
+impl RouteRateLimit {
+    fn covers(&self, path: &str) -> bool {
+        let own = self.path.trim_end_matches('/');
+        path == own || path.strip_prefix(own).is_some_and(|rest| rest.starts_with('/'))
+    }
+}
+
+/// Why a request was turned away
+#[derive(Debug, PartialEq)]
+pub enum Limited {
+    /// A token bucket is empty
+    Rate { bucket: String, retry_after: Duration },
+    /// The caller used up its daily ingestion quota
+    Quota { principal: String, quota: u64, retry_after: Duration },
+}
+
+impl Limited {
+    pub fn retry_after(&self) -> Duration {
+        match self {
+            Limited::Rate { retry_after, .. } | Limited::Quota { retry_after, .. } => *retry_after,
+        }
+    }
+}
+
+/// Buckets kept before full ones are dropped; a full bucket is the same as a new one
+const MAX_BUCKETS: usize = 100_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// When the bucket is full again if left alone
+    full_at: Instant,
+}
+
+impl Bucket {
+    /// Take a token, or tell how long until one is available
+    fn take(&mut self, limit: RateLimit, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.rate).min(limit.burst as f64);
+        self.updated = now;
+        let result = if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            // Very low rates wait longer than a Duration holds
+            Err(seconds((1.0 - self.tokens) / limit.rate))
+        };
+        self.full_at = now.checked_add(seconds((limit.burst as f64 - self.tokens) / limit.rate)).unwrap_or(now);
+        result
+    }
+}
+
+fn seconds(secs: f64) -> Duration {
+    Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX)
+}
+
+/// Token buckets per route, per credential and per client IP, and daily ingestion counts per credential
+///
+/// State is kept in memory, per process: limits apply to each server
+/// separately and counts restart with the process.
+pub struct RateLimiter {
+    routes: Vec<RouteRateLimit>,
+    per_credential: Option<RateLimit>,
+    per_ip: Option<RateLimit>,
+    trusted_proxies: Vec<IpAddr>,
+    active: bool,
+    buckets: Mutex<HashMap<String, Bucket>>,
+    ingested: Mutex<HashMap<String, (NaiveDate, u64)>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig, auth: &AuthConfig) -> Self {
+        let key_limits = auth.enabled
+            && auth.api_keys.iter().any(|key| key.rate_limit.is_some() || key.daily_quota.is_some());
+        Self {
+            routes: config.routes.clone(),
+            per_credential: config.per_credential,
+            per_ip: config.per_ip,
+            trusted_proxies: config.trusted_proxies.clone(),
+            active: !config.routes.is_empty()
+                || config.per_credential.is_some()
+                || config.per_ip.is_some()
+                || key_limits,
+            buckets: Mutex::new(HashMap::new()),
+            ingested: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether any limit is configured
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Admit a request to `path`, counting it against the caller's quota if it ingests events
+    pub fn check(
+        &self,
+        path: &str,
+        principal: Option<&Principal>,
+        client: Option<IpAddr>,
+        ingest: bool,
+    ) -> Result<(), Limited> {
+        self.check_at(path, principal, client, ingest, Instant::now(), Utc::now())
+    }
+
+    /// Address of the client behind a connection from `peer`
+    ///
+    /// For a trusted proxy this is the last `X-Forwarded-For` address that is
+    /// not a trusted proxy itself; addresses before it could be made up by the
+    /// client.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.trusted_proxies.contains(&peer) {
+            return peer;
+        }
+        let forwarded: Vec<IpAddr> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|addr| addr.trim().parse().ok())
+            .collect();
+        forwarded
+            .iter()
+            .rev()
+            .find(|addr| !self.trusted_proxies.contains(addr))
+            .or(forwarded.first())
+            .copied()
+            .unwrap_or(peer)
+    }
+
+    fn check_at(
+        &self,
+        path: &str,
+        principal: Option<&Principal>,
+        client: Option<IpAddr>,
+        ingest: bool,
+        now: Instant,
+        today: DateTime<Utc>,
+    ) -> Result<(), Limited> {
+        let route = self
+            .routes
+            .iter()
+            .filter(|route| route.covers(path))
+            .max_by_key(|route| route.path.len());
+        let credential = principal.and_then(|p| p.rate_limit.or(self.per_credential).map(|limit| (p, limit)));
+
+        {
+            let mut buckets = self.buckets.lock().unwrap();
+            if buckets.len() >= MAX_BUCKETS {
+                buckets.retain(|_, bucket| bucket.full_at > now);
+            }
+            let mut take = |key: String, limit: RateLimit| {
+                let bucket = buckets.entry(key.clone()).or_insert(Bucket {
+                    tokens: limit.burst as f64,
+                    updated: now,
+                    full_at: now,
+                });
+                bucket
+                    .take(limit, now)
+                    .map_err(|retry_after| Limited::Rate { bucket: key, retry_after })
+            };
+            if let (Some(limit), Some(client)) = (self.per_ip, client) {
+                take(format!("ip:{}", client), limit)?;
+            }
+            if let Some(route) = route {
+                take(format!("route:{}", route.path), route.limit)?;
+            }
+            if let Some((principal, limit)) = credential {
+                take(format!("credential:{}", principal.name), limit)?;
+            }
+        }
+
+        if let (true, Some(principal)) = (ingest, principal) {
+            let date = today.date_naive();
+            let mut ingested = self.ingested.lock().unwrap();
+            let entry = ingested.entry(principal.name.clone()).or_insert((date, 0));
+            if entry.0 != date {
+                *entry = (date, 0);
+            }
+            if let Some(quota) = principal.daily_quota.filter(|quota| entry.1 >= *quota) {
+                let midnight = date
+                    .checked_add_days(Days::new(1))
+                    .and_then(|d| d.and_hms_opt(0, 0, 0))
+                    .map(|d| d.and_utc());
+                let retry_after = midnight
+                    .and_then(|m| (m - today).to_std().ok())
+                    .unwrap_or(Duration::from_secs(24 * 60 * 60));
+                return Err(Limited::Quota {
+                    principal: principal.name.clone(),
+                    quota,
+                    retry_after,
+                });
+            }
+            entry.1 += 1;
+        }
+        Ok(())
+    }
+
+    /// Ingestion requests per credential so far today (UTC)
+    pub fn ingested_today(&self) -> BTreeMap<String, u64> {
+        let today = Utc::now().date_naive();
+        self.ingested
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, (date, _))| *date == today)
+            .map(|(name, (_, count))| (name.clone(), *count))
+            .collect()
+    }
+}
+
+/// Middleware answering requests over a limit with 429 and `Retry-After`
+///
+/// Install it with `route_layer` inside the authentication layer, so the
+/// caller's [`Principal`] is known, and serve the router with
+/// `into_make_service_with_connect_info::<SocketAddr>()` for per-IP limits.
+pub async fn rate_limit(State(state): State<AppState>, request: Request<Body>, next: Next) -> Response {
+    let ingest = request
+        .extensions()
+        .get::<MatchedPath>()
+        .is_some_and(|path| required_scope(request.method(), path.as_str()) == Some(Scope::Ingest));
+    let principal = request.extensions().get::<Principal>();
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| state.rate_limiter.client_ip(peer.ip(), request.headers()));
+
+    let Err(limited) = state.rate_limiter.check(request.uri().path(), principal, client, ingest) else {
+        return next.run(request).await;
+    };
+
+    let retry_after_secs = limited.retry_after().as_secs_f64().ceil().max(1.0) as u64;
+    let message = match &limited {
+        Limited::Rate { bucket, .. } => {
+            state.metrics.increment_rate_limited().await;
+            info!(path = %request.uri().path(), bucket = %bucket, "Rejected request over rate limit");
+            format!("Rate limit exceeded, retry in {}s", retry_after_secs)
+        }
+        Limited::Quota { principal, quota, .. } => {
+            state.metrics.increment_over_quota().await;
+            info!(principal = %principal, quota, "Rejected request over daily quota");
+            format!("'{}' used its daily quota of {} ingestion requests", principal, quota)
+        }
+    };
+
+    NexusError::RateLimited { message, retry_after_secs }.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn principal(name: &str, rate_limit: Option<RateLimit>, daily_quota: Option<u64>) -> Principal {
+        Principal {
+            name: name.to_string(),
+            scopes: [Scope::Ingest].into(),
+            rate_limit,
+            daily_quota,
+        }
+    }
+
+    #[test]
+    fn test_token_buckets() {
+        let limiter = RateLimiter::new(
+            &RateLimitConfig {
+                routes: vec![RouteRateLimit {
+                    path: "/webhook".to_string(),
+                    limit: RateLimit { rate: 1.0, burst: 2 },
+                }],
+                per_credential: None,
+                ..Default::default()
+            },
+            &AuthConfig::default(),
+        );
+        let start = Instant::now();
+        let now = Utc::now();
+
+        assert!(limiter.check_at("/webhook/github", None, None, true, start, now).is_ok());
+        assert!(limiter.check_at("/webhook/stripe", None, None, true, start, now).is_ok());
+        let limited = limiter.check_at("/webhook/github", None, None, true, start, now).unwrap_err();
+        assert_eq!(limited.retry_after(), Duration::from_secs(1));
+        assert!(limiter.check_at("/events", None, None, true, start, now).is_ok());
+        assert!(limiter.check_at("/webhook/github", None, None, true, start + Duration::from_secs(1), now).is_ok());
+
+        // A credential's own limit applies across routes
+        let ci = principal("ci", Some(RateLimit { rate: 0.5, burst: 1 }), None);
+        assert!(limiter.check_at("/events", Some(&ci), None, true, start, now).is_ok());
+        assert!(matches!(
+            limiter.check_at("/events", Some(&ci), None, true, start, now),
+            Err(Limited::Rate { bucket, .. }) if bucket == "credential:ci"
+        ));
+    }
+
+    #[test]
+    fn test_daily_quota() {
+        let limiter = RateLimiter::new(&RateLimitConfig::default(), &AuthConfig::default());
+        let ci = principal("ci", None, Some(2));
+        let start = Instant::now();
+        let evening = "2026-03-01T23:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        assert!(limiter.check_at("/events", Some(&ci), None, true, start, evening).is_ok());
+        assert!(limiter.check_at("/events", Some(&ci), None, false, start, evening).is_ok());
+        assert!(limiter.check_at("/events", Some(&ci), None, true, start, evening).is_ok());
+        let limited = limiter.check_at("/events", Some(&ci), None, true, start, evening).unwrap_err();
+        assert_eq!(limited.retry_after(), Duration::from_secs(60 * 60));
+
+        let next_day = evening + chrono::Duration::hours(2);
+        assert!(limiter.check_at("/events", Some(&ci), None, true, start, next_day).is_ok());
+    }
+
+    #[test]
+    fn test_per_ip_limit_behind_trusted_proxy() {
+        let limiter = RateLimiter::new(
+            &RateLimitConfig {
+                per_ip: Some(RateLimit { rate: 1.0, burst: 1 }),
+                trusted_proxies: vec!["10.0.0.1".parse().unwrap()],
+                ..Default::default()
+            },
+            &AuthConfig::default(),
+        );
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "1.2.3.4, 5.6.7.8".parse().unwrap());
+
+        // Only the address the trusted proxy saw counts; earlier ones may be spoofed
+        let client = limiter.client_ip(proxy, &headers);
+        assert_eq!(client, "5.6.7.8".parse::<IpAddr>().unwrap());
+        let untrusted: IpAddr = "9.9.9.9".parse().unwrap();
+        assert_eq!(limiter.client_ip(untrusted, &headers), untrusted);
+
+        let start = Instant::now();
+        let now = Utc::now();
+        assert!(limiter.check_at("/events", None, Some(client), true, start, now).is_ok());
+        assert!(matches!(
+            limiter.check_at("/events", None, Some(client), true, start, now),
+            Err(Limited::Rate { bucket, .. }) if bucket == "ip:5.6.7.8"
+        ));
+        assert!(limiter.check_at("/events", None, Some(untrusted), true, start, now).is_ok());
+        assert!(limiter.check_at("/events", None, None, true, start, now).is_ok());
+    }
+}
@@ -0,0 +1,207 @@
+use crate::config::SchemaConfig;
+use anyhow::{Context, Result};
+use jsonschema::JSONSchema;
+use nexus_event_fabric::CloudEvent;
+pub use nexus_event_fabric::cloudevents::schema_urn;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchemaError {
+    #[error("Invalid JSON Schema: {0}")]
+    InvalidSchema(String),
+    #[error("Schema {event_type} version {version} is already registered with different content")]
+    VersionConflict { event_type: String, version: u32 },
+    #[error("Schema {event_type} version {version} is not registered")]
+    UnknownVersion { event_type: String, version: u32 },
+    #[error("Event data does not match {schema}: {}", .violations.join("; "))]
+    Violations { schema: String, violations: Vec<String> },
+    #[error("Event data must be JSON to be validated against {0}")]
+    NonJsonData(String),
+}
+
+/// One registered version of an event type's schema
+#[derive(Clone)]
+pub struct RegisteredSchema {
+    pub version: u32,
+    pub schema: Value,
+    compiled: Arc<JSONSchema>,
+}
+
+/// Versioned JSON Schemas for event `data`, keyed by event type
+///
+/// Event types without a schema are accepted as-is. Schemas registered at
+/// runtime are kept in memory; list them in `nexus.yaml` to keep them across
+/// restarts.
+#[derive(Default)]
+pub struct SchemaRegistry {
+    schemas: RwLock<HashMap<String, BTreeMap<u32, RegisteredSchema>>>,
+}
+
+impl SchemaRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the schema files listed in the configuration
+    pub fn from_config(schemas: &[SchemaConfig]) -> Result<Self> {
+        let registry = Self::new();
+        for entry in schemas {
+            let content = std::fs::read_to_string(&entry.path)
+                .with_context(|| format!("Failed to read schema file {}", entry.path))?;
+            let schema: Value = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse schema file {}", entry.path))?;
+            registry
+                .register(&entry.event_type, Some(entry.version), schema)
+                .with_context(|| format!("Failed to load schema file {}", entry.path))?;
+        }
+        Ok(registry)
+    }
+
+    /// Register a schema, returning its version
+    ///
+    /// Without an explicit version the schema becomes the latest version.
+    /// Registered versions are immutable; registering the same content again
+    /// is a no-op.
+    pub fn register(&self, event_type: &str, version: Option<u32>, schema: Value) -> Result<u32, SchemaError> {
+        let compiled = JSONSchema::compile(&schema).map_err(|e| SchemaError::InvalidSchema(e.to_string()))?;
+
+        let mut schemas = self.schemas.write().unwrap();
+        let versions = schemas.entry(event_type.to_string()).or_default();
+        let version = version.unwrap_or_else(|| versions.keys().next_back().map_or(1, |v| v + 1));
+
+        if let Some(existing) = versions.get(&version) {
+            if existing.schema != schema {
+                return Err(SchemaError::VersionConflict {
+                    event_type: event_type.to_string(),
+                    version,
+                });
+            }
+            return Ok(version);
+        }
+
+        versions.insert(version, RegisteredSchema {
+            version,
+            schema,
+            compiled: Arc::new(compiled),
+        });
+        Ok(version)
+    }
+
+    /// A specific version of a schema, or the latest one
+    pub fn get(&self, event_type: &str, version: Option<u32>) -> Option<RegisteredSchema> {
+        let schemas = self.schemas.read().unwrap();
+        let versions = schemas.get(event_type)?;
+        match version {
+            Some(version) => versions.get(&version).cloned(),
+            None => versions.values().next_back().cloned(),
+        }
+    }
+
+    /// Validate an event's data and set its `dataschema`
+    ///
+    /// An event whose `dataschema` already names a registered version is
+    /// validated against that version, any other event against the latest.
+    pub fn validate(&self, event: &mut CloudEvent) -> Result<(), SchemaError> {
+        let requested = event.schema_version();
+
+        let Some(registered) = self.get(&event.event_type, requested) else {
+            return match requested {
+                Some(version) => Err(SchemaError::UnknownVersion {
+                    event_type: event.event_type.clone(),
+                    version,
+                }),
+                None => Ok(()),
+            };
+        };
+
+        let urn = schema_urn(&event.event_type, registered.version);
+        if event.data_base64.is_some() {
+            return Err(SchemaError::NonJsonData(urn));
+        }
+
+        let data = event.data.clone().unwrap_or(Value::Null);
+        if let Err(errors) = registered.compiled.validate(&data) {
+            let violations = errors
+                .map(|e| {
+                    let path = e.instance_path.to_string();
+                    format!("{}: {}", if path.is_empty() { "/" } else { &path }, e)
+                })
+                .collect();
+            return Err(SchemaError::Violations { schema: urn, violations });
+        }
+
+        event.dataschema = Some(urn);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn order_schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["order_id", "total"],
+            "properties": {
+                "order_id": {"type": "string"},
+                "total": {"type": "number", "minimum": 0}
+            }
+        })
+    }
+
+    #[test]
+    fn test_validate_sets_dataschema_and_lists_violations() {
+        let registry = SchemaRegistry::new();
+        assert_eq!(registry.register("com.nexus.order.created", None, order_schema()).unwrap(), 1);
+
+        let mut valid = CloudEvent::new("com.nexus.order.created", "/test")
+            .with_data(json!({"order_id": "o-1", "total": 10}));
+        registry.validate(&mut valid).unwrap();
+        assert_eq!(valid.dataschema.as_deref(), Some("urn:nexus:schema:com.nexus.order.created:1"));
+
+        let mut invalid = CloudEvent::new("com.nexus.order.created", "/test")
+            .with_data(json!({"total": -1}));
+        match registry.validate(&mut invalid) {
+            Err(SchemaError::Violations { violations, .. }) => {
+                assert_eq!(violations.len(), 2);
+                assert!(violations.iter().any(|v| v.starts_with("/total:")));
+                assert!(violations.iter().any(|v| v.starts_with("/:")));
+            }
+            other => panic!("expected violations, got {:?}", other),
+        }
+
+        // Types without a schema are not validated
+        let mut other = CloudEvent::new("com.nexus.user.created", "/test").with_data(json!(1));
+        registry.validate(&mut other).unwrap();
+        assert!(other.dataschema.is_none());
+    }
+
+    #[test]
+    fn test_versions() {
+        let registry = SchemaRegistry::new();
+        registry.register("com.nexus.order.created", None, order_schema()).unwrap();
+        let v2 = registry
+            .register("com.nexus.order.created", None, json!({"type": "object"}))
+            .unwrap();
+        assert_eq!(v2, 2);
+        assert!(matches!(
+            registry.register("com.nexus.order.created", Some(1), json!({})),
+            Err(SchemaError::VersionConflict { .. })
+        ));
+
+        // The latest version applies unless the event names one
+        let mut event = CloudEvent::new("com.nexus.order.created", "/test").with_data(json!({}));
+        registry.validate(&mut event).unwrap();
+        assert_eq!(event.dataschema.as_deref(), Some("urn:nexus:schema:com.nexus.order.created:2"));
+
+        event.dataschema = Some(schema_urn("com.nexus.order.created", 1));
+        assert!(matches!(registry.validate(&mut event), Err(SchemaError::Violations { .. })));
+
+        event.dataschema = Some(schema_urn("com.nexus.order.created", 7));
+        assert!(matches!(registry.validate(&mut event), Err(SchemaError::UnknownVersion { .. })));
+    }
+}
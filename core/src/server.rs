@@ -1,18 +1,36 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    routing::{get, post},
+    body::Body,
+    extract::{DefaultBodyLimit, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
     Json, Router,
 };
-use nexus_event_fabric::CloudEvent;
-use nexus_observability::{RequestContext, with_context};
+use nexus_api_gateway::{BindingError, BindingMode, Ingested};
+use nexus_event_fabric::crypto::is_erased;
+use nexus_event_fabric::backend::PAGE_SIZE;
+use nexus_event_fabric::{ndjson, CloudEvent, ErasureLog, ErasureRecord, EventPage, IMPORT_EXTENSION};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use nexus_observability::{scope, with_context, RequestContext};
 use serde::{Deserialize, Serialize};
 use tower_http::trace::TraceLayer;
 use tracing::{info, error, debug, instrument};
 
-use crate::state::AppState;
-use crate::errors::{NexusError, error_response};
+use crate::auth::{authorize, Authenticator};
+use crate::body_limit::{limit_body, BodyLimits};
+use crate::extract::{ApiBytes, ApiJson, ApiPath, ApiQuery};
+use crate::ratelimit::rate_limit;
+use crate::state::{AppState, Dispatch};
+use crate::errors::NexusError;
 use crate::metrics::ExecutionTimer;
+use crate::partition::lane_for;
+use crate::schema::{schema_urn, SchemaError};
+
+/// Response header carrying the request's trace ID
+pub const TRACE_ID_HEADER: &str = "x-trace-id";
 
 pub struct Server {
     port: u16,
@@ -24,15 +42,10 @@ struct HealthResponse {
     status: String,
     version: String,
     nats_connected: bool,
+    event_backend: String,
     uptime_seconds: u64,
 }
 
-#[derive(Deserialize)]
-struct EventPayload {
-    #[serde(flatten)]
-    data: serde_json::Value,
-}
-
 #[derive(Serialize)]
 struct EventResponse {
     event_id: String,
@@ -40,6 +53,18 @@ struct EventResponse {
     event_type: String,
 }
 
+#[derive(Serialize)]
+#[serde(untagged)]
+enum IngestResponse {
+    Single(EventResponse),
+    /// Answer to a Slack `url_verification` request
+    Challenge { challenge: String },
+    Batch {
+        events: Vec<EventResponse>,
+        count: usize,
+    },
+}
+
 #[derive(Deserialize)]
 struct ListEventsQuery {
     #[serde(rename = "type")]
@@ -52,6 +77,20 @@ fn default_limit() -> usize {
     100
 }
 
+#[derive(Deserialize)]
+struct ExportQuery {
+    #[serde(rename = "type")]
+    event_type: Option<String>,
+    /// All stored events when unset
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct ImportResponse {
+    imported: usize,
+    skipped: usize,
+}
+
 #[derive(Serialize)]
 struct EventListResponse {
     events: Vec<CloudEvent>,
@@ -59,6 +98,25 @@ struct EventListResponse {
     total: u64,
 }
 
+#[derive(Debug, Deserialize)]
+struct SchemaQuery {
+    version: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct SchemaResponse {
+    event_type: String,
+    version: u32,
+    dataschema: String,
+    schema: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplayQuery {
+    /// Replay to this function only, without re-publishing the event
+    function: Option<String>,
+}
+
 #[derive(Serialize)]
 struct ReplayResponse {
     event_id: String,
@@ -91,16 +149,61 @@ impl Server {
             .route("/health", get(health_handler))
             .route("/metrics", get(metrics_handler))
             .route("/events", get(list_events_handler).post(event_handler_root))
+            .route("/events/export", get(export_events_handler))
+            .route("/events/import", post(import_events_handler))
             .route("/events/:event_id", get(get_event_handler))
             .route("/replay/:event_id", post(replay_handler))
             .route("/execute/:event_id", post(execute_handler))
             .route("/webhook/*path", post(event_handler))
+            .route("/schemas/:event_type", get(get_schema_handler).put(put_schema_handler))
+            .route("/subjects/erasures", get(list_erasures_handler))
+            .route("/subjects/:subject", delete(erase_subject_handler));
+
+        // Route layers run last-added first: authentication, then rate limits
+        let app = if self.state.rate_limiter.is_active() {
+            app.route_layer(middleware::from_fn_with_state(self.state.clone(), rate_limit))
+        } else {
+            app
+        };
+        let app = if self.state.config.auth.enabled {
+            let authenticator = Arc::new(
+                Authenticator::from_config(&self.state.config.auth)?.with_signed_webhooks(self.state.webhooks.clone()),
+            );
+            info!("Authentication enabled");
+            app.route_layer(middleware::from_fn_with_state(authenticator, authorize))
+        } else {
+            app
+        };
+        // Body limits apply per route below, in place of axum's single default
+        let body_limits = Arc::new(BodyLimits::new(self.state.config.body_limits.clone()));
+        let app = app
+            .layer(middleware::from_fn_with_state(body_limits, limit_body))
+            .layer(DefaultBodyLimit::disable())
+            .layer(middleware::from_fn(trace_request))
             .layer(TraceLayer::new_for_http())
             .with_state(self.state);
 
         let addr = format!("0.0.0.0:{}", self.port);
         info!("Starting server on {}", addr);
 
+        // Per-IP rate limits need the peer address of each connection
+        let listener = tokio::net::TcpListener::bind(&addr).await?;
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+
+        Ok(())
+    }
+
+    /// Serve only the health and metrics endpoints, for worker processes
+    pub async fn run_health_only(self) -> anyhow::Result<()> {
+        let app = Router::new()
+            .route("/health", get(health_handler))
+            .route("/metrics", get(metrics_handler))
+            .layer(middleware::from_fn(trace_request))
+            .with_state(self.state);
+
+        let addr = format!("0.0.0.0:{}", self.port);
+        info!("Starting health endpoint on {}", addr);
+
         let listener = tokio::net::TcpListener::bind(&addr).await?;
         axum::serve(listener, app).await?;
 
@@ -108,6 +211,17 @@ impl Server {
     }
 }
 
+/// Middleware giving each request a trace ID, current while it is served and returned in `X-Trace-Id`
+///
+/// Errors of the request carry the same ID in their `trace_id` field.
+async fn trace_request(request: Request, next: Next) -> Response {
+    let ctx = RequestContext::new();
+    let trace_id = HeaderValue::from_str(&ctx.trace_id).expect("UUIDs are valid header values");
+    let mut response = scope(ctx, next.run(request)).await;
+    response.headers_mut().insert(TRACE_ID_HEADER, trace_id);
+    response
+}
+
 #[instrument(skip(state))]
 async fn health_handler(State(state): State<AppState>) -> Json<HealthResponse> {
     let nats_connected = state.nats_client.read().await.is_connected();
@@ -123,6 +237,7 @@ async fn health_handler(State(state): State<AppState>) -> Json<HealthResponse> {
         status: "ok".to_string(),
         version: env!("CARGO_PKG_VERSION").to_string(),
         nats_connected,
+        event_backend: state.event_backend.name().to_string(),
         uptime_seconds: uptime,
     })
 }
@@ -132,138 +247,315 @@ async fn metrics_handler(State(state): State<AppState>) -> Json<crate::metrics::
     let uptime = state.start_time.elapsed().as_secs();
     state.metrics.update_uptime(uptime).await;
     
-    let metrics = state.metrics.get_metrics().await;
+    let mut metrics = state.metrics.get_metrics().await;
+    metrics.rate_limits.ingested_today = state.rate_limiter.ingested_today();
     debug!("Metrics retrieved: {:?}", metrics);
     
     Json(metrics)
 }
 
-#[instrument(skip(state, payload), fields(path, event_type, event_id))]
+#[instrument(skip(state, headers, body), fields(path, event_type, event_id))]
 async fn event_handler(
     State(state): State<AppState>,
-    Path(path): Path<String>,
-    Json(payload): Json<EventPayload>,
-) -> Result<Json<EventResponse>, (StatusCode, Json<crate::errors::ErrorResponse>)> {
-    let ctx = RequestContext::new();
+    ApiPath(path): ApiPath<String>,
+    headers: HeaderMap,
+    ApiBytes(body): ApiBytes,
+) -> Result<Json<IngestResponse>, NexusError> {
+    let ctx = RequestContext::current();
     with_context(&ctx);
     
     info!(path = %path, "Received event on webhook path");
 
-    // Extract event type from path (e.g., /webhook/user.created -> com.nexus.user.created)
-    let event_type = format!("com.nexus.{}", path.replace('/', "."));
-    
-    // Create CloudEvent
-    let cloud_event = CloudEvent::new(&event_type, "/api/webhook")
-        .with_data(payload.data);
+    let handler = &state.webhooks;
+    if handler.is_signed(&path) {
+        if let Err(e) = handler.verify(&path, &headers, &body) {
+            info!(path = %path, error = %e, "Rejected webhook with invalid signature");
+            state.metrics.record_webhook_signature(false).await;
+            return Err(NexusError::Unauthorized { message: e.to_string() });
+        }
+        state.metrics.record_webhook_signature(true).await;
+    }
 
-    let event_id = cloud_event.id.clone();
-    tracing::Span::current().record("event_type", &event_type.as_str());
-    tracing::Span::current().record("event_id", &event_id.as_str());
+    if let Some(challenge) = handler.slack_challenge(&path, &headers, &body) {
+        return Ok(Json(IngestResponse::Challenge { challenge }));
+    }
 
-    // Publish to NATS
-    match state.event_publisher.publish(&cloud_event).await {
-        Ok(_) => {
-            state.metrics.increment_events_published().await;
-            info!(event_id = %event_id, event_type = %event_type, "Event published successfully");
+    // Plain JSON bodies get their type from the path (e.g., /webhook/user.created -> com.nexus.user.created)
+    // or, on provider paths, from the provider's payload
+    let mode = handler.mode(&headers);
+    let events = handler
+        .to_cloud_events(&path, &headers, &body)
+        .map_err(binding_error)?;
+
+    // Providers retry with the same delivery ID; publish each delivery once
+    let once = handler.provider(&path).is_some();
+    publish_events(&state, events, mode == BindingMode::Batch, once).await
+}
+
+async fn event_handler_root(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ApiBytes(body): ApiBytes,
+) -> Result<Json<IngestResponse>, NexusError> {
+    let ctx = RequestContext::current();
+    with_context(&ctx);
+    
+    info!("Received event on root /events endpoint");
+
+    let handler = &state.webhooks;
+    let mode = handler.mode(&headers);
+    let events = match handler.decode(&headers, &body).map_err(binding_error)? {
+        Ingested::Events(events) => events,
+        Ingested::Plain(mut data) => {
+            // Extract event type from payload if provided, otherwise use generic
+            let event_type = data
+                .get("event_type")
+                .and_then(|v| v.as_str())
+                .unwrap_or("generic.event")
+                .to_string();
             
-            // Execute matching functions asynchronously (fire and forget)
-            let executor = state.function_executor.clone();
-            let event_clone = cloud_event.clone();
-            let metrics = state.metrics.clone();
+            // Remove event_type from data if it exists
+            data.as_object_mut().map(|obj| obj.remove("event_type"));
             
-            tokio::spawn(async move {
-                let timer = ExecutionTimer::start();
-                match executor.execute_matching_functions(&event_clone).await {
-                    Ok(results) => {
-                        let duration = timer.elapsed_ms();
-                        metrics.record_function_execution(duration, true).await;
-                        info!(
-                            event_id = %event_clone.id,
-                            functions_executed = results.len(),
-                            duration_ms = duration,
-                            "Functions executed successfully"
-                        );
-                    }
-                    Err(e) => {
-                        let duration = timer.elapsed_ms();
-                        metrics.record_function_execution(duration, false).await;
-                        error!(
-                            event_id = %event_clone.id,
-                            error = %e,
-                            duration_ms = duration,
-                            "Function execution failed"
-                        );
-                    }
-                }
+            vec![CloudEvent::new(format!("com.nexus.{}", event_type), "/api/events").with_data(data)]
+        }
+        // Raw bodies carry no type; use /webhook/{event_type} to set one
+        Ingested::Raw { content_type, body } => vec![
+            CloudEvent::new("com.nexus.generic.event", "/api/events").with_binary_data(&body, content_type)
+        ],
+    };
+
+    publish_events(&state, events, mode == BindingMode::Batch, false).await
+}
+
+/// Publish ingested events and run matching functions for each
+///
+/// With `once`, events whose ID is already stored are skipped and reported
+/// as duplicates.
+async fn publish_events(
+    state: &AppState,
+    mut events: Vec<CloudEvent>,
+    batch: bool,
+    once: bool,
+) -> Result<Json<IngestResponse>, NexusError> {
+    // Validate the whole batch before publishing any of it
+    for event in &mut events {
+        if let Err(e) = state.schemas.validate(event) {
+            info!(event_id = %event.id, event_type = %event.event_type, error = %e, "Rejected event failing schema validation");
+            return Err(NexusError::InvalidInput {
+                field: "data".to_string(),
+                message: format!("Event {}: {}", event.id, e),
             });
-            
-            Ok(Json(EventResponse {
+        }
+    }
+
+    let mut published = Vec::with_capacity(events.len());
+
+    for cloud_event in events {
+        let event_id = cloud_event.id.clone();
+        let event_type = cloud_event.event_type.clone();
+        tracing::Span::current().record("event_type", event_type.as_str());
+        tracing::Span::current().record("event_id", event_id.as_str());
+
+        // Publish to NATS; events that aged out of the stream are found in the archive
+        let result = if once {
+            match &state.archive {
+                Some(archive) => match archive.get_event_by_id(&event_id).await {
+                    Ok(Some(_)) => Ok(false),
+                    Ok(None) => state.event_publisher.publish_once(&cloud_event).await,
+                    Err(e) => Err(e),
+                },
+                None => state.event_publisher.publish_once(&cloud_event).await,
+            }
+        } else {
+            state.event_publisher.publish(&cloud_event).await.map(|()| true)
+        };
+        let stored = match result {
+            Ok(stored) => stored,
+            Err(e) => {
+                state.metrics.increment_events_failed().await;
+                error!(event_id = %event_id, error = %e, "Failed to publish event");
+                return Err(NexusError::NatsError {
+                    message: format!(
+                        "Failed to publish event {} ({} of the batch already published): {}",
+                        event_id,
+                        published.len(),
+                        e
+                    ),
+                });
+            }
+        };
+
+        if !stored {
+            info!(event_id = %event_id, "Skipped duplicate webhook delivery");
+            published.push(EventResponse {
                 event_id,
-                status: "published".to_string(),
+                status: "duplicate".to_string(),
                 event_type,
-            }))
+            });
+            continue;
         }
-        Err(e) => {
-            state.metrics.increment_events_failed().await;
-            error!(event_id = %event_id, error = %e, "Failed to publish event");
-            Err(error_response(
-                NexusError::NatsError {
-                    message: format!("Failed to publish event: {}", e),
-                },
-                Some(ctx.trace_id)
-            ))
+
+        state.metrics.increment_events_published().await;
+        info!(event_id = %event_id, event_type = %event_type, "Event published successfully");
+        
+        // Execute matching functions asynchronously (fire and forget); durable workers run them otherwise
+        if state.dispatch == Dispatch::Inline {
+            dispatch_matching_functions(state, &cloud_event);
         }
+
+        published.push(EventResponse {
+            event_id,
+            status: "published".to_string(),
+            event_type,
+        });
+    }
+
+    if batch {
+        Ok(Json(IngestResponse::Batch {
+            count: published.len(),
+            events: published,
+        }))
+    } else {
+        let event = published.pop().expect("non-batch ingestion yields one event");
+        Ok(Json(IngestResponse::Single(event)))
     }
 }
 
-async fn event_handler_root(
+fn binding_error(error: BindingError) -> NexusError {
+    let field = match &error {
+        BindingError::MissingAttribute(name) => name.clone(),
+        BindingError::InvalidAttribute { name, .. } => name.clone(),
+        BindingError::Invalid(_) => "event".to_string(),
+        BindingError::InvalidBody(_) => "body".to_string(),
+        BindingError::Provider { field, .. } => field.clone(),
+        BindingError::Transform(_) => "transform".to_string(),
+    };
+    info!(error = %error, "Rejected malformed event");
+    NexusError::InvalidInput {
+        field,
+        message: error.to_string(),
+    }
+}
+
+async fn put_schema_handler(
     State(state): State<AppState>,
-    Json(mut payload): Json<EventPayload>,
-) -> Result<Json<EventResponse>, StatusCode> {
-    info!("Received event on root /events endpoint");
+    ApiPath(event_type): ApiPath<String>,
+    ApiQuery(query): ApiQuery<SchemaQuery>,
+    ApiJson(schema): ApiJson<serde_json::Value>,
+) -> Result<(StatusCode, Json<SchemaResponse>), NexusError> {
+    let ctx = RequestContext::current();
+    with_context(&ctx);
 
-    // Extract event type from payload if provided, otherwise use generic
-    let event_type = payload
-        .data
-        .get("event_type")
-        .and_then(|v| v.as_str())
-        .unwrap_or("generic.event")
-        .to_string();
-    
-    // Remove event_type from data if it exists
-    payload.data.as_object_mut().map(|obj| obj.remove("event_type"));
-    
-    let full_event_type = format!("com.nexus.{}", event_type);
-    
-    // Create CloudEvent
-    let cloud_event = CloudEvent::new(&full_event_type, "/api/events")
-        .with_data(payload.data);
+    let version = state
+        .schemas
+        .register(&event_type, query.version, schema.clone())
+        .map_err(|e| {
+            let field = match e {
+                SchemaError::VersionConflict { .. } => "version",
+                _ => "schema",
+            };
+            NexusError::InvalidInput {
+                field: field.to_string(),
+                message: e.to_string(),
+            }
+        })?;
+
+    info!(event_type = %event_type, version, "Schema registered");
+    Ok((StatusCode::CREATED, Json(SchemaResponse {
+        dataschema: schema_urn(&event_type, version),
+        event_type,
+        version,
+        schema,
+    })))
+}
 
-    let event_id = cloud_event.id.clone();
+async fn get_schema_handler(
+    State(state): State<AppState>,
+    ApiPath(event_type): ApiPath<String>,
+    ApiQuery(query): ApiQuery<SchemaQuery>,
+) -> Result<Json<SchemaResponse>, NexusError> {
+    let ctx = RequestContext::current();
+    with_context(&ctx);
 
-    // Publish to NATS
-    match state.event_publisher.publish(&cloud_event).await {
-        Ok(_) => {
-            info!("Event {} published successfully", event_id);
-            Ok(Json(EventResponse {
-                event_id,
-                status: "published".to_string(),
-                event_type: full_event_type,
-            }))
+    let Some(registered) = state.schemas.get(&event_type, query.version) else {
+        let id = match query.version {
+            Some(version) => format!("{} version {}", event_type, version),
+            None => event_type,
+        };
+        return Err(NexusError::NotFound {
+            resource: "Schema".to_string(),
+            id,
+        });
+    };
+
+    Ok(Json(SchemaResponse {
+        dataschema: schema_urn(&event_type, registered.version),
+        event_type,
+        version: registered.version,
+        schema: registered.schema,
+    }))
+}
+
+/// Erase a subject's data by destroying its encryption keys (crypto-shredding)
+async fn erase_subject_handler(
+    State(state): State<AppState>,
+    ApiPath(subject): ApiPath<String>,
+) -> Result<Json<ErasureRecord>, NexusError> {
+    let ctx = RequestContext::current();
+    with_context(&ctx);
+
+    let (Some(encryptor), Some(encryption)) = (&state.encryptor, &state.config.encryption) else {
+        return Err(NexusError::InvalidInput {
+            field: "subject".to_string(),
+            message: "Subject erasure needs an encryption section in nexus.yaml".to_string(),
+        });
+    };
+
+    let internal = |e: anyhow::Error| {
+        error!(error = %e, "Failed to erase subject");
+        NexusError::InternalError {
+            message: format!("Failed to erase subject: {:#}", e),
         }
-        Err(e) => {
-            error!("Failed to publish event: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+    };
+    let keys_destroyed = encryptor.erase_subject(&subject).map_err(internal)?;
+
+    // Requests for subjects without keys are recorded too, as proof the request was handled
+    let record = ErasureRecord {
+        subject,
+        keys_destroyed,
+        erased_at: chrono::Utc::now(),
+        trace_id: Some(ctx.trace_id.clone()),
+    };
+    ErasureLog::new(&encryption.erasure_log).append(&record).map_err(internal)?;
+
+    info!(keys_destroyed, "Subject erased");
+    Ok(Json(record))
+}
+
+/// Audit trail of subject erasures
+async fn list_erasures_handler(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ErasureRecord>>, NexusError> {
+    let ctx = RequestContext::current();
+    with_context(&ctx);
+
+    let Some(encryption) = &state.config.encryption else {
+        return Ok(Json(vec![]));
+    };
+    ErasureLog::new(&encryption.erasure_log).records().map(Json).map_err(|e| {
+        NexusError::InternalError {
+            message: format!("Failed to read erasure log: {:#}", e),
         }
-    }
+    })
 }
 
 #[instrument(skip(state), fields(event_id))]
 async fn get_event_handler(
     State(state): State<AppState>,
-    Path(event_id): Path<String>,
-) -> Result<Json<CloudEvent>, (StatusCode, Json<crate::errors::ErrorResponse>)> {
-    let ctx = RequestContext::new().with_event_id(event_id.clone());
+    ApiPath(event_id): ApiPath<String>,
+) -> Result<Json<CloudEvent>, NexusError> {
+    let ctx = RequestContext::current().with_event_id(event_id.clone());
     with_context(&ctx);
     
     debug!(event_id = %event_id, "Retrieving event");
@@ -275,42 +567,38 @@ async fn get_event_handler(
         }
         Ok(None) => {
             info!(event_id = %event_id, "Event not found");
-            Err(error_response(
-                NexusError::NotFound {
-                    resource: "Event".to_string(),
-                    id: event_id,
-                },
-                Some(ctx.trace_id)
-            ))
+            Err(NexusError::NotFound {
+                resource: "Event".to_string(),
+                id: event_id,
+            })
         }
         Err(e) => {
             error!(event_id = %event_id, error = %e, "Failed to retrieve event");
-            Err(error_response(
-                NexusError::InternalError {
-                    message: format!("Failed to retrieve event: {}", e),
-                },
-                Some(ctx.trace_id)
-            ))
+            Err(NexusError::InternalError {
+                message: format!("Failed to retrieve event: {}", e),
+            })
         }
     }
 }
 
 async fn list_events_handler(
     State(state): State<AppState>,
-    Query(params): Query<ListEventsQuery>,
-) -> Result<Json<EventListResponse>, StatusCode> {
+    ApiQuery(params): ApiQuery<ListEventsQuery>,
+) -> Result<Json<EventListResponse>, NexusError> {
+    let ctx = RequestContext::current();
+    with_context(&ctx);
+
     info!(
         "Listing events: type={:?}, limit={}",
         params.event_type, params.limit
     );
 
-    let total = match state.event_store.get_event_count().await {
-        Ok(count) => count,
-        Err(e) => {
-            error!("Failed to get event count: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    let total = state.event_store.get_event_count().await.map_err(|e| {
+        error!("Failed to get event count: {}", e);
+        NexusError::InternalError {
+            message: format!("Failed to count events: {}", e),
         }
-    };
+    })?;
 
     match state
         .event_store
@@ -328,7 +616,9 @@ async fn list_events_handler(
         }
         Err(e) => {
             error!("Failed to list events: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(NexusError::InternalError {
+                message: format!("Failed to list events: {}", e),
+            })
         }
     }
 }
@@ -336,9 +626,10 @@ async fn list_events_handler(
 #[instrument(skip(state), fields(event_id))]
 async fn replay_handler(
     State(state): State<AppState>,
-    Path(event_id): Path<String>,
-) -> Result<Json<ReplayResponse>, (StatusCode, Json<crate::errors::ErrorResponse>)> {
-    let ctx = RequestContext::new().with_event_id(event_id.clone());
+    ApiPath(event_id): ApiPath<String>,
+    ApiQuery(params): ApiQuery<ReplayQuery>,
+) -> Result<Json<ReplayResponse>, NexusError> {
+    let ctx = RequestContext::current().with_event_id(event_id.clone());
     with_context(&ctx);
     
     info!(event_id = %event_id, "Replaying event");
@@ -348,61 +639,58 @@ async fn replay_handler(
         Ok(Some(event)) => event,
         Ok(None) => {
             info!(event_id = %event_id, "Event not found for replay");
-            return Err(error_response(
-                NexusError::NotFound {
-                    resource: "Event".to_string(),
-                    id: event_id,
-                },
-                Some(ctx.trace_id)
-            ));
+            return Err(NexusError::NotFound {
+                resource: "Event".to_string(),
+                id: event_id,
+            });
         }
         Err(e) => {
             error!(event_id = %event_id, error = %e, "Failed to retrieve event for replay");
-            return Err(error_response(
-                NexusError::InternalError {
-                    message: format!("Failed to retrieve event: {}", e),
-                },
-                Some(ctx.trace_id)
-            ));
+            return Err(NexusError::InternalError {
+                message: format!("Failed to retrieve event: {}", e),
+            });
+        }
+    };
+
+    // Erased data is never processed again
+    if is_erased(&event) {
+        info!(event_id = %event_id, "Skipping replay of erased event");
+        return Ok(Json(ReplayResponse {
+            event_id,
+            status: "skipped".to_string(),
+            message: "Event data was erased".to_string(),
+        }));
+    }
+
+    // A replayed import runs like a new event
+    let mut event = event;
+    event.extensions.remove(IMPORT_EXTENSION);
+
+    // Functions see the latest shape; the stored event is re-published otherwise unchanged
+    let latest = match state.event_store.upcast(event.clone()) {
+        Ok(latest) => latest,
+        Err(e) => {
+            error!(event_id = %event_id, error = %e, "Failed to upcast event for replay");
+            return Err(NexusError::InternalError {
+                message: format!("Failed to upcast event: {:#}", e),
+            });
         }
     };
 
+    if let Some(function_name) = params.function {
+        return replay_to_function(state, latest, function_name).await;
+    }
+
     // Re-publish the event to NATS
     match state.event_publisher.publish(&event).await {
         Ok(_) => {
             state.metrics.increment_events_replayed().await;
             info!(event_id = %event_id, event_type = %event.event_type, "Event replayed successfully");
             
-            // Execute functions asynchronously
-            let executor = state.function_executor.clone();
-            let event_clone = event.clone();
-            let metrics = state.metrics.clone();
-            
-            tokio::spawn(async move {
-                let timer = ExecutionTimer::start();
-                match executor.execute_matching_functions(&event_clone).await {
-                    Ok(results) => {
-                        let duration = timer.elapsed_ms();
-                        metrics.record_function_execution(duration, true).await;
-                        info!(
-                            event_id = %event_clone.id,
-                            functions_executed = results.len(),
-                            duration_ms = duration,
-                            "Replayed event triggered functions"
-                        );
-                    }
-                    Err(e) => {
-                        let duration = timer.elapsed_ms();
-                        metrics.record_function_execution(duration, false).await;
-                        error!(
-                            event_id = %event_clone.id,
-                            error = %e,
-                            duration_ms = duration,
-                            "Function execution failed for replayed event"
-                        );
-                    }
-                }
-            });
+            // Execute functions asynchronously; durable workers pick up the re-published event otherwise
+            if state.dispatch == Dispatch::Inline {
+                dispatch_matching_functions(&state, &latest);
+            }
             
             Ok(Json(ReplayResponse {
                 event_id,
@@ -413,34 +701,250 @@ async fn replay_handler(
         Err(e) => {
             state.metrics.increment_events_failed().await;
             error!(event_id = %event_id, error = %e, "Failed to replay event");
-            Err(error_response(
-                NexusError::NatsError {
-                    message: format!("Failed to replay event: {}", e),
-                },
-                Some(ctx.trace_id)
-            ))
+            Err(NexusError::NatsError {
+                message: format!("Failed to replay event: {}", e),
+            })
         }
     }
 }
 
+/// Stream stored events as NDJSON, one CloudEvent per line
+async fn export_events_handler(
+    State(state): State<AppState>,
+    ApiQuery(params): ApiQuery<ExportQuery>,
+) -> Result<Response, NexusError> {
+    let ctx = RequestContext::current();
+    with_context(&ctx);
+
+    // The first page is read up front, so an unavailable backend is still an error response
+    let limit = params.limit.unwrap_or(usize::MAX);
+    let first = state
+        .event_store
+        .read_page(params.event_type.as_deref(), 1, limit.min(PAGE_SIZE))
+        .await
+        .map_err(|e| {
+            error!(error = %e, "Failed to read events for export");
+            NexusError::InternalError {
+                message: format!("Failed to read events: {}", e),
+            }
+        })?;
+
+    info!(event_type = ?params.event_type, limit = ?params.limit, "Exporting events");
+
+    // Then one page at a time, each written out before the next is read
+    let event_type = params.event_type;
+    let pages = futures::stream::unfold(
+        (Some(first), None, limit),
+        move |(page, from, remaining): (Option<EventPage>, Option<u64>, usize)| {
+            let state = state.clone();
+            let event_type = event_type.clone();
+            async move {
+                if remaining == 0 {
+                    return None;
+                }
+                let page = match page {
+                    Some(page) => page,
+                    None => match state.event_store.read_page(event_type.as_deref(), from?, remaining.min(PAGE_SIZE)).await {
+                        Ok(page) => page,
+                        Err(e) => {
+                            error!(error = %e, "Failed to read events for export");
+                            return Some((Err(std::io::Error::other(e.to_string())), (None, None, 0)));
+                        }
+                    },
+                };
+
+                let mut lines = Vec::new();
+                let mut count = 0;
+                for event in page.events.iter().take(remaining) {
+                    match ndjson::encode_line(event) {
+                        Ok(line) => lines.extend(line),
+                        Err(e) => return Some((Err(std::io::Error::other(e.to_string())), (None, None, 0))),
+                    }
+                    count += 1;
+                }
+                Some((Ok(lines), (None, page.next, remaining - count)))
+            }
+        },
+    );
+
+    Ok((
+        [(header::CONTENT_TYPE, ndjson::NDJSON_CONTENT_TYPE)],
+        Body::from_stream(pages),
+    )
+        .into_response())
+}
+
+/// Store events from an NDJSON archive (optionally gzipped), skipping IDs already stored
+///
+/// Imported events are marked with `nexusimported`, so neither inline dispatch
+/// nor durable workers run functions on them; replay them to run functions.
+async fn import_events_handler(
+    State(state): State<AppState>,
+    ApiBytes(body): ApiBytes,
+) -> Result<Json<ImportResponse>, NexusError> {
+    let ctx = RequestContext::current();
+    with_context(&ctx);
+
+    let events = ndjson::decode(&body).map_err(|e| {
+        info!(error = %e, "Rejected malformed import archive");
+        NexusError::InvalidInput {
+            field: "body".to_string(),
+            message: format!("{:#}", e),
+        }
+    })?;
+
+    // One pass over the stored events finds the IDs already there
+    let ids: HashSet<String> = events.iter().map(|event| event.id.clone()).collect();
+    let stored = state.event_store.find_stored(&ids).await.map_err(|e| {
+        error!(error = %e, "Failed to check for stored events before import");
+        NexusError::InternalError {
+            message: format!("Failed to check for stored events: {}", e),
+        }
+    })?;
+
+    let mut seen = HashSet::new();
+    let mut imported = 0;
+    let mut skipped = 0;
+    for event in events {
+        if stored.contains(&event.id) || !seen.insert(event.id.clone()) {
+            skipped += 1;
+            continue;
+        }
+
+        if let Err(e) = state.event_publisher.publish(&event.as_imported()).await {
+            state.metrics.increment_events_failed().await;
+            error!(event_id = %event.id, error = %e, "Failed to import event");
+            return Err(NexusError::NatsError {
+                message: format!(
+                    "Failed to import event {} ({} already imported): {}",
+                    event.id, imported, e
+                ),
+            });
+        }
+        imported += 1;
+    }
+
+    info!(imported, skipped, "Imported events");
+    Ok(Json(ImportResponse { imported, skipped }))
+}
+
+/// Replay an event to a single function without re-publishing it
+async fn replay_to_function(
+    state: AppState,
+    event: CloudEvent,
+    function_name: String,
+) -> Result<Json<ReplayResponse>, NexusError> {
+    if !state.function_executor.has_function(&function_name) {
+        info!(function = %function_name, "Function not found for replay");
+        return Err(NexusError::NotFound {
+            resource: "Function".to_string(),
+            id: function_name,
+        });
+    }
+
+    let event_id = event.id.clone();
+    let replay_event = event.as_replay();
+    state.metrics.increment_events_replayed().await;
+
+    info!(
+        event_id = %event_id,
+        function = %function_name,
+        "Replaying event to a single function"
+    );
+
+    spawn_execution(&state, function_name.clone(), replay_event);
+
+    Ok(Json(ReplayResponse {
+        event_id,
+        status: "replayed".to_string(),
+        message: format!("Replayed to function: {}", function_name),
+    }))
+}
+
+/// Run every function matching an event in the background
+fn dispatch_matching_functions(state: &AppState, event: &CloudEvent) {
+    let matching_functions = state.function_executor.find_matching_functions(&event.event_type);
+    if matching_functions.is_empty() {
+        debug!(event_type = %event.event_type, "No functions matched event");
+    }
+
+    for function_name in matching_functions {
+        spawn_execution(state, function_name, event.clone());
+    }
+}
+
+/// Run a function in the background, one event at a time per partition key
+fn spawn_execution(state: &AppState, function_name: String, event: CloudEvent) {
+    let executor = state.function_executor.clone();
+    let metrics = state.metrics.clone();
+    let lane = lane_for(&function_name, executor.partition_key(&function_name, &event));
+
+    state.partitions.dispatch(lane, async move {
+        let timer = ExecutionTimer::start();
+        match executor.execute_function(&function_name, &event).await {
+            Ok(output) => {
+                let duration = timer.elapsed_ms();
+                metrics.record_function_execution(duration, true).await;
+                info!(
+                    event_id = %event.id,
+                    function = %function_name,
+                    replay = event.is_replay(),
+                    output_size = output.len(),
+                    duration_ms = duration,
+                    "Function executed successfully"
+                );
+            }
+            Err(e) => {
+                let duration = timer.elapsed_ms();
+                metrics.record_function_execution(duration, false).await;
+                error!(
+                    event_id = %event.id,
+                    function = %function_name,
+                    replay = event.is_replay(),
+                    error = %e,
+                    duration_ms = duration,
+                    "Function execution failed"
+                );
+            }
+        }
+    });
+}
+
 async fn execute_handler(
     State(state): State<AppState>,
-    Path(event_id): Path<String>,
-) -> Result<Json<FunctionExecutionResponse>, StatusCode> {
+    ApiPath(event_id): ApiPath<String>,
+) -> Result<Json<FunctionExecutionResponse>, NexusError> {
+    let ctx = RequestContext::current().with_event_id(event_id.clone());
+    with_context(&ctx);
+
     info!("Executing functions for event: {}", event_id);
 
-    // Retrieve the event
-    let event = match state.event_store.get_event_by_id(&event_id).await {
+    // Retrieve the event in its latest shape
+    let event = match state.event_store.get_upcast_event_by_id(&event_id).await {
         Ok(Some(event)) => event,
         Ok(None) => {
-            return Err(StatusCode::NOT_FOUND);
+            return Err(NexusError::NotFound {
+                resource: "Event".to_string(),
+                id: event_id,
+            });
         }
         Err(e) => {
             error!("Failed to retrieve event: {}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(NexusError::InternalError {
+                message: format!("Failed to retrieve event: {:#}", e),
+            });
         }
     };
 
+    if is_erased(&event) {
+        info!("Skipping execution of erased event {}", event_id);
+        return Ok(Json(FunctionExecutionResponse {
+            event_id,
+            status: "skipped".to_string(),
+            functions_executed: vec![],
+        }));
+    }
+
     // Execute matching functions
     match state.function_executor.execute_matching_functions(&event).await {
         Ok(results) => {
@@ -467,7 +971,9 @@ async fn execute_handler(
         }
         Err(e) => {
             error!("Function execution failed: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(NexusError::InternalError {
+                message: format!("Function execution failed: {:#}", e),
+            })
         }
     }
 }
@@ -1,40 +1,154 @@
-use crate::config::NexusConfig;
+use crate::config::{EventBackendKind, NexusConfig};
 use crate::executor::FunctionExecutor;
 use crate::metrics::MetricsCollector;
-use nexus_event_fabric::{EventPublisher, EventStore, NatsClient};
+use crate::partition::PartitionDispatcher;
+use crate::ratelimit::RateLimiter;
+use crate::schema::SchemaRegistry;
+use nexus_event_fabric::{
+    Encryptor, EventArchive, EventBackend, EventPublisher, EventStore, FileEventLog, FileLogConfig, InMemoryBackend,
+    JetStreamBackend, NatsClient, Upcasters,
+};
+use nexus_api_gateway::WebhookHandler;
+use anyhow::Context;
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// How functions are run for published events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dispatch {
+    /// Spawned by the request handler after publishing
+    Inline,
+    /// Run by durable consumer workers (see [`crate::worker`])
+    Durable,
+}
 
 /// Shared application state
 #[derive(Clone)]
 pub struct AppState {
     pub config: Arc<NexusConfig>,
     pub nats_client: Arc<RwLock<NatsClient>>,
+    pub event_backend: Arc<dyn EventBackend>,
     pub event_publisher: Arc<EventPublisher>,
     pub event_store: Arc<EventStore>,
+    pub archive: Option<Arc<EventArchive>>,
+    pub encryptor: Option<Arc<Encryptor>>,
     pub function_executor: Arc<FunctionExecutor>,
+    pub schemas: Arc<SchemaRegistry>,
+    pub webhooks: Arc<WebhookHandler>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub dispatch: Dispatch,
+    pub partitions: PartitionDispatcher,
     pub metrics: MetricsCollector,
     pub start_time: Instant,
 }
 
 impl AppState {
-    pub fn new(config: NexusConfig, nats_client: Arc<RwLock<NatsClient>>) -> Result<Self, anyhow::Error> {
+    /// Create the application state, selecting the event backend from `config.events`
+    pub async fn new(config: NexusConfig, nats_client: Arc<RwLock<NatsClient>>) -> Result<Self, anyhow::Error> {
+        let event_backend = select_backend(&config, &nats_client).await?;
+        Self::with_backend(config, nats_client, event_backend)
+    }
+
+    /// Create the application state with an explicit event backend
+    pub fn with_backend(
+        config: NexusConfig,
+        nats_client: Arc<RwLock<NatsClient>>,
+        event_backend: Arc<dyn EventBackend>,
+    ) -> Result<Self, anyhow::Error> {
         let config = Arc::new(config);
-        let event_publisher = Arc::new(EventPublisher::new(nats_client.clone()));
-        let event_store = Arc::new(EventStore::new(nats_client.clone(), "events".to_string()));
+        let encryptor = match &config.encryption {
+            Some(encryption) => Some(Arc::new(
+                Encryptor::from_config(encryption)
+                    .context("Failed to set up encryption (create a keyring with `nexus keys init`)")?,
+            )),
+            None => None,
+        };
+        let mut event_publisher = EventPublisher::new(event_backend.clone())
+            .with_partitions(config.consumers.partitions, config.publish_partition_key());
+        let archive = config
+            .archive
+            .enabled
+            .then(|| Arc::new(EventArchive::open(&config.archive.path)));
+        let mut event_store = EventStore::new(event_backend.clone()).with_upcasters(Upcasters::new(config.upcasters.clone())?);
+        if let Some(archive) = &archive {
+            event_store = event_store.with_archive(archive.clone());
+        }
+        if let Some(encryptor) = &encryptor {
+            event_publisher = event_publisher.with_encryptor(encryptor.clone());
+            event_store = event_store.with_encryptor(encryptor.clone());
+        }
+        let event_publisher = Arc::new(event_publisher);
+        let event_store = Arc::new(event_store);
         let function_executor = Arc::new(FunctionExecutor::new(config.clone())?);
+        let schemas = Arc::new(SchemaRegistry::from_config(&config.schemas)?);
+        let webhooks = Arc::new(WebhookHandler::new().with_webhooks(config.webhooks.clone())?);
+        let rate_limiter = Arc::new(RateLimiter::new(&config.rate_limits, &config.auth));
+        let dispatch = if event_backend.name() == "jetstream" {
+            Dispatch::Durable
+        } else {
+            Dispatch::Inline
+        };
         let metrics = MetricsCollector::new();
         let start_time = Instant::now();
         
         Ok(Self {
             config,
             nats_client,
+            event_backend,
             event_publisher,
             event_store,
+            archive,
+            encryptor,
             function_executor,
+            schemas,
+            webhooks,
+            rate_limiter,
+            dispatch,
+            partitions: PartitionDispatcher::new(),
             metrics,
             start_time,
         })
     }
 }
+
+async fn select_backend(
+    config: &NexusConfig,
+    nats_client: &Arc<RwLock<NatsClient>>,
+) -> Result<Arc<dyn EventBackend>, anyhow::Error> {
+    let stream = config.event_stream();
+    let capacity = usize::try_from(stream.max_messages).unwrap_or(usize::MAX);
+
+    let jetstream = || -> Arc<dyn EventBackend> {
+        Arc::new(
+            JetStreamBackend::new(nats_client.clone(), stream.name.clone())
+                .with_format(stream.format)
+                .with_partitions(config.consumers.partitions),
+        )
+    };
+    let memory = || -> Arc<dyn EventBackend> { Arc::new(InMemoryBackend::with_capacity(capacity)) };
+
+    let backend = match config.events.backend {
+        EventBackendKind::Jetstream => jetstream(),
+        EventBackendKind::Memory => memory(),
+        EventBackendKind::File => {
+            let log_config = FileLogConfig {
+                fsync: config.events.fsync,
+                ..FileLogConfig::from(&stream)
+            };
+            Arc::new(FileEventLog::open(&config.events.path, log_config)?)
+        }
+        EventBackendKind::Auto => {
+            if nats_client.read().await.is_connected() {
+                jetstream()
+            } else {
+                warn!("NATS not connected, falling back to the in-memory event backend");
+                memory()
+            }
+        }
+    };
+
+    info!("Using '{}' event backend", backend.name());
+    Ok(backend)
+}
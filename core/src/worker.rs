@@ -0,0 +1,286 @@
+use crate::metrics::ExecutionTimer;
+use crate::partition::{lane_for, LaneGate};
+use crate::state::AppState;
+use anyhow::Result;
+use nexus_event_fabric::consumer::durable_name_for_partition;
+use nexus_event_fabric::crypto::is_erased;
+use nexus_event_fabric::subject::{all_events_filter, partition_filter};
+use nexus_event_fabric::{CloudEvent, Delivery, DurableConsumer};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+/// Pause before fetching again after a failed fetch
+const FETCH_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Start one durable consumer worker per configured function and partition
+///
+/// Each function owns a durable consumer on the event stream, so events
+/// published while the server is down are executed once it is back, and an
+/// event is acknowledged only after the function ran successfully.
+///
+/// With `consumers.partitions` above 1 events are published to the subject of
+/// their key's partition, and a function has one consumer per partition
+/// subject, bound for the `assigned` partitions (all of them when `None`).
+/// Replicas given disjoint partitions never run events with the same key at
+/// the same time.
+pub async fn spawn_function_workers(state: &AppState, assigned: Option<&[u32]>) -> Result<Vec<JoinHandle<()>>> {
+    let stream = state.config.event_stream();
+    let partitions = state.config.consumers.partitions.max(1);
+    let assigned: Vec<u32> = match assigned {
+        Some(assigned) if !assigned.is_empty() => assigned.to_vec(),
+        _ => (0..partitions).collect(),
+    };
+    if let Some(partition) = assigned.iter().find(|partition| **partition >= partitions) {
+        anyhow::bail!(
+            "Partition {} does not exist, consumers.partitions is {}",
+            partition,
+            partitions
+        );
+    }
+
+    let mut handles = Vec::new();
+    for function in &state.config.functions {
+        for &partition in &assigned {
+            let filter = if partitions > 1 {
+                partition_filter(partition)
+            } else {
+                all_events_filter()
+            };
+            let consumer = DurableConsumer::bind(
+                &state.nats_client,
+                &stream.name,
+                &durable_name_for_partition(&function.name, partition, partitions),
+                filter,
+                state.config.consumers.clone(),
+            )
+            .await?;
+
+            let worker_state = state.clone();
+            let function_name = function.name.clone();
+            handles.push(tokio::spawn(async move {
+                run_worker(worker_state, function_name, partition, consumer).await;
+            }));
+        }
+    }
+
+    info!("Started {} function worker(s)", handles.len());
+    Ok(handles)
+}
+
+async fn run_worker(state: AppState, function_name: String, partition: u32, consumer: DurableConsumer) {
+    info!(function = %function_name, consumer = %consumer.name(), partition, "Function worker running");
+
+    let gate: Arc<LaneGate<Arc<Delivery>>> = Arc::new(LaneGate::new());
+    tokio::spawn(keep_held_deliveries(
+        state.clone(),
+        gate.clone(),
+        (state.config.consumers.ack_wait / 3).max(Duration::from_millis(100)),
+    ));
+
+    loop {
+        let deliveries = match consumer.fetch().await {
+            Ok(deliveries) => deliveries,
+            Err(e) => {
+                warn!(function = %function_name, error = %e, "Failed to fetch events");
+                tokio::time::sleep(FETCH_RETRY_DELAY).await;
+                continue;
+            }
+        };
+
+        // Events with the same partition key run one at a time, in delivery
+        // order; the batch is settled before the next fetch
+        let mut pending = Vec::with_capacity(deliveries.len());
+        for delivery in deliveries {
+            let event = read_event(&state, &delivery);
+            let lane = event
+                .as_ref()
+                .ok()
+                .and_then(|event| lane_for(&function_name, state.function_executor.partition_key(&function_name, event)));
+            let worker_state = state.clone();
+            let worker_function = function_name.clone();
+            let worker_gate = gate.clone();
+            pending.push(state.partitions.dispatch(lane.clone(), async move {
+                match lane {
+                    Some(lane) => run_lane(&worker_state, &worker_function, &worker_gate, &lane, delivery, event).await,
+                    None => {
+                        if let Err(e) = handle_delivery(&worker_state, &worker_function, &delivery, event).await {
+                            error!(function = %worker_function, error = %e, "Failed to settle delivery");
+                        }
+                    }
+                }
+            }));
+        }
+        for done in pending {
+            let _ = done.await;
+        }
+    }
+}
+
+/// Run a delivery on its lane, then the deliveries it held back
+///
+/// A delivery that is to be redelivered blocks its lane: later deliveries
+/// with the same key are held, unacknowledged, until it succeeds or is
+/// given up, so they never run ahead of it. The block lasts at most the
+/// retry delay plus the ack wait, see [`block_deadline`].
+async fn run_lane(
+    state: &AppState,
+    function_name: &str,
+    gate: &LaneGate<Arc<Delivery>>,
+    lane: &str,
+    delivery: Delivery,
+    event: Result<CloudEvent>,
+) {
+    let mut sequence = delivery.sequence();
+    let Some(mut delivery) = gate.admit(lane, sequence, Arc::new(delivery)) else {
+        debug!(function = %function_name, lane, sequence, "Holding event behind an earlier failed event");
+        return;
+    };
+    let mut event = event;
+
+    loop {
+        match handle_delivery(state, function_name, &delivery, event).await {
+            Ok(Outcome::Settled) => {}
+            Ok(Outcome::Redeliver) => {
+                gate.block(lane, sequence, block_deadline(state));
+                return;
+            }
+            Err(e) => {
+                // JetStream redelivers what could not be settled
+                error!(function = %function_name, error = %e, "Failed to settle delivery");
+                gate.block(lane, sequence, block_deadline(state));
+                return;
+            }
+        }
+
+        let Some((next, held)) = gate.settle(lane, sequence) else {
+            return;
+        };
+        sequence = next;
+        event = read_event(state, &held);
+        delivery = held;
+    }
+}
+
+/// How long a lane waits for the redelivery of its failed event
+///
+/// The redelivery is due after the retry delay; once it is also past its ack
+/// wait, it went to another replica or was deleted.
+fn block_deadline(state: &AppState) -> Instant {
+    Instant::now() + state.config.consumers.retry_delay + state.config.consumers.ack_wait
+}
+
+/// Keep extending the ack wait of held deliveries, so they are not redelivered
+/// out of order or use up their delivery attempts while waiting
+///
+/// Deliveries of lanes whose failed event did not come back in time are
+/// returned to JetStream for redelivery instead.
+async fn keep_held_deliveries(state: AppState, gate: Arc<LaneGate<Arc<Delivery>>>, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        for (lane, held) in gate.expire(Instant::now()) {
+            warn!(lane, held = held.len(), "Failed event was not redelivered in time, releasing the events held behind it");
+            state.metrics.increment_lanes_expired().await;
+            for delivery in held {
+                if let Err(e) = delivery.nak().await {
+                    warn!(error = %e, "Failed to release held event");
+                }
+            }
+        }
+        for delivery in gate.held() {
+            if let Err(e) = delivery.in_progress().await {
+                warn!(error = %e, "Failed to extend ack wait of held event");
+            }
+        }
+    }
+}
+
+/// What became of a delivery
+enum Outcome {
+    /// Acknowledged, or given up on
+    Settled,
+    /// Execution failed and the event will be redelivered
+    Redeliver,
+}
+
+/// Decode, decrypt and upcast the event of a delivery
+fn read_event(state: &AppState, delivery: &Delivery) -> Result<CloudEvent> {
+    let event = state.event_store.decrypt(delivery.event()?)?;
+    state.event_store.upcast(event)
+}
+
+async fn handle_delivery(
+    state: &AppState,
+    function_name: &str,
+    delivery: &Delivery,
+    event: Result<CloudEvent>,
+) -> Result<Outcome> {
+    let event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            error!(function = %function_name, error = %e, "Dropping event that cannot be decoded, decrypted or upcast");
+            delivery.term().await?;
+            return Ok(Outcome::Settled);
+        }
+    };
+
+    if !state.function_executor.function_matches(function_name, &event.event_type) {
+        delivery.ack().await?;
+        return Ok(Outcome::Settled);
+    }
+    if is_erased(&event) {
+        info!(event_id = %event.id, function = %function_name, "Skipping erased event");
+        delivery.ack().await?;
+        return Ok(Outcome::Settled);
+    }
+    if event.is_imported() {
+        debug!(event_id = %event.id, function = %function_name, "Skipping imported event");
+        delivery.ack().await?;
+        return Ok(Outcome::Settled);
+    }
+
+    let timer = ExecutionTimer::start();
+    match state.function_executor.execute_function(function_name, &event).await {
+        Ok(output) => {
+            let duration = timer.elapsed_ms();
+            state.metrics.record_function_execution(duration, true).await;
+            info!(
+                event_id = %event.id,
+                function = %function_name,
+                output_size = output.len(),
+                duration_ms = duration,
+                "Function executed from durable consumer"
+            );
+            delivery.ack().await?;
+            Ok(Outcome::Settled)
+        }
+        Err(e) => {
+            let duration = timer.elapsed_ms();
+            state.metrics.record_function_execution(duration, false).await;
+            let attempt = delivery.delivered();
+            let max_deliver = state.config.consumers.max_deliver;
+            if max_deliver > 0 && attempt >= max_deliver {
+                error!(
+                    event_id = %event.id,
+                    function = %function_name,
+                    attempt,
+                    error = %e,
+                    "Function execution failed, giving up after final attempt"
+                );
+                delivery.term().await?;
+                Ok(Outcome::Settled)
+            } else {
+                warn!(
+                    event_id = %event.id,
+                    function = %function_name,
+                    attempt,
+                    error = %e,
+                    "Function execution failed, event will be redelivered"
+                );
+                delivery.nak().await?;
+                Ok(Outcome::Redeliver)
+            }
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use crate::backend::PAGE_SIZE;
+use crate::stream_config::{deserialize_duration, serialize_duration};
+use crate::{ndjson, CloudEvent, EventBackend, StoredEvent};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{debug, info};
+
+/// Key prefix of archived segments
+const SEGMENT_PREFIX: &str = "segments/";
+
+/// Key prefix of segment indexes, the IDs of a segment's events one per line
+const INDEX_PREFIX: &str = "index/";
+
+/// Key of the position in the event backend the archiver continues from
+const CURSOR_KEY: &str = "cursor.json";
+
+/// Where the archiver left off: the next sequence to read and the ID of the event before it
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchiveCursor {
+    sequence: u64,
+    last_id: String,
+}
+
+/// Key of the index of a segment
+fn index_key(segment_key: &str) -> String {
+    let name = segment_key.strip_prefix(SEGMENT_PREFIX).unwrap_or(segment_key);
+    format!("{}{}.ids", INDEX_PREFIX, name.trim_end_matches(".ndjson.gz"))
+}
+
+/// Settings of the event archive, as configured in the `archive` section of nexus.yaml
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveConfig {
+    pub enabled: bool,
+    /// Directory the segment files are written to
+    pub path: String,
+    /// Age at which events are archived; must be below the event stream's `max_age`
+    #[serde(
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub after: Duration,
+    /// Time between archiver runs
+    #[serde(
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: ".nexus/archive".to_string(),
+            after: Duration::from_secs(6 * 24 * 60 * 60),
+            interval: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// Blob storage for archive segments
+///
+/// Keys are `/`-separated paths. [`FsObjectStore`] keeps them on local disk;
+/// other stores (S3, GCS, ...) only need these three operations.
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    /// Store an object, replacing any existing one
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<()>;
+
+    /// Read an object, `None` if it does not exist
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Keys starting with the prefix, in lexical order
+    async fn list(&self, prefix: &str) -> Result<Vec<String>>;
+}
+
+/// Object store in a local directory, one file per key
+pub struct FsObjectStore {
+    root: PathBuf,
+}
+
+impl FsObjectStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+#[async_trait]
+impl ObjectStore for FsObjectStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<()> {
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Write then rename, so readers never see a partial object
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, bytes).await?;
+        tokio::fs::rename(&partial, &path)
+            .await
+            .with_context(|| format!("Failed to store {}", path.display()))
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.root.join(key)).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let root = self.root.clone();
+        let prefix = prefix.to_string();
+        tokio::task::spawn_blocking(move || {
+            let mut keys = Vec::new();
+            collect_keys(&root, &root, &mut keys)?;
+            keys.retain(|key| key.starts_with(&prefix) && !key.ends_with(".partial"));
+            keys.sort();
+            Ok(keys)
+        })
+        .await?
+    }
+}
+
+fn collect_keys(root: &Path, dir: &Path, keys: &mut Vec<String>) -> Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_keys(root, &path, keys)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let key: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+            keys.push(key.join("/"));
+        }
+    }
+    Ok(())
+}
+
+/// Aged events kept in gzipped NDJSON segments, partitioned by the hour of the event time
+///
+/// Segments are written once and never modified. Each has a small index
+/// object listing its event IDs; lookups by ID use the indexes of all
+/// segments, loaded on first use.
+pub struct EventArchive {
+    store: Arc<dyn ObjectStore>,
+    index: Mutex<Option<HashMap<String, String>>>,
+}
+
+impl EventArchive {
+    pub fn new(store: Arc<dyn ObjectStore>) -> Self {
+        Self {
+            store,
+            index: Mutex::new(None),
+        }
+    }
+
+    /// Archive in a directory on local disk
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        Self::new(Arc::new(FsObjectStore::new(path)))
+    }
+
+    /// Copy events with a time at or before `cutoff` that are not archived yet
+    ///
+    /// Reads the backend a page at a time from where the previous run left
+    /// off, and stops at the first event that is too recent. Returns the
+    /// number of newly archived events.
+    pub async fn archive_aged(&self, backend: &dyn EventBackend, cutoff: DateTime<Utc>) -> Result<usize> {
+        let mut from = self.resume_from(backend).await?;
+        let mut archived = 0;
+
+        loop {
+            let page = backend.read_page(None, from, PAGE_SIZE).await?;
+            let aged = page.iter().take_while(|stored| stored.event.time <= cutoff).count();
+            let done = aged < page.len() || page.is_empty();
+
+            let aged: Vec<StoredEvent> = page.into_iter().take(aged).collect();
+            if let Some(last) = aged.last() {
+                let cursor = ArchiveCursor {
+                    sequence: last.sequence + 1,
+                    last_id: last.event.id.clone(),
+                };
+                archived += self.archive(aged.into_iter().map(|stored| stored.event).collect()).await?;
+                self.store.put(CURSOR_KEY, serde_json::to_vec(&cursor)?).await?;
+                from = cursor.sequence;
+            }
+            if done {
+                return Ok(archived);
+            }
+        }
+    }
+
+    /// Sequence to continue archiving from, the start if the backend no longer matches the cursor
+    ///
+    /// Backends that lose their events, like the in-memory one, number new
+    /// events from 1 again; the event before the cursor then has another ID.
+    async fn resume_from(&self, backend: &dyn EventBackend) -> Result<u64> {
+        let Some(bytes) = self.store.get(CURSOR_KEY).await? else {
+            return Ok(1);
+        };
+        let cursor: ArchiveCursor = serde_json::from_slice(&bytes).context("Corrupt archive cursor")?;
+
+        let previous = backend.read_page(None, cursor.sequence.saturating_sub(1), 1).await?;
+        match previous.first() {
+            // The event before the cursor may have expired from the stream since
+            Some(stored) if stored.sequence >= cursor.sequence || stored.event.id == cursor.last_id => {
+                Ok(cursor.sequence)
+            }
+            _ => {
+                info!("Event backend no longer matches the archive cursor, archiving from its start");
+                Ok(1)
+            }
+        }
+    }
+
+    /// Write events that are not archived yet to new segments
+    pub async fn archive(&self, events: Vec<CloudEvent>) -> Result<usize> {
+        let mut guard = self.index.lock().await;
+        let index = self.load_index(&mut guard).await?;
+
+        let mut hours: BTreeMap<String, Vec<CloudEvent>> = BTreeMap::new();
+        for event in events {
+            if !index.contains_key(&event.id) {
+                hours.entry(event.time.format("%Y/%m/%d/%H").to_string()).or_default().push(event);
+            }
+        }
+
+        let written_at = Utc::now().format("%Y%m%dT%H%M%S%3f");
+        let mut archived = 0;
+        for (hour, events) in hours {
+            let key = format!("{}{}/{}-{}.ndjson.gz", SEGMENT_PREFIX, hour, written_at, &uuid::Uuid::new_v4().simple().to_string()[..8]);
+            let mut lines = Vec::new();
+            for event in &events {
+                lines.extend(ndjson::encode_line(event)?);
+            }
+            self.store.put(&key, ndjson::gzip(&lines)?).await?;
+            let ids: Vec<&str> = events.iter().map(|event| event.id.as_str()).collect();
+            self.store.put(&index_key(&key), ids.join("\n").into_bytes()).await?;
+
+            debug!("Archived {} event(s) to {}", events.len(), key);
+            archived += events.len();
+            for event in events {
+                index.insert(event.id, key.clone());
+            }
+        }
+
+        if archived > 0 {
+            info!("Archived {} event(s)", archived);
+        }
+        Ok(archived)
+    }
+
+    /// Find an archived event by its ID
+    pub async fn get_event_by_id(&self, event_id: &str) -> Result<Option<CloudEvent>> {
+        let key = {
+            let mut guard = self.index.lock().await;
+            match self.load_index(&mut guard).await?.get(event_id) {
+                Some(key) => key.clone(),
+                None => return Ok(None),
+            }
+        };
+
+        let Some(segment) = self.store.get(&key).await? else {
+            return Ok(None);
+        };
+        let events = ndjson::decode(&segment).with_context(|| format!("Corrupt archive segment {}", key))?;
+        Ok(events.into_iter().find(|event| event.id == event_id))
+    }
+
+    /// Number of archived events
+    pub async fn event_count(&self) -> Result<usize> {
+        let mut guard = self.index.lock().await;
+        Ok(self.load_index(&mut guard).await?.len())
+    }
+
+    async fn load_index<'a>(
+        &self,
+        guard: &'a mut Option<HashMap<String, String>>,
+    ) -> Result<&'a mut HashMap<String, String>> {
+        if guard.is_none() {
+            let indexes: HashSet<String> = self.store.list(INDEX_PREFIX).await?.into_iter().collect();
+            let mut index = HashMap::new();
+            for key in self.store.list(SEGMENT_PREFIX).await? {
+                for id in self.segment_ids(&key, indexes.contains(&index_key(&key))).await? {
+                    index.insert(id, key.clone());
+                }
+            }
+            debug!("Loaded archive index with {} event(s)", index.len());
+            *guard = Some(index);
+        }
+        Ok(guard.get_or_insert_with(HashMap::new))
+    }
+
+    /// IDs of the events in a segment, from its index if it has one
+    ///
+    /// A segment without an index, written before indexes existed or just
+    /// before a crash, is read in full once and gets its index written.
+    async fn segment_ids(&self, key: &str, indexed: bool) -> Result<Vec<String>> {
+        if indexed {
+            if let Some(ids) = self.store.get(&index_key(key)).await? {
+                let ids = String::from_utf8(ids).with_context(|| format!("Corrupt archive index of {}", key))?;
+                return Ok(ids.lines().map(str::to_string).collect());
+            }
+        }
+
+        let Some(segment) = self.store.get(key).await? else {
+            return Ok(Vec::new());
+        };
+        let events = ndjson::decode(&segment).with_context(|| format!("Corrupt archive segment {}", key))?;
+        let ids: Vec<String> = events.into_iter().map(|event| event.id).collect();
+        self.store.put(&index_key(key), ids.join("\n").into_bytes()).await?;
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryBackend;
+
+    #[tokio::test]
+    async fn test_archives_aged_events_once() {
+        let dir = std::env::temp_dir().join(format!("nexus-archive-{}", uuid::Uuid::new_v4()));
+        let backend = InMemoryBackend::new();
+
+        let mut old = CloudEvent::new("com.nexus.order.created", "/test").with_data(serde_json::json!({"n": 1}));
+        old.time = Utc::now() - chrono::Duration::days(6);
+        let recent = CloudEvent::new("com.nexus.order.created", "/test");
+        backend.publish("events.com.nexus.order.created", &old).await.unwrap();
+        backend.publish("events.com.nexus.order.created", &recent).await.unwrap();
+
+        let cutoff = Utc::now() - chrono::Duration::days(1);
+        let archive = EventArchive::open(&dir);
+        assert_eq!(archive.archive_aged(&backend, cutoff).await.unwrap(), 1);
+        assert_eq!(archive.archive_aged(&backend, cutoff).await.unwrap(), 0);
+
+        // A fresh instance rebuilds the index from the segment files
+        let reopened = EventArchive::open(&dir);
+        let found = reopened.get_event_by_id(&old.id).await.unwrap().unwrap();
+        assert_eq!(found.data.unwrap()["n"], 1);
+        assert!(reopened.get_event_by_id(&recent.id).await.unwrap().is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_archives_page_by_page_from_cursor() {
+        let dir = std::env::temp_dir().join(format!("nexus-archive-{}", uuid::Uuid::new_v4()));
+        let backend = InMemoryBackend::new();
+        let old_time = Utc::now() - chrono::Duration::days(6);
+        for _ in 0..PAGE_SIZE + 200 {
+            let mut event = CloudEvent::new("com.nexus.order.created", "/test");
+            event.time = old_time;
+            backend.publish("events.com.nexus.order.created", &event).await.unwrap();
+        }
+        let recent = CloudEvent::new("com.nexus.order.created", "/test");
+        backend.publish("events.com.nexus.order.created", &recent).await.unwrap();
+
+        let cutoff = Utc::now() - chrono::Duration::days(1);
+        let archive = EventArchive::open(&dir);
+        assert_eq!(archive.archive_aged(&backend, cutoff).await.unwrap(), PAGE_SIZE + 200);
+
+        // The cursor waits at the recent event until it ages
+        let later = Utc::now() + chrono::Duration::days(1);
+        assert_eq!(EventArchive::open(&dir).archive_aged(&backend, later).await.unwrap(), 1);
+
+        // Indexes are read instead of segments; a segment without one still loads
+        let segments = archive.store.list(SEGMENT_PREFIX).await.unwrap();
+        assert_eq!(archive.store.list(INDEX_PREFIX).await.unwrap().len(), segments.len());
+        std::fs::remove_file(dir.join(index_key(&segments[0]))).unwrap();
+        let reopened = EventArchive::open(&dir);
+        assert_eq!(reopened.event_count().await.unwrap(), PAGE_SIZE + 201);
+
+        // A backend that starts over is archived from its start
+        let restarted = InMemoryBackend::new();
+        let mut event = CloudEvent::new("com.nexus.order.created", "/test");
+        event.time = old_time;
+        restarted.publish("events.com.nexus.order.created", &event).await.unwrap();
+        assert_eq!(reopened.archive_aged(&restarted, cutoff).await.unwrap(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
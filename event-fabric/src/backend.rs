@@ -0,0 +1,54 @@
+use crate::CloudEvent;
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// Events read per page when going through a whole backend
+pub const PAGE_SIZE: usize = 1000;
+
+/// An event with its position in the backend
+#[derive(Debug, Clone)]
+pub struct StoredEvent {
+    /// Increases with publish order; never reused
+    pub sequence: u64,
+    pub event: CloudEvent,
+}
+
+/// Storage and transport for published events
+///
+/// `EventPublisher` and `EventStore` are thin wrappers over a backend, so the
+/// rest of the system does not care whether events live in JetStream or in
+/// process memory.
+#[async_trait]
+pub trait EventBackend: Send + Sync {
+    /// Short name used in logs and health output
+    fn name(&self) -> &'static str;
+
+    /// Whether the backend can currently accept and serve events
+    async fn is_available(&self) -> bool;
+
+    /// Append an event under the given subject
+    async fn publish(&self, subject: &str, event: &CloudEvent) -> Result<()>;
+
+    /// Append an event unless one with the same ID is already stored
+    ///
+    /// Returns whether the event was appended. Concurrent calls with the same
+    /// event append it at most once.
+    async fn publish_once(&self, subject: &str, event: &CloudEvent) -> Result<bool>;
+
+    /// Retrieve a single event by its ID
+    async fn get_event_by_id(&self, event_id: &str) -> Result<Option<CloudEvent>>;
+
+    /// List events in publish order, optionally filtered by type
+    ///
+    /// The type may be a pattern with `*` and `>` tokens, see [`crate::subject::filter_for`].
+    async fn list_events(&self, event_type: Option<&str>, limit: usize) -> Result<Vec<CloudEvent>>;
+
+    /// Read up to `limit` events with a sequence of at least `from`, in publish order
+    ///
+    /// Continue after the last sequence of a page to go through all events;
+    /// an empty page means there are none left.
+    async fn read_page(&self, event_type: Option<&str>, from: u64, limit: usize) -> Result<Vec<StoredEvent>>;
+
+    /// Number of events currently stored
+    async fn event_count(&self) -> Result<u64>;
+}
@@ -1,7 +1,47 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// Extension attribute set on events delivered by a targeted replay
+pub const REPLAY_EXTENSION: &str = "nexusreplay";
+
+/// Extension attribute set on events stored by an import, which functions do not run
+pub const IMPORT_EXTENSION: &str = "nexusimported";
+
+/// Prefix of the extension attributes Nexus sets itself, such as the
+/// encryption, erasure and replay markers
+pub const RESERVED_EXTENSION_PREFIX: &str = "nexus";
+
+/// Extension attribute grouping events that must be processed in order
+pub const PARTITION_KEY_EXTENSION: &str = "partitionkey";
+
+/// Prefix of `dataschema` URNs naming a registered schema version
+pub const SCHEMA_URN_PREFIX: &str = "urn:nexus:schema:";
+
+/// Maximum length of an extension attribute name
+pub const MAX_EXTENSION_NAME_LEN: usize = 20;
+
+/// Ways an event violates the CloudEvents v1.0 specification
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationError {
+    #[error("unsupported specversion '{0}', expected '1.0'")]
+    UnsupportedVersion(String),
+    #[error("attribute '{0}' must not be empty")]
+    EmptyAttribute(&'static str),
+    #[error("source '{0}' is not a valid URI-reference")]
+    InvalidSource(String),
+    #[error("dataschema '{0}' is not an absolute URI")]
+    InvalidDataSchema(String),
+    #[error("extension name '{0}' must be 1-20 lowercase letters or digits")]
+    InvalidExtensionName(String),
+    #[error("'data' and 'data_base64' must not both be set")]
+    ConflictingData,
+    #[error("'data_base64' is not valid base64")]
+    InvalidDataBase64,
+}
+
 /// CloudEvents v1.0 specification
 /// https://github.com/cloudevents/spec/blob/v1.0.2/cloudevents/spec.md
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,14 +62,26 @@ pub struct CloudEvent {
     /// Event timestamp
     pub time: DateTime<Utc>,
     
+    /// Subject of the event in the context of the source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    
     /// Content type of data
     #[serde(skip_serializing_if = "Option::is_none")]
     pub datacontenttype: Option<String>,
     
+    /// Schema the data adheres to (absolute URI)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dataschema: Option<String>,
+    
     /// Event payload
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<serde_json::Value>,
     
+    /// Binary event payload, base64-encoded (exclusive with `data`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_base64: Option<String>,
+    
     /// Additional extension attributes
     #[serde(flatten)]
     pub extensions: std::collections::HashMap<String, serde_json::Value>,
@@ -44,8 +96,11 @@ impl CloudEvent {
             source: source.into(),
             id: Uuid::new_v4().to_string(),
             time: Utc::now(),
+            subject: None,
             datacontenttype: Some("application/json".to_string()),
+            dataschema: None,
             data: None,
+            data_base64: None,
             extensions: std::collections::HashMap::new(),
         }
     }
@@ -56,12 +111,150 @@ impl CloudEvent {
         self
     }
 
+    /// Set binary event data, stored base64-encoded in `data_base64`
+    pub fn with_binary_data(mut self, bytes: &[u8], content_type: impl Into<String>) -> Self {
+        self.data = None;
+        self.data_base64 = Some(BASE64.encode(bytes));
+        self.datacontenttype = Some(content_type.into());
+        self
+    }
+
+    /// The event data in its original encoding
+    ///
+    /// Binary data is decoded from `data_base64`, string data with a non-JSON
+    /// content type is returned as-is and anything else is serialized as JSON.
+    pub fn data_bytes(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        if let Some(encoded) = &self.data_base64 {
+            return Ok(Some(BASE64.decode(encoded)?));
+        }
+        match &self.data {
+            None => Ok(None),
+            Some(serde_json::Value::String(text)) if !self.has_json_data() => {
+                Ok(Some(text.clone().into_bytes()))
+            }
+            Some(value) => Ok(Some(serde_json::to_vec(value)?)),
+        }
+    }
+
+    /// Whether `datacontenttype` is JSON (or absent, which implies JSON)
+    pub fn has_json_data(&self) -> bool {
+        self.datacontenttype.as_deref().is_none_or(is_json_content_type)
+    }
+
     /// Add an extension attribute
     pub fn with_extension(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
         self.extensions.insert(key.into(), value);
         self
     }
 
+    /// Mark the event as a replay so guests can tell it apart from the original delivery
+    pub fn as_replay(&self) -> Self {
+        self.clone()
+            .with_extension(REPLAY_EXTENSION, serde_json::Value::Bool(true))
+    }
+
+    /// Whether the event was delivered by a replay
+    pub fn is_replay(&self) -> bool {
+        self.extensions
+            .get(REPLAY_EXTENSION)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// Mark the event as imported, so durable workers store it without running functions
+    pub fn as_imported(&self) -> Self {
+        self.clone()
+            .with_extension(IMPORT_EXTENSION, serde_json::Value::Bool(true))
+    }
+
+    /// Whether the event was stored by an import
+    pub fn is_imported(&self) -> bool {
+        self.extensions
+            .get(IMPORT_EXTENSION)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// Remove the extensions in Nexus' reserved namespace, returning their names
+    ///
+    /// Ingested events must not carry markers such as `nexusdek` or
+    /// `nexuserased`: they would skip encryption or hide the event from
+    /// functions.
+    pub fn strip_reserved_extensions(&mut self) -> Vec<String> {
+        let reserved: Vec<String> = self
+            .extensions
+            .keys()
+            .filter(|name| name.starts_with(RESERVED_EXTENSION_PREFIX))
+            .cloned()
+            .collect();
+        for name in &reserved {
+            self.extensions.remove(name);
+        }
+        reserved
+    }
+
+    /// Partition key from the `partitionkey` extension, if set
+    pub fn partition_key(&self) -> Option<String> {
+        match self.extensions.get(PARTITION_KEY_EXTENSION)? {
+            serde_json::Value::String(key) => Some(key.clone()),
+            serde_json::Value::Number(key) => Some(key.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Version of the registered schema named by `dataschema`, if any
+    pub fn schema_version(&self) -> Option<u32> {
+        self.dataschema
+            .as_deref()?
+            .strip_prefix(SCHEMA_URN_PREFIX)?
+            .strip_prefix(self.event_type.as_str())?
+            .strip_prefix(':')?
+            .parse()
+            .ok()
+    }
+
+    /// Check the event against the CloudEvents v1.0 specification
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.specversion != "1.0" {
+            return Err(ValidationError::UnsupportedVersion(self.specversion.clone()));
+        }
+        if self.id.is_empty() {
+            return Err(ValidationError::EmptyAttribute("id"));
+        }
+        if self.event_type.is_empty() {
+            return Err(ValidationError::EmptyAttribute("type"));
+        }
+        if self.source.is_empty() {
+            return Err(ValidationError::EmptyAttribute("source"));
+        }
+        if !is_uri_reference(&self.source) {
+            return Err(ValidationError::InvalidSource(self.source.clone()));
+        }
+        if self.subject.as_deref() == Some("") {
+            return Err(ValidationError::EmptyAttribute("subject"));
+        }
+        if self.datacontenttype.as_deref() == Some("") {
+            return Err(ValidationError::EmptyAttribute("datacontenttype"));
+        }
+        if let Some(dataschema) = &self.dataschema {
+            if !is_absolute_uri(dataschema) {
+                return Err(ValidationError::InvalidDataSchema(dataschema.clone()));
+            }
+        }
+        if self.data.is_some() && self.data_base64.is_some() {
+            return Err(ValidationError::ConflictingData);
+        }
+        if let Some(encoded) = &self.data_base64 {
+            if BASE64.decode(encoded).is_err() {
+                return Err(ValidationError::InvalidDataBase64);
+            }
+        }
+        if let Some(name) = self.extensions.keys().find(|name| !is_extension_name(name)) {
+            return Err(ValidationError::InvalidExtensionName(name.clone()));
+        }
+        Ok(())
+    }
+
     /// Serialize to JSON
     pub fn to_json(&self) -> anyhow::Result<String> {
         Ok(serde_json::to_string(self)?)
@@ -78,10 +271,88 @@ impl CloudEvent {
     }
 }
 
+/// The `dataschema` URN of a schema version, e.g. `urn:nexus:schema:com.nexus.order.created:2`
+pub fn schema_urn(event_type: &str, version: u32) -> String {
+    format!("{}{}:{}", SCHEMA_URN_PREFIX, event_type, version)
+}
+
+/// Resolve a JSON path such as `$.data.user.id` or `data.items.0` against an event
+///
+/// Strings are used as-is, numbers and booleans in their JSON form; other
+/// values do not yield a key.
+pub fn resolve_key(event: &CloudEvent, path: &str) -> Option<String> {
+    let path = path.strip_prefix('$').unwrap_or(path).trim_start_matches('.');
+    let mut value = serde_json::to_value(event).ok()?;
+
+    for token in path.split('.').filter(|t| !t.is_empty()) {
+        value = match value {
+            serde_json::Value::Object(mut map) => map.remove(token)?,
+            serde_json::Value::Array(mut items) => {
+                let index: usize = token.parse().ok()?;
+                if index >= items.len() {
+                    return None;
+                }
+                items.swap_remove(index)
+            }
+            _ => return None,
+        };
+    }
+
+    match value {
+        serde_json::Value::String(key) => Some(key),
+        serde_json::Value::Number(key) => Some(key.to_string()),
+        serde_json::Value::Bool(key) => Some(key.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether a media type carries JSON (`application/json`, `*/*+json`, `text/json`)
+pub fn is_json_content_type(content_type: &str) -> bool {
+    let media_type = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    media_type == "application/json" || media_type == "text/json" || media_type.ends_with("+json")
+}
+
+fn is_uri_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-._~:/?#[]@!$&'()*+,;=%".contains(c)
+}
+
+fn is_uri_reference(value: &str) -> bool {
+    value.chars().all(is_uri_char)
+}
+
+fn is_absolute_uri(value: &str) -> bool {
+    let Some((scheme, rest)) = value.split_once(':') else {
+        return false;
+    };
+    let mut scheme_chars = scheme.chars();
+    scheme_chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && scheme_chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        && !rest.is_empty()
+        && rest.chars().all(is_uri_char)
+}
+
+fn is_extension_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_EXTENSION_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_resolve_key() {
+        let event = CloudEvent::new("com.nexus.order.created", "/test")
+            .with_data(serde_json::json!({"user": {"id": 42}, "items": ["a", "b"]}));
+
+        assert_eq!(resolve_key(&event, "$.data.user.id").unwrap(), "42");
+        assert_eq!(resolve_key(&event, "data.items.1").unwrap(), "b");
+        assert_eq!(resolve_key(&event, "$.type").unwrap(), "com.nexus.order.created");
+        assert!(resolve_key(&event, "$.data.user").is_none());
+        assert!(resolve_key(&event, "$.data.missing").is_none());
+    }
+
     #[test]
     fn test_create_cloud_event() {
         let event = CloudEvent::new("com.example.test", "/api/test")
@@ -104,4 +375,56 @@ mod tests {
         assert_eq!(event.id, deserialized.id);
         assert_eq!(event.event_type, deserialized.event_type);
     }
+
+    #[test]
+    fn test_replay_extension() {
+        let event = CloudEvent::new("test.event", "/source");
+        assert!(!event.is_replay());
+
+        let replay = event.as_replay();
+        assert_eq!(replay.id, event.id);
+        assert!(replay.is_replay());
+        assert!(replay.to_json().unwrap().contains("\"nexusreplay\":true"));
+
+        let mut imported = event.as_imported();
+        assert!(imported.is_imported());
+        assert_eq!(imported.strip_reserved_extensions(), vec![IMPORT_EXTENSION.to_string()]);
+        assert!(!imported.is_imported());
+    }
+
+    #[test]
+    fn test_validate() {
+        let event = CloudEvent::new("com.example.test", "/api/test");
+        assert!(event.validate().is_ok());
+
+        let mut invalid = event.clone();
+        invalid.source = "not a uri".to_string();
+        assert!(matches!(invalid.validate(), Err(ValidationError::InvalidSource(_))));
+
+        let mut invalid = event.clone();
+        invalid.dataschema = Some("/relative/schema.json".to_string());
+        assert!(matches!(invalid.validate(), Err(ValidationError::InvalidDataSchema(_))));
+
+        let invalid = event.clone().with_extension("Tenant_ID", serde_json::json!("acme"));
+        assert!(matches!(invalid.validate(), Err(ValidationError::InvalidExtensionName(_))));
+
+        let mut invalid = event.with_data(serde_json::json!({}));
+        invalid.data_base64 = Some("AAEC".to_string());
+        assert_eq!(invalid.validate(), Err(ValidationError::ConflictingData));
+    }
+
+    #[test]
+    fn test_binary_data_round_trip() {
+        let bytes = [0u8, 159, 146, 150, b'<'];
+        let event = CloudEvent::new("com.example.image", "/upload")
+            .with_binary_data(&bytes, "image/png");
+        assert!(event.validate().is_ok());
+        assert!(!event.has_json_data());
+
+        let decoded = CloudEvent::from_json(&event.to_json().unwrap()).unwrap();
+        assert_eq!(decoded.data_bytes().unwrap().unwrap(), bytes);
+
+        let json = CloudEvent::new("t", "/s").with_data(serde_json::json!({"a": 1}));
+        assert_eq!(json.data_bytes().unwrap().unwrap(), br#"{"a":1}"#);
+    }
 }
@@ -0,0 +1,269 @@
+use crate::jetstream_backend::decode_message;
+use crate::stream_config::{deserialize_duration, serialize_duration};
+use crate::subject::stable_hash;
+use crate::{CloudEvent, NatsClient};
+use anyhow::{Context, Result};
+use async_nats::jetstream::{self, consumer::pull, AckKind};
+use futures::stream::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// How long a fetch waits for new messages before returning an empty batch
+const FETCH_EXPIRES: Duration = Duration::from_secs(5);
+
+/// Settings for durable consumers, as configured in the `consumers` section of nexus.yaml
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsumerConfig {
+    /// Time a delivery may stay unacknowledged before JetStream redelivers it
+    #[serde(
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub ack_wait: Duration,
+    /// Maximum number of delivery attempts per event (`-1` for unlimited)
+    pub max_deliver: i64,
+    /// Number of events fetched per pull request
+    pub batch_size: usize,
+    /// Delay before a failed event is redelivered
+    #[serde(
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub retry_delay: Duration,
+    /// Partitions the events of each function are split into by partition key
+    ///
+    /// Each partition has its own durable consumer, and only the worker
+    /// running a partition executes its events, so events with the same key
+    /// never run on two replicas at once.
+    pub partitions: u32,
+}
+
+impl Default for ConsumerConfig {
+    fn default() -> Self {
+        Self {
+            ack_wait: Duration::from_secs(30),
+            max_deliver: 5,
+            batch_size: 10,
+            retry_delay: Duration::from_secs(5),
+            partitions: 1,
+        }
+    }
+}
+
+/// Durable pull consumer on an event stream
+///
+/// The consumer's position lives in JetStream, so after a restart delivery
+/// resumes after the last acknowledged event, and events that were never
+/// acknowledged are redelivered.
+pub struct DurableConsumer {
+    name: String,
+    consumer: jetstream::consumer::Consumer<pull::Config>,
+    config: ConsumerConfig,
+}
+
+impl DurableConsumer {
+    /// Bind to the named durable consumer, creating it if it does not exist
+    ///
+    /// A newly created consumer starts with events published from now on. An
+    /// existing consumer with another ack wait, delivery limit or filter is
+    /// updated to the given settings.
+    pub async fn bind(
+        nats_client: &Arc<RwLock<NatsClient>>,
+        stream_name: &str,
+        durable_name: &str,
+        filter_subject: String,
+        config: ConsumerConfig,
+    ) -> Result<Self> {
+        let client = nats_client.read().await;
+        let nats = client.client().context("NATS client not available")?;
+        let jetstream = jetstream::new(nats.clone());
+
+        let stream = jetstream
+            .get_stream(stream_name)
+            .await
+            .context("Failed to get stream")?;
+
+        let desired = pull::Config {
+            durable_name: Some(durable_name.to_string()),
+            filter_subject,
+            deliver_policy: jetstream::consumer::DeliverPolicy::New,
+            ack_policy: jetstream::consumer::AckPolicy::Explicit,
+            ack_wait: config.ack_wait,
+            max_deliver: config.max_deliver,
+            ..Default::default()
+        };
+        let mut consumer = stream
+            .get_or_create_consumer(durable_name, desired.clone())
+            .await
+            .with_context(|| format!("Failed to bind durable consumer '{}'", durable_name))?;
+
+        // Creating an existing consumer with a new configuration updates it
+        let current = &consumer.cached_info().config;
+        if current.ack_wait != desired.ack_wait
+            || current.max_deliver != desired.max_deliver
+            || current.filter_subject != desired.filter_subject
+        {
+            info!("Updating durable consumer '{}' to the configured settings", durable_name);
+            consumer = stream
+                .create_consumer(desired)
+                .await
+                .with_context(|| format!("Failed to update durable consumer '{}'", durable_name))?;
+        }
+
+        info!("Bound durable consumer '{}' on stream '{}'", durable_name, stream_name);
+        Ok(Self {
+            name: durable_name.to_string(),
+            consumer,
+            config,
+        })
+    }
+
+    /// Name of the durable consumer
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Wait for the next batch of deliveries (empty if none arrived in time)
+    pub async fn fetch(&self) -> Result<Vec<Delivery>> {
+        let mut messages = self
+            .consumer
+            .batch()
+            .max_messages(self.config.batch_size.max(1))
+            .expires(FETCH_EXPIRES)
+            .messages()
+            .await
+            .context("Failed to fetch from durable consumer")?;
+
+        let mut deliveries = Vec::new();
+        while let Some(message) = messages.next().await {
+            match message {
+                Ok(message) => deliveries.push(Delivery::new(message, self.config.retry_delay)),
+                Err(e) => {
+                    warn!("Consumer '{}' failed to receive a message: {}", self.name, e);
+                    break;
+                }
+            }
+        }
+
+        if !deliveries.is_empty() {
+            debug!("Consumer '{}' fetched {} message(s)", self.name, deliveries.len());
+        }
+        Ok(deliveries)
+    }
+}
+
+/// A single event delivered by a [`DurableConsumer`]
+pub struct Delivery {
+    message: jetstream::Message,
+    retry_delay: Duration,
+}
+
+impl Delivery {
+    fn new(message: jetstream::Message, retry_delay: Duration) -> Self {
+        Self {
+            message,
+            retry_delay,
+        }
+    }
+
+    /// Decode the delivered event
+    pub fn event(&self) -> Result<CloudEvent> {
+        decode_message(&self.message).context("Failed to decode delivered event")
+    }
+
+    /// How many times this event has been delivered, including this delivery
+    pub fn delivered(&self) -> i64 {
+        self.message.info().map(|info| info.delivered).unwrap_or(1)
+    }
+
+    /// Position of the event in the stream, the same on every redelivery
+    pub fn sequence(&self) -> u64 {
+        self.message.info().map(|info| info.stream_sequence).unwrap_or(0)
+    }
+
+    /// Acknowledge successful processing
+    pub async fn ack(&self) -> Result<()> {
+        self.message
+            .ack()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to ack message: {}", e))
+    }
+
+    /// Request redelivery after the configured retry delay
+    pub async fn nak(&self) -> Result<()> {
+        self.message
+            .ack_with(AckKind::Nak(Some(self.retry_delay)))
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to nak message: {}", e))
+    }
+
+    /// Reset the ack wait of a delivery that is held back but not yet processed
+    pub async fn in_progress(&self) -> Result<()> {
+        self.message
+            .ack_with(AckKind::Progress)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to extend ack wait: {}", e))
+    }
+
+    /// Stop redelivery of a message that can never be processed
+    pub async fn term(&self) -> Result<()> {
+        self.message
+            .ack_with(AckKind::Term)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to terminate message: {}", e))
+    }
+}
+
+/// Durable consumer name for a function, restricted to characters NATS accepts
+///
+/// Names with other characters get a hash of the function name appended, so
+/// `a.b` and `a_b` have consumers of their own.
+pub fn durable_name_for(function_name: &str) -> String {
+    let sanitized: String = function_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if sanitized == function_name {
+        format!("nexus-fn-{}", sanitized)
+    } else {
+        format!("nexus-fn-{}-{:016x}", sanitized, stable_hash(function_name))
+    }
+}
+
+/// Durable consumer name for one partition of a function's events
+///
+/// With a single partition this is the function's unpartitioned consumer.
+pub fn durable_name_for_partition(function_name: &str, partition: u32, partitions: u32) -> String {
+    if partitions <= 1 {
+        durable_name_for(function_name)
+    } else {
+        format!("{}-p{}", durable_name_for(function_name), partition)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_durable_name_for() {
+        assert_eq!(durable_name_for("hello-world"), "nexus-fn-hello-world");
+        assert!(durable_name_for("user.welcome v2").starts_with("nexus-fn-user_welcome_v2-"));
+        assert_ne!(durable_name_for("a.b"), durable_name_for("a_b"));
+        assert_ne!(durable_name_for("a.b"), durable_name_for("a b"));
+        assert_eq!(durable_name_for_partition("hello-world", 0, 1), "nexus-fn-hello-world");
+        assert_eq!(durable_name_for_partition("hello-world", 3, 4), "nexus-fn-hello-world-p3");
+    }
+
+    #[test]
+    fn test_consumer_config_defaults() {
+        let config: ConsumerConfig = serde_json::from_str(r#"{"ack_wait": "1m"}"#).unwrap();
+        assert_eq!(config.ack_wait, Duration::from_secs(60));
+        assert_eq!(config.max_deliver, 5);
+        assert_eq!(config.partitions, 1);
+    }
+}
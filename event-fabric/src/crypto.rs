@@ -0,0 +1,421 @@
+use crate::cloudevents::resolve_key;
+use crate::shredding::{SubjectKeys, SUBJECT_KEY_PREFIX};
+use crate::subject::type_matches;
+use crate::CloudEvent;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Extension naming the keyring key that wraps the event's data key
+pub const KEY_ID_EXTENSION: &str = "nexuskeyid";
+
+/// Extension holding the wrapped data key (base64 of nonce and ciphertext)
+pub const DATA_KEY_EXTENSION: &str = "nexusdek";
+
+/// Extension listing the encrypted paths, comma separated; `$` is the whole data
+pub const ENCRYPTED_PATHS_EXTENSION: &str = "nexusencpaths";
+
+/// Extension set on events whose data was erased
+pub const ERASED_EXTENSION: &str = "nexuserased";
+
+/// Path standing for the whole `data` (or `data_base64`)
+const WHOLE_DATA: &str = "$";
+
+const NONCE_LEN: usize = 12;
+
+/// Settings of payload encryption, as configured in the `encryption` section of nexus.yaml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    /// Keyring file, see [`Keyring`]
+    pub keyring: String,
+    #[serde(default)]
+    pub rules: Vec<EncryptionRule>,
+    /// Per-subject key file, see [`SubjectKeys`]
+    #[serde(default = "default_subject_keys")]
+    pub subject_keys: String,
+    /// Audit trail of subject erasures
+    #[serde(default = "default_erasure_log")]
+    pub erasure_log: String,
+}
+
+fn default_subject_keys() -> String {
+    ".nexus/subject-keys.json".to_string()
+}
+
+fn default_erasure_log() -> String {
+    ".nexus/erasures.log".to_string()
+}
+
+/// Which events are encrypted, and which parts of their data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionRule {
+    /// Event type pattern, `*` and `>` tokens allowed
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// Dotted paths into `data`; the whole data when empty
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// JSON path to the subject (e.g. `$.data.user_id`) whose own key encrypts
+    /// the event, so erasing the subject shreds it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject_key: Option<String>,
+}
+
+/// Key encryption keys, stored as JSON: `{"active": "k2", "keys": {"k1": "<base64>", "k2": "<base64>"}}`
+///
+/// New events are encrypted under the active key. Rotating adds a key and
+/// makes it active; older keys stay to decrypt events written under them.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Keyring {
+    active: String,
+    keys: BTreeMap<String, String>,
+}
+
+impl Keyring {
+    /// A keyring with one fresh key
+    pub fn generate() -> Self {
+        let mut keyring = Self {
+            active: String::new(),
+            keys: BTreeMap::new(),
+        };
+        keyring.rotate();
+        keyring
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read keyring {}", path.display()))?;
+        let keyring: Keyring = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse keyring {}", path.display()))?;
+        keyring.key(&keyring.active)?;
+        Ok(keyring)
+    }
+
+    /// Write the keyring, readable by the owner only
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+
+    /// Add a fresh key and make it active, returning its ID
+    pub fn rotate(&mut self) -> String {
+        let id = format!("k{}", self.keys.len() + 1);
+        self.keys.insert(id.clone(), generate_key());
+        self.active = id.clone();
+        id
+    }
+
+    pub fn active_key_id(&self) -> &str {
+        &self.active
+    }
+
+    pub fn key_ids(&self) -> impl Iterator<Item = &String> {
+        self.keys.keys()
+    }
+
+    fn key(&self, id: &str) -> Result<Aes256Gcm> {
+        let encoded = self
+            .keys
+            .get(id)
+            .with_context(|| format!("Key '{}' is not in the keyring", id))?;
+        cipher_from_base64(id, encoded)
+    }
+}
+
+/// A fresh 256-bit key, base64-encoded
+pub(crate) fn generate_key() -> String {
+    BASE64.encode(Aes256Gcm::generate_key(OsRng))
+}
+
+pub(crate) fn cipher_from_base64(id: &str, encoded: &str) -> Result<Aes256Gcm> {
+    let bytes = BASE64.decode(encoded).with_context(|| format!("Key '{}' is not base64", id))?;
+    if bytes.len() != 32 {
+        anyhow::bail!("Key '{}' must be 32 bytes", id);
+    }
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&bytes)))
+}
+
+/// Envelope encryption of event data
+///
+/// Each event gets a random data key that encrypts the selected values; the
+/// data key itself is encrypted under the active keyring key. Ciphertexts are
+/// bound to the event ID, so encrypted values cannot be moved between events.
+///
+/// For rules with a `subject_key`, the data key is encrypted under the
+/// subject's own key instead; once that key is erased the event reads back
+/// redacted.
+pub struct Encryptor {
+    keyring: Keyring,
+    rules: Vec<EncryptionRule>,
+    subjects: Option<SubjectKeys>,
+}
+
+impl Encryptor {
+    pub fn new(keyring: Keyring, rules: Vec<EncryptionRule>) -> Self {
+        Self {
+            keyring,
+            rules,
+            subjects: None,
+        }
+    }
+
+    pub fn with_subject_keys(mut self, subjects: SubjectKeys) -> Self {
+        self.subjects = Some(subjects);
+        self
+    }
+
+    /// Load the keyring and subject keys named in the configuration
+    pub fn from_config(config: &EncryptionConfig) -> Result<Self> {
+        Ok(Self::new(Keyring::load(&config.keyring)?, config.rules.clone())
+            .with_subject_keys(SubjectKeys::open(&config.subject_keys)?))
+    }
+
+    /// Destroy the keys of a subject, returning how many were destroyed
+    pub fn erase_subject(&self, subject: &str) -> Result<usize> {
+        self.subjects
+            .as_ref()
+            .context("Subject keys are not configured")?
+            .erase(subject)
+    }
+
+    /// Encrypt the event if a rule covers its type; other events are returned unchanged
+    pub fn encrypt(&self, event: &CloudEvent) -> Result<CloudEvent> {
+        let mut event = event.clone();
+        if is_encrypted(&event) {
+            return Ok(event);
+        }
+        let Some(rule) = self.rules.iter().find(|r| type_matches(&r.event_type, &event.event_type)) else {
+            return Ok(event);
+        };
+
+        // Resolved before the path may be encrypted
+        let subject = rule.subject_key.as_deref().and_then(|path| resolve_key(&event, path));
+
+        let data_key = Aes256Gcm::generate_key(OsRng);
+        let cipher = Aes256Gcm::new(&data_key);
+        let aad = event.id.clone();
+
+        let mut encrypted = Vec::new();
+        if rule.paths.is_empty() || event.data_base64.is_some() {
+            if let Some(encoded) = &event.data_base64 {
+                let bytes = BASE64.decode(encoded).context("data_base64 is not valid base64")?;
+                event.data_base64 = Some(seal(&cipher, &bytes, &aad)?);
+                encrypted.push(WHOLE_DATA.to_string());
+            } else if let Some(data) = &event.data {
+                event.data = Some(Value::String(seal(&cipher, &serde_json::to_vec(data)?, &aad)?));
+                encrypted.push(WHOLE_DATA.to_string());
+            }
+        } else if let Some(data) = event.data.as_mut() {
+            for path in &rule.paths {
+                if let Some(value) = data.pointer_mut(&pointer(path)) {
+                    *value = Value::String(seal(&cipher, &serde_json::to_vec(value)?, &aad)?);
+                    encrypted.push(path.clone());
+                }
+            }
+        }
+
+        if encrypted.is_empty() {
+            return Ok(event);
+        }
+
+        let (key_id, key) = match (subject, &self.subjects) {
+            (Some(subject), Some(subjects)) => subjects.key_for(&subject)?,
+            (Some(_), None) => anyhow::bail!("Subject keys are not configured"),
+            (None, _) => {
+                let key_id = self.keyring.active_key_id().to_string();
+                let key = self.keyring.key(&key_id)?;
+                (key_id, key)
+            }
+        };
+        let wrapped = seal(&key, data_key.as_slice(), &aad)?;
+        Ok(event
+            .with_extension(KEY_ID_EXTENSION, Value::String(key_id))
+            .with_extension(DATA_KEY_EXTENSION, Value::String(wrapped))
+            .with_extension(ENCRYPTED_PATHS_EXTENSION, Value::String(encrypted.join(","))))
+    }
+
+    /// Restore the plaintext of an encrypted event; other events are returned unchanged
+    pub fn decrypt(&self, mut event: CloudEvent) -> Result<CloudEvent> {
+        if !is_encrypted(&event) {
+            return Ok(event);
+        }
+        let key_id = extension_str(&event, KEY_ID_EXTENSION)?;
+        let wrapped = extension_str(&event, DATA_KEY_EXTENSION)?;
+        let paths = extension_str(&event, ENCRYPTED_PATHS_EXTENSION)?;
+        let aad = event.id.clone();
+
+        let key = if key_id.starts_with(SUBJECT_KEY_PREFIX) {
+            let subjects = self.subjects.as_ref().context("Subject keys are not configured")?;
+            match subjects.key(&key_id)? {
+                Some(key) => key,
+                None => return Ok(redact(event)),
+            }
+        } else {
+            self.keyring.key(&key_id)?
+        };
+        let data_key = open(&key, &wrapped, &aad)
+            .with_context(|| format!("Failed to unwrap the data key of event '{}'", event.id))?;
+        if data_key.len() != 32 {
+            anyhow::bail!("Data key of event '{}' has the wrong length", event.id);
+        }
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&data_key));
+
+        for path in paths.split(',') {
+            if path == WHOLE_DATA && event.data_base64.is_some() {
+                let sealed = event.data_base64.take().unwrap_or_default();
+                event.data_base64 = Some(BASE64.encode(open(&cipher, &sealed, &aad)?));
+                continue;
+            }
+            let value = if path == WHOLE_DATA {
+                event.data.as_mut()
+            } else {
+                event.data.as_mut().and_then(|data| data.pointer_mut(&pointer(path)))
+            };
+            let Some(value) = value else {
+                continue;
+            };
+            let sealed = value.as_str().context("Encrypted value is not a string")?;
+            *value = serde_json::from_slice(&open(&cipher, sealed, &aad)?)?;
+        }
+
+        event.extensions.remove(KEY_ID_EXTENSION);
+        event.extensions.remove(DATA_KEY_EXTENSION);
+        event.extensions.remove(ENCRYPTED_PATHS_EXTENSION);
+        Ok(event)
+    }
+}
+
+/// Drop the data of an event whose subject key was erased
+fn redact(mut event: CloudEvent) -> CloudEvent {
+    event.data = None;
+    event.data_base64 = None;
+    event.extensions.remove(DATA_KEY_EXTENSION);
+    event.extensions.remove(ENCRYPTED_PATHS_EXTENSION);
+    event.extensions.insert(ERASED_EXTENSION.to_string(), Value::Bool(true));
+    event
+}
+
+/// Whether the event's data was erased
+pub fn is_erased(event: &CloudEvent) -> bool {
+    event.extensions.get(ERASED_EXTENSION).and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+/// Whether the event carries encrypted data
+pub fn is_encrypted(event: &CloudEvent) -> bool {
+    event.extensions.contains_key(DATA_KEY_EXTENSION)
+}
+
+fn extension_str(event: &CloudEvent, name: &str) -> Result<String> {
+    event
+        .extensions
+        .get(name)
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .with_context(|| format!("Encrypted event '{}' lacks the {} extension", event.id, name))
+}
+
+/// JSON pointer for a dotted path
+fn pointer(path: &str) -> String {
+    path.split('.')
+        .map(|token| format!("/{}", token.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+/// Encrypt to base64 of nonce followed by ciphertext
+fn seal(cipher: &Aes256Gcm, plaintext: &[u8], aad: &str) -> Result<String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: plaintext, aad: aad.as_bytes() })
+        .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    Ok(BASE64.encode(sealed))
+}
+
+fn open(cipher: &Aes256Gcm, sealed: &str, aad: &str) -> Result<Vec<u8>> {
+    let sealed = BASE64.decode(sealed).context("Ciphertext is not base64")?;
+    if sealed.len() < NONCE_LEN {
+        anyhow::bail!("Ciphertext is too short");
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: aad.as_bytes() })
+        .map_err(|_| anyhow::anyhow!("Decryption failed (wrong key or tampered data)"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rules() -> Vec<EncryptionRule> {
+        vec![
+            EncryptionRule {
+                event_type: "com.nexus.user.*".to_string(),
+                paths: vec!["email".to_string(), "address.street".to_string()],
+                subject_key: None,
+            },
+            EncryptionRule {
+                event_type: "com.nexus.payment.>".to_string(),
+                paths: vec![],
+                subject_key: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_encrypts_paths_and_survives_rotation() {
+        let mut keyring = Keyring::generate();
+        let event = CloudEvent::new("com.nexus.user.created", "/test")
+            .with_data(json!({"name": "Ada", "email": "ada@example.com", "address": {"street": "1 Main", "city": "Paris"}}));
+
+        let encrypted = Encryptor::new(keyring.clone(), rules()).encrypt(&event).unwrap();
+        let data = encrypted.data.as_ref().unwrap();
+        assert_eq!(data["name"], "Ada");
+        assert_eq!(data["address"]["city"], "Paris");
+        assert_ne!(data["email"], "ada@example.com");
+        assert!(encrypted.validate().is_ok());
+
+        // Events written under the old key stay readable after rotation
+        assert_eq!(keyring.rotate(), "k2");
+        let encryptor = Encryptor::new(keyring, rules());
+        let decrypted = encryptor.decrypt(encrypted).unwrap();
+        assert_eq!(decrypted.data, event.data);
+        assert!(!is_encrypted(&decrypted));
+
+        let payment = CloudEvent::new("com.nexus.payment.card.charged", "/test").with_data(json!({"card": "4242"}));
+        let sealed = encryptor.encrypt(&payment).unwrap();
+        assert!(sealed.data.as_ref().unwrap().is_string());
+        assert_eq!(sealed.extensions[KEY_ID_EXTENSION], "k2");
+        assert_eq!(encryptor.decrypt(sealed).unwrap().data, payment.data);
+    }
+
+    #[test]
+    fn test_ciphertext_is_bound_to_event() {
+        let encryptor = Encryptor::new(Keyring::generate(), rules());
+        let event = CloudEvent::new("com.nexus.payment.charged", "/test").with_data(json!({"card": "4242"}));
+        let mut copied = encryptor.encrypt(&event).unwrap();
+        copied.id = "another-event".to_string();
+        assert!(encryptor.decrypt(copied).is_err());
+
+        // Other types are left alone
+        let other = CloudEvent::new("com.nexus.order.created", "/test").with_data(json!({"n": 1}));
+        assert!(!is_encrypted(&encryptor.encrypt(&other).unwrap()));
+    }
+}
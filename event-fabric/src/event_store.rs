@@ -1,72 +1,98 @@
-use crate::{CloudEvent, NatsClient};
+use crate::archive::EventArchive;
+use crate::crypto::{is_encrypted, Encryptor, ERASED_EXTENSION};
+use crate::upcast::Upcasters;
+use crate::backend::PAGE_SIZE;
+use crate::{CloudEvent, EventBackend};
 use anyhow::{Context, Result};
-use async_nats::jetstream;
-use futures::stream::StreamExt;
+use std::collections::HashSet;
 use std::sync::Arc;
-use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 
-/// Event store for querying and retrieving events from JetStream
+/// Events read from the store by [`EventStore::read_page`]
+#[derive(Debug, Clone)]
+pub struct EventPage {
+    pub events: Vec<CloudEvent>,
+    /// Sequence to read the next page from, `None` after the last page
+    pub next: Option<u64>,
+}
+
+/// Event store for querying and retrieving published events
 pub struct EventStore {
-    nats_client: Arc<RwLock<NatsClient>>,
-    stream_name: String,
+    backend: Arc<dyn EventBackend>,
+    upcasters: Upcasters,
+    archive: Option<Arc<EventArchive>>,
+    encryptor: Option<Arc<Encryptor>>,
 }
 
 impl EventStore {
-    pub fn new(nats_client: Arc<RwLock<NatsClient>>, stream_name: String) -> Self {
+    pub fn new(backend: Arc<dyn EventBackend>) -> Self {
         Self {
-            nats_client,
-            stream_name,
+            backend,
+            upcasters: Upcasters::default(),
+            archive: None,
+            encryptor: None,
         }
     }
 
-    /// Retrieve a single event by its ID
-    pub async fn get_event_by_id(&self, event_id: &str) -> Result<Option<CloudEvent>> {
-        debug!("Retrieving event by ID: {}", event_id);
+    /// Use these upcasters for events read for processing
+    pub fn with_upcasters(mut self, upcasters: Upcasters) -> Self {
+        self.upcasters = upcasters;
+        self
+    }
 
-        let client = self.nats_client.read().await;
-        if !client.is_connected() {
-            anyhow::bail!("NATS client not connected");
-        }
+    /// Look up events the backend no longer holds in this archive
+    pub fn with_archive(mut self, archive: Arc<EventArchive>) -> Self {
+        self.archive = Some(archive);
+        self
+    }
 
-        let nats_client = client
-            .client()
-            .context("NATS client not available")?;
-        
-        let jetstream = jetstream::new(nats_client.clone());
-
-        // Get the stream
-        let stream = jetstream
-            .get_stream(&self.stream_name)
-            .await
-            .context("Failed to get stream")?;
-
-        // Create a temporary consumer to fetch messages
-        let consumer = stream
-            .create_consumer(async_nats::jetstream::consumer::pull::Config {
-                durable_name: None,
-                filter_subject: format!("{}.*", self.stream_name),
-                deliver_policy: async_nats::jetstream::consumer::DeliverPolicy::All,
-                ack_policy: async_nats::jetstream::consumer::AckPolicy::None,
-                ..Default::default()
-            })
-            .await
-            .context("Failed to create consumer")?;
-
-        // Fetch messages and search for the matching event ID
-        let mut messages = consumer.fetch().max_messages(1000).messages().await?;
-
-        while let Some(Ok(msg)) = messages.next().await {
-            if let Ok(event) = serde_json::from_slice::<CloudEvent>(&msg.payload) {
-                if event.id == event_id {
-                    info!("Found event: {}", event_id);
-                    return Ok(Some(event));
-                }
+    /// Decrypt encrypted events on reads
+    pub fn with_encryptor(mut self, encryptor: Arc<Encryptor>) -> Self {
+        self.encryptor = Some(encryptor);
+        self
+    }
+
+    /// Retrieve a single event by its ID, from the archive if the backend no longer has it
+    ///
+    /// A stored event that is not valid CloudEvents v1.0 is an error.
+    pub async fn get_event_by_id(&self, event_id: &str) -> Result<Option<CloudEvent>> {
+        let mut event = self.backend.get_event_by_id(event_id).await?;
+        if event.is_none() {
+            if let Some(archive) = &self.archive {
+                event = archive.get_event_by_id(event_id).await?;
             }
         }
+        if let Some(event) = &event {
+            event
+                .validate()
+                .with_context(|| format!("Stored event '{}' is invalid", event_id))?;
+        }
+        event.map(|event| self.decrypt(event)).transpose()
+    }
 
-        warn!("Event not found: {}", event_id);
-        Ok(None)
+    /// Retrieve an event by its ID in the latest shape, for replay and execution
+    pub async fn get_upcast_event_by_id(&self, event_id: &str) -> Result<Option<CloudEvent>> {
+        self.get_event_by_id(event_id)
+            .await?
+            .map(|event| self.upcast(event))
+            .transpose()
+    }
+
+    /// Restore the plaintext of an event read from the stream
+    ///
+    /// Only a destroyed subject key marks the result as erased; an erasure
+    /// marker stored with the event is dropped.
+    pub fn decrypt(&self, mut event: CloudEvent) -> Result<CloudEvent> {
+        event.extensions.remove(ERASED_EXTENSION);
+        match &self.encryptor {
+            Some(encryptor) => encryptor.decrypt(event),
+            None => Ok(event),
+        }
+    }
+
+    /// Bring an event read from the stream to the latest shape
+    pub fn upcast(&self, event: CloudEvent) -> Result<CloudEvent> {
+        self.upcasters.upcast(event)
     }
 
     /// List events by type with optional limit
@@ -80,54 +106,11 @@ impl EventStore {
             event_type, limit
         );
 
-        let client = self.nats_client.read().await;
-        if !client.is_connected() {
-            anyhow::bail!("NATS client not connected");
-        }
-
-        let nats_client = client
-            .client()
-            .context("NATS client not available")?;
-        
-        let jetstream = jetstream::new(nats_client.clone());
-
-        let stream = jetstream
-            .get_stream(&self.stream_name)
-            .await
-            .context("Failed to get stream")?;
-
-        // Determine filter subject
-        let filter_subject = match event_type {
-            Some(ref et) => format!("{}.{}", self.stream_name, et.replace('.', "_")),
-            None => format!("{}.*", self.stream_name),
-        };
-
-        let consumer = stream
-            .create_consumer(async_nats::jetstream::consumer::pull::Config {
-                durable_name: None,
-                filter_subject,
-                deliver_policy: async_nats::jetstream::consumer::DeliverPolicy::All,
-                ack_policy: async_nats::jetstream::consumer::AckPolicy::None,
-                ..Default::default()
-            })
-            .await
-            .context("Failed to create consumer")?;
-
-        let mut events = Vec::new();
-        let mut messages = consumer
-            .fetch()
-            .max_messages(limit.min(1000))
-            .messages()
+        let events = self
+            .backend
+            .list_events(event_type.as_deref(), limit)
             .await?;
-
-        while let Some(Ok(msg)) = messages.next().await {
-            if let Ok(event) = serde_json::from_slice::<CloudEvent>(&msg.payload) {
-                events.push(event);
-                if events.len() >= limit {
-                    break;
-                }
-            }
-        }
+        let events = self.readable(events);
 
         info!(
             "Retrieved {} events (type={:?})",
@@ -137,37 +120,136 @@ impl EventStore {
         Ok(events)
     }
 
-    /// Get the count of messages in the stream
-    pub async fn get_event_count(&self) -> Result<u64> {
-        let client = self.nats_client.read().await;
-        if !client.is_connected() {
-            anyhow::bail!("NATS client not connected");
+    /// Read events from sequence `from` on (1 for the start), as listed by [`Self::list_events`]
+    pub async fn read_page(&self, event_type: Option<&str>, from: u64, limit: usize) -> Result<EventPage> {
+        let page = self.backend.read_page(event_type, from, limit).await?;
+        let next = page.last().map(|stored| stored.sequence + 1);
+        let events = self.readable(page.into_iter().map(|stored| stored.event).collect());
+        Ok(EventPage { events, next })
+    }
+
+    /// The subset of `ids` that is stored, in the backend or the archive
+    ///
+    /// Reads through the whole backend, so it suits bulk checks like imports.
+    pub async fn find_stored(&self, ids: &HashSet<String>) -> Result<HashSet<String>> {
+        let mut found = HashSet::new();
+        let mut from = 1;
+        while found.len() < ids.len() {
+            let page = self.backend.read_page(None, from, PAGE_SIZE).await?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            from = last.sequence + 1;
+            found.extend(
+                page.into_iter()
+                    .map(|stored| stored.event.id)
+                    .filter(|id| ids.contains(id)),
+            );
+        }
+
+        if let Some(archive) = &self.archive {
+            let missing: Vec<String> = ids.difference(&found).cloned().collect();
+            for id in missing {
+                if archive.get_event_by_id(&id).await?.is_some() {
+                    found.insert(id);
+                }
+            }
         }
+        Ok(found)
+    }
 
-        let nats_client = client
-            .client()
-            .context("NATS client not available")?;
-        
-        let jetstream = jetstream::new(nats_client.clone());
+    /// Drop stored events that are not valid CloudEvents and decrypt the rest
+    fn readable(&self, mut events: Vec<CloudEvent>) -> Vec<CloudEvent> {
+        events.retain(|event| match event.validate() {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Skipping invalid stored event '{}': {}", event.id, e);
+                false
+            }
+        });
 
-        let mut stream = jetstream
-            .get_stream(&self.stream_name)
-            .await
-            .context("Failed to get stream")?;
+        // Events that cannot be decrypted are listed as stored
+        for event in events.iter_mut().filter(|event| is_encrypted(event)) {
+            match self.decrypt(event.clone()) {
+                Ok(decrypted) => *event = decrypted,
+                Err(e) => warn!("Listing event '{}' encrypted: {:#}", event.id, e),
+            }
+        }
+        events
+    }
 
-        let info = stream.info().await.context("Failed to get stream info")?;
-        Ok(info.state.messages)
+    /// Get the count of stored events
+    pub async fn get_event_count(&self) -> Result<u64> {
+        self.backend.event_count().await
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{EventPublisher, InMemoryBackend};
+
+    #[tokio::test]
+    async fn test_published_events_are_retrievable() {
+        let backend: Arc<dyn EventBackend> = Arc::new(InMemoryBackend::new());
+        let publisher = EventPublisher::new(backend.clone());
+        let store = EventStore::new(backend);
+
+        let event = CloudEvent::new("com.nexus.user.created", "/test");
+        publisher.publish(&event).await.unwrap();
+
+        let found = store.get_event_by_id(&event.id).await.unwrap().unwrap();
+        assert_eq!(found.event_type, "com.nexus.user.created");
+        assert_eq!(store.get_event_count().await.unwrap(), 1);
+        assert_eq!(
+            store
+                .list_events(Some("com.nexus.user.created".to_string()), 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stored_erasure_marker_is_ignored() {
+        let backend: Arc<dyn EventBackend> = Arc::new(InMemoryBackend::new());
+        let store = EventStore::new(backend.clone());
+
+        let mut event = CloudEvent::new("com.nexus.user.created", "/test").with_data(serde_json::json!({"n": 1}));
+        event.extensions.insert(ERASED_EXTENSION.to_string(), serde_json::Value::Bool(true));
+        backend.publish("events.com.nexus.user.created", &event).await.unwrap();
+
+        let found = store.get_event_by_id(&event.id).await.unwrap().unwrap();
+        assert!(!crate::crypto::is_erased(&found));
+        assert_eq!(found.data, event.data);
+    }
 
     #[tokio::test]
-    async fn test_event_store_creation() {
-        let nats_client = Arc::new(RwLock::new(NatsClient::new()));
-        let store = EventStore::new(nats_client, "test_events".to_string());
-        assert_eq!(store.stream_name, "test_events");
+    async fn test_pages_and_finds_events_past_the_first_page() {
+        let backend: Arc<dyn EventBackend> = Arc::new(InMemoryBackend::new());
+        let publisher = EventPublisher::new(backend.clone());
+        let store = EventStore::new(backend);
+
+        let mut last = None;
+        for _ in 0..PAGE_SIZE + 500 {
+            let event = CloudEvent::new("com.nexus.user.created", "/test");
+            publisher.publish(&event).await.unwrap();
+            last = Some(event.id);
+        }
+
+        let mut read = 0;
+        let mut from = Some(1);
+        while let Some(start) = from {
+            let page = store.read_page(None, start, PAGE_SIZE).await.unwrap();
+            read += page.events.len();
+            from = page.next;
+        }
+        assert_eq!(read, PAGE_SIZE + 500);
+
+        let ids = HashSet::from([last.unwrap(), "missing".to_string()]);
+        let found = store.find_stored(&ids).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(!found.contains("missing"));
     }
 }
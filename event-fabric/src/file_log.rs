@@ -0,0 +1,759 @@
+use crate::format::{decode_event, WireFormat};
+use crate::nats_client::{DEFAULT_MAX_AGE, DEFAULT_MAX_MESSAGES};
+use crate::subject::type_matches;
+use crate::{CloudEvent, EventBackend, StoredEvent, StreamConfig};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+const SEGMENT_EXTENSION: &str = "log";
+const INDEX_EXTENSION: &str = "idx";
+
+/// Default size at which the active segment is rolled over (16 MiB)
+pub const DEFAULT_MAX_SEGMENT_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Retention and segment settings for a [`FileEventLog`]
+///
+/// A zero `max_age` keeps events regardless of age.
+#[derive(Debug, Clone)]
+pub struct FileLogConfig {
+    pub max_messages: u64,
+    pub max_age: Duration,
+    pub max_segment_bytes: u64,
+    /// Encoding of newly appended events
+    pub format: WireFormat,
+    /// Sync each append to disk before returning. Without it, events that are
+    /// still in the OS page cache are lost if the host crashes.
+    pub fsync: bool,
+}
+
+impl Default for FileLogConfig {
+    fn default() -> Self {
+        Self {
+            max_messages: DEFAULT_MAX_MESSAGES as u64,
+            max_age: DEFAULT_MAX_AGE,
+            max_segment_bytes: DEFAULT_MAX_SEGMENT_BYTES,
+            format: WireFormat::default(),
+            fsync: false,
+        }
+    }
+}
+
+impl From<&StreamConfig> for FileLogConfig {
+    /// Apply the retention limits of a stream to the embedded log
+    fn from(stream: &StreamConfig) -> Self {
+        Self {
+            max_messages: u64::try_from(stream.max_messages).unwrap_or(u64::MAX),
+            max_age: stream.max_age,
+            format: stream.format,
+            ..Default::default()
+        }
+    }
+}
+
+/// Embedded, persistent event log for single-node deployments
+///
+/// Events are appended to segment files (`<first seq>.log`) next to an index
+/// file (`<first seq>.idx`) holding the sequence, offset, timestamp, ID and
+/// type of every record. Whole segments are deleted once all of their events
+/// fall outside the retention limits.
+pub struct FileEventLog {
+    state: Arc<Mutex<LogState>>,
+}
+
+impl FileEventLog {
+    /// Open (or create) the log in `dir`, recovering from torn writes
+    pub fn open(dir: impl Into<PathBuf>, config: FileLogConfig) -> Result<Self> {
+        let state = LogState::open(dir.into(), config)?;
+        Ok(Self {
+            state: Arc::new(Mutex::new(state)),
+        })
+    }
+
+    /// Directory holding the segment files
+    pub fn dir(&self) -> PathBuf {
+        self.state.lock().unwrap().dir.clone()
+    }
+
+    /// Run blocking file I/O against the log state off the async runtime
+    async fn with_state<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut LogState) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let state = self.state.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut state = state.lock().unwrap();
+            f(&mut state)
+        })
+        .await
+        .context("Event log task panicked")?
+    }
+}
+
+#[async_trait]
+impl EventBackend for FileEventLog {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    async fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn publish(&self, subject: &str, event: &CloudEvent) -> Result<()> {
+        let subject = subject.to_string();
+        let event = event.clone();
+        self.with_state(move |state| state.append(&subject, &event))
+            .await
+    }
+
+    async fn publish_once(&self, subject: &str, event: &CloudEvent) -> Result<bool> {
+        let subject = subject.to_string();
+        let event = event.clone();
+        self.with_state(move |state| {
+            if state.ids.contains_key(&event.id) {
+                return Ok(false);
+            }
+            state.append(&subject, &event)?;
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn get_event_by_id(&self, event_id: &str) -> Result<Option<CloudEvent>> {
+        let event_id = event_id.to_string();
+        self.with_state(move |state| state.get(&event_id)).await
+    }
+
+    async fn list_events(&self, event_type: Option<&str>, limit: usize) -> Result<Vec<CloudEvent>> {
+        let event_type = event_type.map(str::to_string);
+        self.with_state(move |state| state.list(event_type.as_deref(), limit))
+            .await
+    }
+
+    async fn read_page(&self, event_type: Option<&str>, from: u64, limit: usize) -> Result<Vec<StoredEvent>> {
+        let event_type = event_type.map(str::to_string);
+        self.with_state(move |state| state.page(event_type.as_deref(), from, limit))
+            .await
+    }
+
+    async fn event_count(&self) -> Result<u64> {
+        self.with_state(|state| Ok(state.entries.len() as u64)).await
+    }
+}
+
+/// One event as stored in a segment file
+struct Record {
+    seq: u64,
+    timestamp_ms: i64,
+    subject: String,
+    content_type: String,
+    payload: Vec<u8>,
+}
+
+/// Index entry pointing at a record in a segment file
+#[derive(Debug, Clone)]
+struct IndexEntry {
+    seq: u64,
+    segment: u64,
+    offset: u64,
+    len: u32,
+    timestamp_ms: i64,
+    id: String,
+    event_type: String,
+}
+
+struct ActiveSegment {
+    base_seq: u64,
+    log: File,
+    index: File,
+    size: u64,
+}
+
+struct LogState {
+    dir: PathBuf,
+    config: FileLogConfig,
+    entries: VecDeque<IndexEntry>,
+    ids: HashMap<String, u64>,
+    segments: BTreeSet<u64>,
+    active: Option<ActiveSegment>,
+    next_seq: u64,
+}
+
+impl LogState {
+    fn open(dir: PathBuf, config: FileLogConfig) -> Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create event log directory {:?}", dir))?;
+
+        let mut segments = BTreeSet::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+            if let Some(base) = path.file_stem().and_then(|s| s.to_str()).and_then(|s| s.parse().ok()) {
+                segments.insert(base);
+            }
+        }
+
+        let mut state = Self {
+            dir,
+            config,
+            entries: VecDeque::new(),
+            ids: HashMap::new(),
+            segments: BTreeSet::new(),
+            active: None,
+            next_seq: 1,
+        };
+
+        for base in segments {
+            state.load_segment(base)?;
+        }
+
+        // Keep appending to the last segment if it has room left
+        if let Some(&base) = state.segments.iter().next_back() {
+            let size = fs::metadata(state.segment_path(base, SEGMENT_EXTENSION))?.len();
+            if size < state.config.max_segment_bytes {
+                state.active = Some(state.open_segment(base)?);
+            }
+        }
+
+        state.enforce_retention()?;
+
+        info!(
+            "Opened event log at {:?} ({} events, {} segments)",
+            state.dir,
+            state.entries.len(),
+            state.segments.len()
+        );
+        Ok(state)
+    }
+
+    fn segment_path(&self, base_seq: u64, extension: &str) -> PathBuf {
+        self.dir.join(format!("{:020}.{}", base_seq, extension))
+    }
+
+    /// Load a segment's index, re-indexing any records the index is missing
+    fn load_segment(&mut self, base: u64) -> Result<()> {
+        let log_path = self.segment_path(base, SEGMENT_EXTENSION);
+        let index_path = self.segment_path(base, INDEX_EXTENSION);
+
+        let mut entries = read_index(&index_path, base)?;
+        let indexed_end = entries.last().map(|e| e.offset + 4 + e.len as u64).unwrap_or(0);
+
+        // Records written after the last index entry (crash between the two writes)
+        let mut log = OpenOptions::new().read(true).write(true).open(&log_path)?;
+        let log_len = log.metadata()?.len();
+        if indexed_end < log_len {
+            log.seek(SeekFrom::Start(indexed_end))?;
+            let mut offset = indexed_end;
+            let mut recovered = Vec::new();
+            while let Some((record, len)) = read_record(&mut log)? {
+                recovered.push(index_entry(base, offset, len, &record)?);
+                offset += 4 + len as u64;
+            }
+            if offset < log_len {
+                warn!("Truncating torn write at {:?} offset {}", log_path, offset);
+                log.set_len(offset)?;
+            }
+            if !recovered.is_empty() {
+                warn!("Re-indexed {} record(s) in {:?}", recovered.len(), log_path);
+            }
+            entries.extend(recovered);
+        }
+
+        // Rewrite the index so it matches the segment exactly
+        let mut index = File::create(&index_path)?;
+        for entry in &entries {
+            index.write_all(&encode_index_entry(entry)?)?;
+        }
+
+        for entry in entries {
+            self.next_seq = self.next_seq.max(entry.seq + 1);
+            self.ids.insert(entry.id.clone(), entry.seq);
+            self.entries.push_back(entry);
+        }
+        self.segments.insert(base);
+        Ok(())
+    }
+
+    fn open_segment(&self, base_seq: u64) -> Result<ActiveSegment> {
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.segment_path(base_seq, SEGMENT_EXTENSION))?;
+        let index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.segment_path(base_seq, INDEX_EXTENSION))?;
+        let size = log.metadata()?.len();
+
+        Ok(ActiveSegment {
+            base_seq,
+            log,
+            index,
+            size,
+        })
+    }
+
+    fn append(&mut self, subject: &str, event: &CloudEvent) -> Result<()> {
+        self.enforce_retention()?;
+
+        if self.active.is_none() {
+            let segment = self.open_segment(self.next_seq)?;
+            self.segments.insert(segment.base_seq);
+            self.active = Some(segment);
+        }
+
+        let codec = self.config.format.codec();
+        let record = Record {
+            seq: self.next_seq,
+            timestamp_ms: chrono::Utc::now().timestamp_millis(),
+            subject: subject.to_string(),
+            content_type: codec.content_type().to_string(),
+            payload: codec.encode(event)?,
+        };
+        let bytes = encode_record(&record)?;
+
+        let active = self.active.as_mut().expect("active segment");
+        let entry = IndexEntry {
+            seq: record.seq,
+            segment: active.base_seq,
+            offset: active.size,
+            len: (bytes.len() - 4) as u32,
+            timestamp_ms: record.timestamp_ms,
+            id: event.id.clone(),
+            event_type: event.event_type.clone(),
+        };
+        // Encode both before writing either, so an oversized value leaves no partial record
+        let index_bytes = encode_index_entry(&entry)?;
+
+        active.log.write_all(&bytes).context("Failed to append to event log")?;
+        active.index.write_all(&index_bytes)?;
+        active.size += bytes.len() as u64;
+        if self.config.fsync {
+            // The index is rebuilt from the segment on open, so only the log must be durable
+            active.log.sync_data().context("Failed to sync event log")?;
+        }
+
+        if active.size >= self.config.max_segment_bytes {
+            debug!("Rolling event log segment {}", active.base_seq);
+            self.active = None;
+        }
+
+        // Point at the newest copy, so an ID stays indexed until its last event expires
+        self.ids.insert(entry.id.clone(), entry.seq);
+        self.entries.push_back(entry);
+        self.next_seq += 1;
+
+        self.enforce_retention()?;
+        debug!("Appended event {} as seq {}", event.id, record.seq);
+        Ok(())
+    }
+
+    /// Drop events beyond the count and age limits and delete expired segments
+    fn enforce_retention(&mut self) -> Result<()> {
+        let cutoff = if self.config.max_age.is_zero() {
+            i64::MIN
+        } else {
+            let max_age_ms = i64::try_from(self.config.max_age.as_millis()).unwrap_or(i64::MAX);
+            chrono::Utc::now().timestamp_millis().saturating_sub(max_age_ms)
+        };
+
+        while let Some(front) = self.entries.front() {
+            let over_count = self.entries.len() as u64 > self.config.max_messages;
+            if !over_count && front.timestamp_ms >= cutoff {
+                break;
+            }
+            let expired = self.entries.pop_front().expect("front entry");
+            if self.ids.get(&expired.id) == Some(&expired.seq) {
+                self.ids.remove(&expired.id);
+            }
+        }
+
+        let first_retained = self.entries.front().map(|e| e.seq).unwrap_or(self.next_seq);
+        let active_base = self.active.as_ref().map(|a| a.base_seq);
+        let bases: Vec<u64> = self.segments.iter().copied().collect();
+
+        for (i, &base) in bases.iter().enumerate() {
+            let end = bases.get(i + 1).copied().unwrap_or(self.next_seq);
+            if end > first_retained || Some(base) == active_base {
+                continue;
+            }
+            for extension in [SEGMENT_EXTENSION, INDEX_EXTENSION] {
+                match fs::remove_file(self.segment_path(base, extension)) {
+                    Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+            self.segments.remove(&base);
+            debug!("Deleted expired event log segment {}", base);
+        }
+
+        Ok(())
+    }
+
+    fn entry(&self, seq: u64) -> Option<&IndexEntry> {
+        let first = self.entries.front()?.seq;
+        self.entries.get(seq.checked_sub(first)? as usize)
+    }
+
+    fn read_event(&self, entry: &IndexEntry) -> Result<CloudEvent> {
+        let path = self.segment_path(entry.segment, SEGMENT_EXTENSION);
+        let mut file = File::open(&path)
+            .with_context(|| format!("Failed to open segment {:?}", path))?;
+        file.seek(SeekFrom::Start(entry.offset + 4))?;
+
+        let mut buf = vec![0u8; entry.len as usize];
+        file.read_exact(&mut buf)?;
+        let record = decode_record(&buf)?;
+
+        decode_event(Some(&record.content_type), &record.payload)
+    }
+
+    fn get(&self, event_id: &str) -> Result<Option<CloudEvent>> {
+        match self.ids.get(event_id).and_then(|seq| self.entry(*seq)) {
+            Some(entry) => Ok(Some(self.read_event(entry)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn list(&self, event_type: Option<&str>, limit: usize) -> Result<Vec<CloudEvent>> {
+        self.entries
+            .iter()
+            .filter(|entry| event_type.is_none_or(|t| type_matches(t, &entry.event_type)))
+            .take(limit)
+            .map(|entry| self.read_event(entry))
+            .collect()
+    }
+
+    fn page(&self, event_type: Option<&str>, from: u64, limit: usize) -> Result<Vec<StoredEvent>> {
+        let start = self.entries.partition_point(|entry| entry.seq < from);
+        self.entries
+            .range(start..)
+            .filter(|entry| event_type.is_none_or(|t| type_matches(t, &entry.event_type)))
+            .take(limit)
+            .map(|entry| {
+                Ok(StoredEvent {
+                    sequence: entry.seq,
+                    event: self.read_event(entry)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Append a string with a `u16` length prefix; longer strings are rejected
+fn put_str(buf: &mut Vec<u8>, value: &str) -> Result<()> {
+    let len = u16::try_from(value.len()).map_err(|_| {
+        anyhow::anyhow!(
+            "Value of {} bytes exceeds the {} byte limit of event log strings: {:.32}...",
+            value.len(),
+            u16::MAX,
+            value
+        )
+    })?;
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(value.as_bytes());
+    Ok(())
+}
+
+/// Cursor over a length-prefixed record body
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.buf.len() < n {
+            anyhow::bail!("Truncated event log record");
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn str(&mut self) -> Result<String> {
+        let len = u16::from_le_bytes(self.take(2)?.try_into()?) as usize;
+        Ok(String::from_utf8(self.take(len)?.to_vec())?)
+    }
+}
+
+/// `len | seq | timestamp | subject | content type | payload`
+fn encode_record(record: &Record) -> Result<Vec<u8>> {
+    let mut body = Vec::with_capacity(record.payload.len() + 64);
+    body.extend_from_slice(&record.seq.to_le_bytes());
+    body.extend_from_slice(&record.timestamp_ms.to_le_bytes());
+    put_str(&mut body, &record.subject).context("Invalid subject")?;
+    put_str(&mut body, &record.content_type).context("Invalid content type")?;
+    body.extend_from_slice(&record.payload);
+
+    let len = u32::try_from(body.len()).context("Event is too large for the event log")?;
+    let mut bytes = len.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&body);
+    Ok(bytes)
+}
+
+fn decode_record(body: &[u8]) -> Result<Record> {
+    let mut reader = Reader { buf: body };
+    Ok(Record {
+        seq: reader.u64()?,
+        timestamp_ms: reader.i64()?,
+        subject: reader.str()?,
+        content_type: reader.str()?,
+        payload: reader.buf.to_vec(),
+    })
+}
+
+/// Read the next complete record, returning `None` at the end or at a torn write
+fn read_record(file: &mut File) -> Result<Option<(Record, u32)>> {
+    let mut len = [0u8; 4];
+    match file.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_le_bytes(len);
+
+    let mut body = vec![0u8; len as usize];
+    match file.read_exact(&mut body) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+
+    match decode_record(&body) {
+        Ok(record) => Ok(Some((record, len))),
+        Err(_) => Ok(None),
+    }
+}
+
+fn index_entry(segment: u64, offset: u64, len: u32, record: &Record) -> Result<IndexEntry> {
+    let event = decode_event(Some(&record.content_type), &record.payload)
+        .context("Failed to decode event while re-indexing")?;
+    Ok(IndexEntry {
+        seq: record.seq,
+        segment,
+        offset,
+        len,
+        timestamp_ms: record.timestamp_ms,
+        id: event.id,
+        event_type: event.event_type,
+    })
+}
+
+/// `len | seq | offset | record len | timestamp | id | type`
+fn encode_index_entry(entry: &IndexEntry) -> Result<Vec<u8>> {
+    let mut body = Vec::with_capacity(64 + entry.id.len() + entry.event_type.len());
+    body.extend_from_slice(&entry.seq.to_le_bytes());
+    body.extend_from_slice(&entry.offset.to_le_bytes());
+    body.extend_from_slice(&entry.len.to_le_bytes());
+    body.extend_from_slice(&entry.timestamp_ms.to_le_bytes());
+    put_str(&mut body, &entry.id).context("Invalid event ID")?;
+    put_str(&mut body, &entry.event_type).context("Invalid event type")?;
+
+    let mut bytes = (body.len() as u32).to_le_bytes().to_vec();
+    bytes.extend_from_slice(&body);
+    Ok(bytes)
+}
+
+/// Read all complete entries of an index file, ignoring a torn tail
+fn read_index(path: &Path, segment: u64) -> Result<Vec<IndexEntry>> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut entries = Vec::new();
+    let mut reader = Reader { buf: &bytes };
+    while let Ok(len) = reader.u32() {
+        let Ok(body) = reader.take(len as usize) else {
+            break;
+        };
+        let mut body = Reader { buf: body };
+        let entry = (|| -> Result<IndexEntry> {
+            Ok(IndexEntry {
+                seq: body.u64()?,
+                segment,
+                offset: body.u64()?,
+                len: body.u32()?,
+                timestamp_ms: body.i64()?,
+                id: body.str()?,
+                event_type: body.str()?,
+            })
+        })();
+        match entry {
+            Ok(entry) => entries.push(entry),
+            Err(_) => break,
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("nexus-file-log-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_events_survive_reopen() {
+        let dir = temp_dir();
+        let event = CloudEvent::new("com.nexus.user.created", "/test")
+            .with_data(serde_json::json!({"user": 1}));
+
+        {
+            let log = FileEventLog::open(&dir, FileLogConfig::default()).unwrap();
+            log.publish("events.test", &event).await.unwrap();
+            log.publish("events.test", &CloudEvent::new("com.nexus.other", "/test")).await.unwrap();
+        }
+
+        let log = FileEventLog::open(&dir, FileLogConfig::default()).unwrap();
+        let found = log.get_event_by_id(&event.id).await.unwrap().unwrap();
+        assert_eq!(found.data, event.data);
+        assert_eq!(log.event_count().await.unwrap(), 2);
+        assert_eq!(log.list_events(Some("com.nexus.other"), 10).await.unwrap().len(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reads_mixed_formats() {
+        let dir = temp_dir();
+        let json_event = CloudEvent::new("com.nexus.user.created", "/test");
+        let avro_event = CloudEvent::new("com.nexus.user.created", "/test")
+            .with_data(serde_json::json!({"user": 2}));
+
+        {
+            let log = FileEventLog::open(&dir, FileLogConfig::default()).unwrap();
+            log.publish("events.test", &json_event).await.unwrap();
+        }
+
+        // Switching the format only affects newly appended events
+        let config = FileLogConfig {
+            format: WireFormat::Avro,
+            ..Default::default()
+        };
+        let log = FileEventLog::open(&dir, config).unwrap();
+        log.publish("events.test", &avro_event).await.unwrap();
+
+        assert_eq!(log.list_events(None, 10).await.unwrap().len(), 2);
+        let found = log.get_event_by_id(&avro_event.id).await.unwrap().unwrap();
+        assert_eq!(found.data, avro_event.data);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retention_by_count_deletes_segments() {
+        let dir = temp_dir();
+        let config = FileLogConfig {
+            max_messages: 2,
+            max_segment_bytes: 1,
+            ..Default::default()
+        };
+        let log = FileEventLog::open(&dir, config).unwrap();
+
+        let first = CloudEvent::new("test", "/test");
+        log.publish("events.test", &first).await.unwrap();
+        for _ in 0..3 {
+            log.publish("events.test", &CloudEvent::new("test", "/test")).await.unwrap();
+        }
+
+        assert_eq!(log.event_count().await.unwrap(), 2);
+        assert!(log.get_event_by_id(&first.id).await.unwrap().is_none());
+        let segments = fs::read_dir(&dir)
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().path().extension().unwrap() == SEGMENT_EXTENSION)
+            .count();
+        assert_eq!(segments, 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_republished_id_outlives_expired_copy() {
+        let dir = temp_dir();
+        let config = FileLogConfig {
+            max_messages: 2,
+            fsync: true,
+            ..Default::default()
+        };
+        let log = FileEventLog::open(&dir, config).unwrap();
+
+        let event = CloudEvent::new("test", "/test");
+        log.publish("events.test", &event).await.unwrap();
+        log.publish("events.test", &event).await.unwrap();
+        log.publish("events.test", &CloudEvent::new("test", "/test")).await.unwrap();
+
+        // The first copy expired, the second is still retained
+        assert_eq!(log.event_count().await.unwrap(), 2);
+        assert!(log.get_event_by_id(&event.id).await.unwrap().is_some());
+        assert!(!log.publish_once("events.test", &event).await.unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_recovers_from_torn_write_and_missing_index() {
+        let dir = temp_dir();
+        let event = CloudEvent::new("test", "/test");
+        {
+            let log = FileEventLog::open(&dir, FileLogConfig::default()).unwrap();
+            log.publish("events.test", &event).await.unwrap();
+        }
+
+        let log_path = dir.join(format!("{:020}.{}", 1, SEGMENT_EXTENSION));
+        let mut file = OpenOptions::new().append(true).open(&log_path).unwrap();
+        file.write_all(&[42, 0, 0, 0, 1, 2]).unwrap();
+        fs::remove_file(dir.join(format!("{:020}.{}", 1, INDEX_EXTENSION))).unwrap();
+
+        let log = FileEventLog::open(&dir, FileLogConfig::default()).unwrap();
+        assert!(log.get_event_by_id(&event.id).await.unwrap().is_some());
+        log.publish("events.test", &CloudEvent::new("test", "/test")).await.unwrap();
+        assert_eq!(log.event_count().await.unwrap(), 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejects_strings_over_length_prefix() {
+        let dir = temp_dir();
+        let log = FileEventLog::open(&dir, FileLogConfig::default()).unwrap();
+        let mut long_id = CloudEvent::new("test", "/test");
+        long_id.id = "x".repeat(70_000);
+
+        assert!(log.publish("events.test", &long_id).await.is_err());
+        assert!(log.publish(&"s".repeat(70_000), &CloudEvent::new("test", "/test")).await.is_err());
+        log.publish("events.test", &CloudEvent::new("test", "/test")).await.unwrap();
+        drop(log);
+
+        // Nothing of the rejected events was written
+        let log = FileEventLog::open(&dir, FileLogConfig::default()).unwrap();
+        assert_eq!(log.list_events(None, 10).await.unwrap().len(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
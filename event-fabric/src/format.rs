@@ -0,0 +1,640 @@
+//! Wire formats for stored events
+//!
+//! Every stored message carries the content type of its format (a NATS
+//! `Content-Type` header, or the content type field of a file log record),
+//! so a stream can switch formats and still decode older messages. Messages
+//! without a content type are JSON.
+
+use crate::CloudEvent;
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// NATS header carrying the content type of a stored event
+pub const CONTENT_TYPE_HEADER: &str = "Content-Type";
+
+pub const JSON_CONTENT_TYPE: &str = "application/cloudevents+json";
+pub const PROTOBUF_CONTENT_TYPE: &str = "application/cloudevents+protobuf";
+/// Nexus' own Avro encoding, see [`AVRO_SCHEMA`]
+pub const AVRO_CONTENT_TYPE: &str = "application/vnd.nexus.cloudevent+avro";
+
+/// Content type earlier releases stored [`AVRO_SCHEMA`] events under
+const LEGACY_AVRO_CONTENT_TYPE: &str = "application/cloudevents+avro";
+
+/// Encoding of events in storage
+pub trait EventFormat: Send + Sync {
+    /// Content type recorded with each encoded event
+    fn content_type(&self) -> &'static str;
+
+    fn encode(&self, event: &CloudEvent) -> Result<Vec<u8>>;
+
+    fn decode(&self, bytes: &[u8]) -> Result<CloudEvent>;
+}
+
+/// Wire format of a stream, as configured in the `streams` section of nexus.yaml
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WireFormat {
+    /// CloudEvents JSON format
+    #[default]
+    Json,
+    /// CloudEvents Protobuf format
+    Protobuf,
+    /// Nexus' Avro encoding, [`AVRO_SCHEMA`] (not the CloudEvents Avro format)
+    Avro,
+}
+
+impl WireFormat {
+    pub fn codec(&self) -> &'static dyn EventFormat {
+        match self {
+            WireFormat::Json => &JsonFormat,
+            WireFormat::Protobuf => &ProtobufFormat,
+            WireFormat::Avro => &AvroFormat,
+        }
+    }
+
+    /// Format for a stored content type (JSON when absent)
+    pub fn from_content_type(content_type: Option<&str>) -> Result<Self> {
+        match content_type.map(|ct| ct.split(';').next().unwrap_or("").trim()) {
+            None | Some("") | Some(JSON_CONTENT_TYPE) | Some("application/json") => Ok(WireFormat::Json),
+            Some(PROTOBUF_CONTENT_TYPE) => Ok(WireFormat::Protobuf),
+            Some(AVRO_CONTENT_TYPE) | Some(LEGACY_AVRO_CONTENT_TYPE) => Ok(WireFormat::Avro),
+            Some(other) => anyhow::bail!("Unsupported event content type '{}'", other),
+        }
+    }
+}
+
+/// Decode a stored event using the format named by its content type
+pub fn decode_event(content_type: Option<&str>, bytes: &[u8]) -> Result<CloudEvent> {
+    WireFormat::from_content_type(content_type)?.codec().decode(bytes)
+}
+
+/// CloudEvents JSON format
+pub struct JsonFormat;
+
+impl EventFormat for JsonFormat {
+    fn content_type(&self) -> &'static str {
+        JSON_CONTENT_TYPE
+    }
+
+    fn encode(&self, event: &CloudEvent) -> Result<Vec<u8>> {
+        event.to_json_bytes()
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<CloudEvent> {
+        serde_json::from_slice(bytes).context("Failed to decode JSON event")
+    }
+}
+
+/// An event attribute as typed by the binary formats
+enum Attribute {
+    Bool(bool),
+    Int(i32),
+    /// A number outside the 32-bit integer range
+    Number(serde_json::Number),
+    String(String),
+    Bytes(Vec<u8>),
+    Uri(String),
+    Timestamp(DateTime<Utc>),
+}
+
+/// Optional attributes and extensions of an event, in binary-format types
+///
+/// Objects and arrays in extension values are stored as their JSON text.
+fn attributes_of(event: &CloudEvent) -> Vec<(String, Attribute)> {
+    let mut attributes = vec![("time".to_string(), Attribute::Timestamp(event.time))];
+    if let Some(subject) = &event.subject {
+        attributes.push(("subject".to_string(), Attribute::String(subject.clone())));
+    }
+    if let Some(content_type) = &event.datacontenttype {
+        attributes.push(("datacontenttype".to_string(), Attribute::String(content_type.clone())));
+    }
+    if let Some(dataschema) = &event.dataschema {
+        attributes.push(("dataschema".to_string(), Attribute::Uri(dataschema.clone())));
+    }
+    for (name, value) in &event.extensions {
+        let attribute = match value {
+            serde_json::Value::Bool(b) => Attribute::Bool(*b),
+            serde_json::Value::String(s) => Attribute::String(s.clone()),
+            serde_json::Value::Number(n) => match n.as_i64().and_then(|n| i32::try_from(n).ok()) {
+                Some(n) => Attribute::Int(n),
+                None => Attribute::Number(n.clone()),
+            },
+            other => Attribute::String(other.to_string()),
+        };
+        attributes.push((name.clone(), attribute));
+    }
+    attributes
+}
+
+/// Event data in binary-format form
+enum Data {
+    Binary(Vec<u8>),
+    Text(String),
+}
+
+fn data_of(event: &CloudEvent) -> Result<Option<Data>> {
+    if let Some(encoded) = &event.data_base64 {
+        return Ok(Some(Data::Binary(BASE64.decode(encoded)?)));
+    }
+    Ok(match &event.data {
+        None => None,
+        Some(serde_json::Value::String(text)) if !event.has_json_data() => Some(Data::Text(text.clone())),
+        Some(value) => Some(Data::Text(serde_json::to_string(value)?)),
+    })
+}
+
+/// Reassemble an event from its binary-format parts
+fn build_event(
+    id: String,
+    source: String,
+    specversion: String,
+    event_type: String,
+    attributes: Vec<(String, Attribute)>,
+    data: Option<Data>,
+) -> Result<CloudEvent> {
+    let mut event = CloudEvent::new(event_type, source);
+    event.id = id;
+    event.specversion = specversion;
+    event.datacontenttype = None;
+
+    for (name, attribute) in attributes {
+        let value = match attribute {
+            Attribute::Timestamp(time) if name == "time" => {
+                event.time = time;
+                continue;
+            }
+            Attribute::Bool(b) => serde_json::Value::Bool(b),
+            Attribute::Int(n) => serde_json::Value::from(n),
+            Attribute::Number(n) => serde_json::Value::Number(n),
+            Attribute::String(s) | Attribute::Uri(s) => serde_json::Value::String(s),
+            Attribute::Bytes(b) => serde_json::Value::String(BASE64.encode(b)),
+            Attribute::Timestamp(time) => serde_json::Value::String(time.to_rfc3339()),
+        };
+        match (name.as_str(), value) {
+            ("subject", serde_json::Value::String(s)) => event.subject = Some(s),
+            ("datacontenttype", serde_json::Value::String(s)) => event.datacontenttype = Some(s),
+            ("dataschema", serde_json::Value::String(s)) => event.dataschema = Some(s),
+            (_, value) => {
+                event.extensions.insert(name, value);
+            }
+        }
+    }
+
+    match data {
+        None => {}
+        Some(Data::Binary(bytes)) => event.data_base64 = Some(BASE64.encode(bytes)),
+        Some(Data::Text(text)) if event.has_json_data() => {
+            event.data = Some(serde_json::from_str(&text).context("Invalid JSON event data")?);
+        }
+        Some(Data::Text(text)) => event.data = Some(serde_json::Value::String(text)),
+    }
+    Ok(event)
+}
+
+/// Messages of the CloudEvents Protobuf format (`io.cloudevents.v1.CloudEvent`)
+mod proto {
+    use std::collections::HashMap;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CloudEvent {
+        #[prost(string, tag = "1")]
+        pub id: String,
+        #[prost(string, tag = "2")]
+        pub source: String,
+        #[prost(string, tag = "3")]
+        pub spec_version: String,
+        #[prost(string, tag = "4")]
+        pub r#type: String,
+        #[prost(map = "string, message", tag = "5")]
+        pub attributes: HashMap<String, AttributeValue>,
+        #[prost(oneof = "Data", tags = "6, 7")]
+        pub data: Option<Data>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Data {
+        #[prost(bytes, tag = "6")]
+        BinaryData(Vec<u8>),
+        #[prost(string, tag = "7")]
+        TextData(String),
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AttributeValue {
+        #[prost(oneof = "Attr", tags = "1, 2, 3, 4, 5, 6, 7")]
+        pub attr: Option<Attr>,
+    }
+
+    /// The `ce_boolean` .. `ce_timestamp` fields of `CloudEventAttributeValue`
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Attr {
+        #[prost(bool, tag = "1")]
+        Boolean(bool),
+        #[prost(int32, tag = "2")]
+        Integer(i32),
+        #[prost(string, tag = "3")]
+        String(String),
+        #[prost(bytes, tag = "4")]
+        Bytes(Vec<u8>),
+        #[prost(string, tag = "5")]
+        Uri(String),
+        #[prost(string, tag = "6")]
+        UriRef(String),
+        #[prost(message, tag = "7")]
+        Timestamp(Timestamp),
+    }
+
+    /// `google.protobuf.Timestamp`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Timestamp {
+        #[prost(int64, tag = "1")]
+        pub seconds: i64,
+        #[prost(int32, tag = "2")]
+        pub nanos: i32,
+    }
+}
+
+/// CloudEvents Protobuf format
+///
+/// Event data is written as `binary_data` when it came in as bytes and as
+/// `text_data` otherwise; `proto_data` is not produced and ignored on read.
+/// The format's only number type is a 32-bit integer, so other numbers in
+/// extensions are written, and read back, as strings.
+pub struct ProtobufFormat;
+
+impl EventFormat for ProtobufFormat {
+    fn content_type(&self) -> &'static str {
+        PROTOBUF_CONTENT_TYPE
+    }
+
+    fn encode(&self, event: &CloudEvent) -> Result<Vec<u8>> {
+        use proto::Attr;
+
+        let attributes: HashMap<String, proto::AttributeValue> = attributes_of(event)
+            .into_iter()
+            .map(|(name, attribute)| {
+                let attr = match attribute {
+                    Attribute::Bool(b) => Attr::Boolean(b),
+                    Attribute::Int(n) => Attr::Integer(n),
+                    Attribute::String(s) => Attr::String(s),
+                    Attribute::Bytes(b) => Attr::Bytes(b),
+                    Attribute::Uri(s) => Attr::Uri(s),
+                    Attribute::Number(n) => Attr::String(n.to_string()),
+                    Attribute::Timestamp(time) => Attr::Timestamp(proto::Timestamp {
+                        seconds: time.timestamp(),
+                        nanos: time.timestamp_subsec_nanos() as i32,
+                    }),
+                };
+                (name, proto::AttributeValue { attr: Some(attr) })
+            })
+            .collect();
+
+        let message = proto::CloudEvent {
+            id: event.id.clone(),
+            source: event.source.clone(),
+            spec_version: event.specversion.clone(),
+            r#type: event.event_type.clone(),
+            attributes,
+            data: data_of(event)?.map(|data| match data {
+                Data::Binary(bytes) => proto::Data::BinaryData(bytes),
+                Data::Text(text) => proto::Data::TextData(text),
+            }),
+        };
+        Ok(prost::Message::encode_to_vec(&message))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<CloudEvent> {
+        use proto::Attr;
+
+        let message: proto::CloudEvent =
+            prost::Message::decode(bytes).context("Failed to decode Protobuf event")?;
+
+        let mut attributes = Vec::with_capacity(message.attributes.len());
+        for (name, value) in message.attributes {
+            let attribute = match value.attr {
+                Some(Attr::Boolean(b)) => Attribute::Bool(b),
+                Some(Attr::Integer(n)) => Attribute::Int(n),
+                Some(Attr::String(s)) => Attribute::String(s),
+                Some(Attr::Bytes(b)) => Attribute::Bytes(b),
+                Some(Attr::Uri(s)) | Some(Attr::UriRef(s)) => Attribute::Uri(s),
+                Some(Attr::Timestamp(ts)) => Attribute::Timestamp(
+                    DateTime::from_timestamp(ts.seconds, ts.nanos.max(0) as u32)
+                        .context("Invalid timestamp attribute")?,
+                ),
+                None => continue,
+            };
+            attributes.push((name, attribute));
+        }
+
+        let data = message.data.map(|data| match data {
+            proto::Data::BinaryData(bytes) => Data::Binary(bytes),
+            proto::Data::TextData(text) => Data::Text(text),
+        });
+
+        build_event(
+            message.id,
+            message.source,
+            message.spec_version,
+            message.r#type,
+            attributes,
+            data,
+        )
+    }
+}
+
+/// Avro schema of events written by [`AvroFormat`]
+///
+/// This is a Nexus encoding, not the CloudEvents Avro format: the required
+/// attributes are fields of their own, data is the raw bytes or the text
+/// (JSON text for JSON data), and numbers outside the `int` range keep
+/// their type as `long` or `double`.
+pub const AVRO_SCHEMA: &str = r#"{
+  "namespace": "io.nexus",
+  "type": "record",
+  "name": "NexusEvent",
+  "fields": [
+    {"name": "id", "type": "string"},
+    {"name": "source", "type": "string"},
+    {"name": "specversion", "type": "string"},
+    {"name": "type", "type": "string"},
+    {"name": "attribute", "type": {"type": "map", "values": ["null", "boolean", "int", "string", "bytes", "long", "double"]}},
+    {"name": "data", "type": ["bytes", "null", "string"]}
+  ]
+}"#;
+
+/// Avro binary encoding of [`AVRO_SCHEMA`]
+///
+/// Timestamps are stored as RFC 3339 strings and URIs as strings.
+pub struct AvroFormat;
+
+impl EventFormat for AvroFormat {
+    fn content_type(&self) -> &'static str {
+        AVRO_CONTENT_TYPE
+    }
+
+    fn encode(&self, event: &CloudEvent) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        avro::put_string(&mut buf, &event.id);
+        avro::put_string(&mut buf, &event.source);
+        avro::put_string(&mut buf, &event.specversion);
+        avro::put_string(&mut buf, &event.event_type);
+
+        let attributes = attributes_of(event);
+        if !attributes.is_empty() {
+            avro::put_long(&mut buf, attributes.len() as i64);
+            for (name, attribute) in attributes {
+                avro::put_string(&mut buf, &name);
+                match attribute {
+                    Attribute::Bool(b) => {
+                        avro::put_long(&mut buf, 1);
+                        buf.push(b as u8);
+                    }
+                    Attribute::Int(n) => {
+                        avro::put_long(&mut buf, 2);
+                        avro::put_long(&mut buf, n as i64);
+                    }
+                    Attribute::String(s) | Attribute::Uri(s) => {
+                        avro::put_long(&mut buf, 3);
+                        avro::put_string(&mut buf, &s);
+                    }
+                    Attribute::Timestamp(time) => {
+                        avro::put_long(&mut buf, 3);
+                        avro::put_string(&mut buf, &time.to_rfc3339());
+                    }
+                    Attribute::Bytes(b) => {
+                        avro::put_long(&mut buf, 4);
+                        avro::put_bytes(&mut buf, &b);
+                    }
+                    Attribute::Number(n) => match (n.as_i64(), n.as_f64()) {
+                        (Some(n), _) => {
+                            avro::put_long(&mut buf, 5);
+                            avro::put_long(&mut buf, n);
+                        }
+                        (None, Some(x)) if n.is_f64() => {
+                            avro::put_long(&mut buf, 6);
+                            buf.extend_from_slice(&x.to_le_bytes());
+                        }
+                        // Integers beyond the `long` range
+                        _ => {
+                            avro::put_long(&mut buf, 3);
+                            avro::put_string(&mut buf, &n.to_string());
+                        }
+                    },
+                }
+            }
+        }
+        avro::put_long(&mut buf, 0);
+
+        match data_of(event)? {
+            Some(Data::Binary(bytes)) => {
+                avro::put_long(&mut buf, 0);
+                avro::put_bytes(&mut buf, &bytes);
+            }
+            None => avro::put_long(&mut buf, 1),
+            Some(Data::Text(text)) => {
+                avro::put_long(&mut buf, 2);
+                avro::put_string(&mut buf, &text);
+            }
+        }
+        Ok(buf)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<CloudEvent> {
+        let mut reader = avro::Reader::new(bytes);
+        let id = reader.string()?;
+        let source = reader.string()?;
+        let specversion = reader.string()?;
+        let event_type = reader.string()?;
+
+        let mut attributes = Vec::new();
+        loop {
+            let mut count = reader.long()?;
+            if count == 0 {
+                break;
+            }
+            if count < 0 {
+                // A negative count is followed by the block size in bytes
+                count = -count;
+                reader.long()?;
+            }
+            for _ in 0..count {
+                let name = reader.string()?;
+                let attribute = match reader.long()? {
+                    0 => continue,
+                    1 => Attribute::Bool(reader.byte()? != 0),
+                    2 => Attribute::Int(i32::try_from(reader.long()?).context("Invalid int attribute")?),
+                    3 => {
+                        let value = reader.string()?;
+                        match DateTime::parse_from_rfc3339(&value) {
+                            Ok(time) if name == "time" => Attribute::Timestamp(time.with_timezone(&Utc)),
+                            _ => Attribute::String(value),
+                        }
+                    }
+                    4 => Attribute::Bytes(reader.bytes()?.to_vec()),
+                    5 => Attribute::Number(reader.long()?.into()),
+                    6 => {
+                        let x = f64::from_le_bytes(reader.fixed::<8>()?);
+                        Attribute::Number(serde_json::Number::from_f64(x).context("Invalid double attribute")?)
+                    }
+                    other => anyhow::bail!("Invalid Avro attribute branch {}", other),
+                };
+                attributes.push((name, attribute));
+            }
+        }
+
+        let data = match reader.long()? {
+            0 => Some(Data::Binary(reader.bytes()?.to_vec())),
+            1 => None,
+            2 => Some(Data::Text(reader.string()?)),
+            other => anyhow::bail!("Invalid Avro data branch {}", other),
+        };
+
+        build_event(id, source, specversion, event_type, attributes, data)
+    }
+}
+
+/// Avro binary encoding primitives
+mod avro {
+    use anyhow::{Context, Result};
+
+    pub fn put_long(buf: &mut Vec<u8>, value: i64) {
+        let mut n = ((value << 1) ^ (value >> 63)) as u64;
+        while n >= 0x80 {
+            buf.push((n as u8) | 0x80);
+            n >>= 7;
+        }
+        buf.push(n as u8);
+    }
+
+    pub fn put_bytes(buf: &mut Vec<u8>, value: &[u8]) {
+        put_long(buf, value.len() as i64);
+        buf.extend_from_slice(value);
+    }
+
+    pub fn put_string(buf: &mut Vec<u8>, value: &str) {
+        put_bytes(buf, value.as_bytes());
+    }
+
+    pub struct Reader<'a> {
+        buf: &'a [u8],
+        pos: usize,
+    }
+
+    impl<'a> Reader<'a> {
+        pub fn new(buf: &'a [u8]) -> Self {
+            Self { buf, pos: 0 }
+        }
+
+        pub fn byte(&mut self) -> Result<u8> {
+            let byte = *self.buf.get(self.pos).context("Truncated Avro event")?;
+            self.pos += 1;
+            Ok(byte)
+        }
+
+        pub fn long(&mut self) -> Result<i64> {
+            let mut n: u64 = 0;
+            for shift in (0..64).step_by(7) {
+                let byte = self.byte()?;
+                n |= ((byte & 0x7f) as u64) << shift;
+                if byte & 0x80 == 0 {
+                    return Ok(((n >> 1) as i64) ^ -((n & 1) as i64));
+                }
+            }
+            anyhow::bail!("Invalid Avro varint")
+        }
+
+        pub fn bytes(&mut self) -> Result<&'a [u8]> {
+            let len = usize::try_from(self.long()?).context("Negative Avro length")?;
+            let end = self.pos.checked_add(len).filter(|end| *end <= self.buf.len());
+            let end = end.context("Truncated Avro event")?;
+            let bytes = &self.buf[self.pos..end];
+            self.pos = end;
+            Ok(bytes)
+        }
+
+        pub fn fixed<const N: usize>(&mut self) -> Result<[u8; N]> {
+            let end = self.pos.checked_add(N).filter(|end| *end <= self.buf.len());
+            let end = end.context("Truncated Avro event")?;
+            let bytes = self.buf[self.pos..end].try_into()?;
+            self.pos = end;
+            Ok(bytes)
+        }
+
+        pub fn string(&mut self) -> Result<String> {
+            Ok(String::from_utf8(self.bytes()?.to_vec())?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_events() -> Vec<CloudEvent> {
+        let mut json = CloudEvent::new("com.nexus.telemetry", "/sensors/1")
+            .with_data(serde_json::json!({"temp": 21.5, "tags": ["a", "b"]}))
+            .with_extension("partitionkey", serde_json::json!("sensor-1"))
+            .with_extension("attempt", serde_json::json!(3))
+            .with_extension("sampled", serde_json::json!(true));
+        json.subject = Some("room-4".to_string());
+        json.dataschema = Some("urn:nexus:schema:telemetry:1".to_string());
+
+        let binary = CloudEvent::new("com.nexus.image", "/upload").with_binary_data(&[0, 1, 2, 255], "image/png");
+        let mut text = CloudEvent::new("com.nexus.note", "/notes")
+            .with_data(serde_json::json!("plain text"));
+        text.datacontenttype = Some("text/plain".to_string());
+        let empty = CloudEvent::new("com.nexus.ping", "/");
+
+        vec![json, binary, text, empty]
+    }
+
+    #[test]
+    fn test_binary_formats_round_trip() {
+        for format in [WireFormat::Protobuf, WireFormat::Avro] {
+            for event in sample_events() {
+                let bytes = format.codec().encode(&event).unwrap();
+                let decoded = decode_event(Some(format.codec().content_type()), &bytes).unwrap();
+                assert_eq!(
+                    serde_json::to_value(&decoded).unwrap(),
+                    serde_json::to_value(&event).unwrap(),
+                    "{:?} round trip of {}",
+                    format,
+                    event.event_type
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_numbers_beyond_int_range() {
+        let event = CloudEvent::new("com.nexus.test", "/test")
+            .with_extension("bytes", serde_json::json!(5_000_000_000i64))
+            .with_extension("ratio", serde_json::json!(0.25));
+
+        let avro = AvroFormat.decode(&AvroFormat.encode(&event).unwrap()).unwrap();
+        assert_eq!(avro.extensions["bytes"], serde_json::json!(5_000_000_000i64));
+        assert_eq!(avro.extensions["ratio"], serde_json::json!(0.25));
+
+        let protobuf = ProtobufFormat.decode(&ProtobufFormat.encode(&event).unwrap()).unwrap();
+        assert_eq!(protobuf.extensions["bytes"], serde_json::json!("5000000000"));
+    }
+
+    #[test]
+    fn test_content_type_selects_format() {
+        let event = CloudEvent::new("com.nexus.test", "/test");
+        let json = JsonFormat.encode(&event).unwrap();
+        assert_eq!(decode_event(None, &json).unwrap().id, event.id);
+        assert!(decode_event(Some("application/x-unknown"), &json).is_err());
+
+        let protobuf = ProtobufFormat.encode(&event).unwrap();
+        assert!(protobuf.len() < json.len());
+
+        // Avro events stored by earlier releases stay readable
+        let avro = AvroFormat.encode(&event).unwrap();
+        assert_eq!(decode_event(Some("application/cloudevents+avro"), &avro).unwrap().id, event.id);
+    }
+}
@@ -0,0 +1,332 @@
+use crate::format::{decode_event, WireFormat, CONTENT_TYPE_HEADER, JSON_CONTENT_TYPE};
+use crate::subject::{all_events_filter, filters_for, is_legacy_subject, subject_for, type_matches};
+use crate::backend::PAGE_SIZE;
+use crate::{CloudEvent, EventBackend, NatsClient, StoredEvent};
+use anyhow::{Context, Result};
+use async_nats::jetstream;
+use async_trait::async_trait;
+use futures::stream::StreamExt;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// Event backend persisting events in a NATS JetStream stream
+pub struct JetStreamBackend {
+    nats_client: Arc<RwLock<NatsClient>>,
+    stream_name: String,
+    format: WireFormat,
+    partitions: u32,
+}
+
+impl JetStreamBackend {
+    pub fn new(nats_client: Arc<RwLock<NatsClient>>, stream_name: impl Into<String>) -> Self {
+        Self {
+            nats_client,
+            stream_name: stream_name.into(),
+            format: WireFormat::default(),
+            partitions: 1,
+        }
+    }
+
+    /// Also read events published to the subjects of this many consumer partitions
+    pub fn with_partitions(mut self, partitions: u32) -> Self {
+        self.partitions = partitions.max(1);
+        self
+    }
+
+    /// Encode newly published events in the given format
+    pub fn with_format(mut self, format: WireFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Name of the JetStream stream holding the events
+    pub fn stream_name(&self) -> &str {
+        &self.stream_name
+    }
+
+    async fn get_stream(&self) -> Result<jetstream::stream::Stream> {
+        let client = self.nats_client.read().await;
+        if !client.is_connected() {
+            anyhow::bail!("NATS client not connected");
+        }
+
+        let nats_client = client
+            .client()
+            .context("NATS client not available")?;
+
+        let jetstream = jetstream::new(nats_client.clone());
+
+        jetstream
+            .get_stream(&self.stream_name)
+            .await
+            .context("Failed to get stream")
+    }
+
+    /// Filter subjects of the events of a type pattern, or of all events
+    fn type_filters(&self, event_type: Option<&str>) -> Vec<String> {
+        match event_type {
+            Some(et) => filters_for(et, self.partitions),
+            None => vec![all_events_filter()],
+        }
+    }
+
+    /// Create an ephemeral consumer that reads the whole stream
+    async fn ephemeral_consumer(
+        &self,
+        filters: Vec<String>,
+    ) -> Result<jetstream::consumer::Consumer<jetstream::consumer::pull::Config>> {
+        self.ephemeral_consumer_from(filters, jetstream::consumer::DeliverPolicy::All)
+            .await
+    }
+
+    /// Create an ephemeral consumer that reads the stream from the given position
+    async fn ephemeral_consumer_from(
+        &self,
+        mut filters: Vec<String>,
+        deliver_policy: jetstream::consumer::DeliverPolicy,
+    ) -> Result<jetstream::consumer::Consumer<jetstream::consumer::pull::Config>> {
+        let (filter_subject, filter_subjects) = match filters.len() {
+            1 => (filters.remove(0), Vec::new()),
+            _ => (String::new(), filters),
+        };
+        self.get_stream()
+            .await?
+            .create_consumer(jetstream::consumer::pull::Config {
+                durable_name: None,
+                filter_subject,
+                filter_subjects,
+                deliver_policy,
+                ack_policy: jetstream::consumer::AckPolicy::None,
+                ..Default::default()
+            })
+            .await
+            .context("Failed to create consumer")
+    }
+
+    /// Move events stored under the old `events.<type_with_underscores>` subjects
+    /// to their dotted subjects
+    ///
+    /// Each migrated event is re-published under its new subject and the
+    /// original message is deleted, so migrated events move to the end of the
+    /// stream. Returns the number of migrated events.
+    pub async fn migrate_legacy_subjects(&self) -> Result<usize> {
+        let stream = self.get_stream().await?;
+        let consumer = self.ephemeral_consumer(vec![all_events_filter()]).await?;
+        let client = self.nats_client.read().await;
+        let mut migrated = 0;
+
+        loop {
+            let mut messages = consumer.fetch().max_messages(1000).messages().await?;
+            let mut fetched = 0;
+
+            while let Some(Ok(msg)) = messages.next().await {
+                fetched += 1;
+
+                let Ok(event) = decode_message(&msg) else {
+                    continue;
+                };
+                if !is_legacy_subject(msg.subject.as_str(), &event.event_type) {
+                    continue;
+                }
+
+                let sequence = msg
+                    .info()
+                    .map_err(|e| anyhow::anyhow!("Failed to read message info: {}", e))?
+                    .stream_sequence;
+
+                client
+                    .publish_with_content_type(
+                        &subject_for(&event.event_type),
+                        message_content_type(&msg).unwrap_or(JSON_CONTENT_TYPE),
+                        msg.payload.to_vec(),
+                    )
+                    .await?;
+                stream
+                    .delete_message(sequence)
+                    .await
+                    .context("Failed to delete migrated message")?;
+
+                debug!("Migrated event {} from {}", event.id, msg.subject);
+                migrated += 1;
+            }
+
+            if fetched == 0 {
+                break;
+            }
+        }
+
+        info!("Migrated {} event(s) to dotted subjects", migrated);
+        Ok(migrated)
+    }
+}
+
+#[async_trait]
+impl EventBackend for JetStreamBackend {
+    fn name(&self) -> &'static str {
+        "jetstream"
+    }
+
+    async fn is_available(&self) -> bool {
+        self.nats_client.read().await.is_connected()
+    }
+
+    async fn publish(&self, subject: &str, event: &CloudEvent) -> Result<()> {
+        let client = self.nats_client.read().await;
+
+        if !client.is_connected() {
+            anyhow::bail!("NATS client not connected");
+        }
+
+        let codec = self.format.codec();
+        let payload = codec.encode(event)?;
+        client
+            .publish_with_content_type(subject, codec.content_type(), payload)
+            .await
+    }
+
+    async fn publish_once(&self, subject: &str, event: &CloudEvent) -> Result<bool> {
+        // The duplicate window catches concurrent publishes; the lookup, older duplicates
+        if self.get_event_by_id(&event.id).await?.is_some() {
+            return Ok(false);
+        }
+
+        let client = self.nats_client.read().await;
+        if !client.is_connected() {
+            anyhow::bail!("NATS client not connected");
+        }
+
+        let codec = self.format.codec();
+        let payload = codec.encode(event)?;
+        client
+            .publish_with_msg_id(subject, codec.content_type(), &event.id, payload)
+            .await
+    }
+
+    async fn get_event_by_id(&self, event_id: &str) -> Result<Option<CloudEvent>> {
+        debug!("Retrieving event by ID: {}", event_id);
+
+        // JetStream has no index by event ID; page through the whole stream
+        let mut from = 1;
+        loop {
+            let page = self.read_page(None, from, PAGE_SIZE).await?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            from = last.sequence + 1;
+            if let Some(stored) = page.into_iter().find(|stored| stored.event.id == event_id) {
+                info!("Found event: {}", event_id);
+                return Ok(Some(stored.event));
+            }
+        }
+
+        warn!("Event not found: {}", event_id);
+        Ok(None)
+    }
+
+    async fn list_events(&self, event_type: Option<&str>, limit: usize) -> Result<Vec<CloudEvent>> {
+        let consumer = self.ephemeral_consumer(self.type_filters(event_type)).await?;
+
+        let mut events = Vec::new();
+        let mut messages = consumer
+            .fetch()
+            .max_messages(limit.min(1000))
+            .messages()
+            .await?;
+
+        while let Some(Ok(msg)) = messages.next().await {
+            if let Ok(event) = decode_message(&msg) {
+                // Filters of wildcard patterns over partitions match more than the pattern
+                if event_type.is_some_and(|t| !type_matches(t, &event.event_type)) {
+                    continue;
+                }
+                events.push(event);
+                if events.len() >= limit {
+                    break;
+                }
+            }
+        }
+
+        Ok(events)
+    }
+
+    async fn read_page(&self, event_type: Option<&str>, from: u64, limit: usize) -> Result<Vec<StoredEvent>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let consumer = self
+            .ephemeral_consumer_from(
+                self.type_filters(event_type),
+                jetstream::consumer::DeliverPolicy::ByStartSequence {
+                    start_sequence: from.max(1),
+                },
+            )
+            .await?;
+
+        // Keep fetching past undecodable messages, so a page is only empty at the end
+        let mut page = Vec::new();
+        while page.is_empty() {
+            let mut messages = consumer
+                .fetch()
+                .max_messages(limit.clamp(1, PAGE_SIZE))
+                .messages()
+                .await?;
+            let mut fetched = 0;
+
+            while let Some(msg) = messages.next().await {
+                let msg = msg.map_err(|e| anyhow::anyhow!("Failed to read message: {}", e))?;
+                fetched += 1;
+                let sequence = msg
+                    .info()
+                    .map_err(|e| anyhow::anyhow!("Failed to read message info: {}", e))?
+                    .stream_sequence;
+                match decode_message(&msg) {
+                    Ok(event) if event_type.is_some_and(|t| !type_matches(t, &event.event_type)) => {}
+                    Ok(event) => page.push(StoredEvent { sequence, event }),
+                    Err(e) => warn!("Skipping undecodable message {}: {}", sequence, e),
+                }
+                if page.len() >= limit {
+                    break;
+                }
+            }
+
+            if fetched == 0 {
+                break;
+            }
+        }
+
+        Ok(page)
+    }
+
+    async fn event_count(&self) -> Result<u64> {
+        let mut stream = self.get_stream().await?;
+        let info = stream.info().await.context("Failed to get stream info")?;
+        Ok(info.state.messages)
+    }
+}
+
+/// Content type a message was stored with, if any
+pub(crate) fn message_content_type(msg: &jetstream::Message) -> Option<&str> {
+    msg.headers
+        .as_ref()?
+        .get(CONTENT_TYPE_HEADER)
+        .map(|value| value.as_str())
+}
+
+/// Decode a stored event in whatever format it was written
+pub(crate) fn decode_message(msg: &jetstream::Message) -> Result<CloudEvent> {
+    decode_event(message_content_type(msg), &msg.payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_jetstream_backend_creation() {
+        let nats_client = Arc::new(RwLock::new(NatsClient::new()));
+        let backend = JetStreamBackend::new(nats_client, "test_events");
+        assert_eq!(backend.stream_name(), "test_events");
+        assert!(!backend.is_available().await);
+    }
+}
@@ -2,8 +2,32 @@ pub mod cloudevents;
 pub mod publisher;
 pub mod nats_client;
 pub mod event_store;
+pub mod backend;
+pub mod jetstream_backend;
+pub mod memory_backend;
+pub mod file_log;
+pub mod stream_config;
+pub mod subject;
+pub mod consumer;
+pub mod format;
+pub mod upcast;
+pub mod ndjson;
+pub mod archive;
+pub mod crypto;
+pub mod shredding;
 
-pub use cloudevents::CloudEvent;
+pub use cloudevents::{CloudEvent, ValidationError, IMPORT_EXTENSION, PARTITION_KEY_EXTENSION, REPLAY_EXTENSION};
 pub use publisher::EventPublisher;
 pub use nats_client::NatsClient;
-pub use event_store::EventStore;
+pub use event_store::{EventPage, EventStore};
+pub use backend::{EventBackend, StoredEvent};
+pub use jetstream_backend::JetStreamBackend;
+pub use memory_backend::InMemoryBackend;
+pub use file_log::{FileEventLog, FileLogConfig};
+pub use stream_config::StreamConfig;
+pub use format::{EventFormat, WireFormat};
+pub use consumer::{ConsumerConfig, Delivery, DurableConsumer};
+pub use upcast::{UpcastOp, Upcaster, Upcasters};
+pub use archive::{ArchiveConfig, EventArchive, FsObjectStore, ObjectStore};
+pub use crypto::{EncryptionConfig, EncryptionRule, Encryptor, Keyring};
+pub use shredding::{ErasureLog, ErasureRecord, SubjectKeys};
@@ -0,0 +1,156 @@
+use crate::subject::type_matches;
+use crate::{CloudEvent, EventBackend, StoredEvent};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use tokio::sync::RwLock;
+use tracing::debug;
+
+/// Default number of events kept before the oldest are dropped
+pub const DEFAULT_CAPACITY: usize = 100_000;
+
+/// In-process event backend for local development and tests
+///
+/// Events are kept in memory only and are lost when the process exits.
+pub struct InMemoryBackend {
+    events: RwLock<VecDeque<StoredEvent>>,
+    capacity: usize,
+}
+
+impl InMemoryBackend {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Create a backend that keeps at most `capacity` events
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            events: RwLock::new(VecDeque::new()),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Append an event, dropping the oldest one at capacity
+    fn append(&self, events: &mut VecDeque<StoredEvent>, subject: &str, event: &CloudEvent) {
+        let sequence = events.back().map_or(1, |last| last.sequence + 1);
+        if events.len() >= self.capacity {
+            events.pop_front();
+        }
+        events.push_back(StoredEvent {
+            sequence,
+            event: event.clone(),
+        });
+        debug!("Stored event {} in memory under {}", event.id, subject);
+    }
+}
+
+impl Default for InMemoryBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl EventBackend for InMemoryBackend {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn publish(&self, subject: &str, event: &CloudEvent) -> Result<()> {
+        let mut events = self.events.write().await;
+        self.append(&mut events, subject, event);
+        Ok(())
+    }
+
+    async fn publish_once(&self, subject: &str, event: &CloudEvent) -> Result<bool> {
+        let mut events = self.events.write().await;
+        if events.iter().any(|stored| stored.event.id == event.id) {
+            return Ok(false);
+        }
+        self.append(&mut events, subject, event);
+        Ok(true)
+    }
+
+    async fn get_event_by_id(&self, event_id: &str) -> Result<Option<CloudEvent>> {
+        let events = self.events.read().await;
+        Ok(events
+            .iter()
+            .find(|stored| stored.event.id == event_id)
+            .map(|stored| stored.event.clone()))
+    }
+
+    async fn list_events(&self, event_type: Option<&str>, limit: usize) -> Result<Vec<CloudEvent>> {
+        let events = self.events.read().await;
+        Ok(events
+            .iter()
+            .map(|stored| &stored.event)
+            .filter(|event| event_type.is_none_or(|t| type_matches(t, &event.event_type)))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn read_page(&self, event_type: Option<&str>, from: u64, limit: usize) -> Result<Vec<StoredEvent>> {
+        let events = self.events.read().await;
+        let start = events.partition_point(|stored| stored.sequence < from);
+        Ok(events
+            .range(start..)
+            .filter(|stored| event_type.is_none_or(|t| type_matches(t, &stored.event.event_type)))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn event_count(&self) -> Result<u64> {
+        Ok(self.events.read().await.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_and_get() {
+        let backend = InMemoryBackend::new();
+        let event = CloudEvent::new("com.example.test", "/test");
+
+        backend.publish("events.test", &event).await.unwrap();
+
+        let found = backend.get_event_by_id(&event.id).await.unwrap();
+        assert_eq!(found.unwrap().id, event.id);
+        assert!(backend.get_event_by_id("missing").await.unwrap().is_none());
+        assert_eq!(backend.event_count().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_filters_by_type() {
+        let backend = InMemoryBackend::new();
+        for event_type in ["a.created", "b.created", "a.created", "a.deleted"] {
+            let event = CloudEvent::new(event_type, "/test");
+            backend.publish("events.test", &event).await.unwrap();
+        }
+
+        assert_eq!(backend.list_events(None, 10).await.unwrap().len(), 4);
+        assert_eq!(backend.list_events(Some("a.created"), 10).await.unwrap().len(), 2);
+        assert_eq!(backend.list_events(Some("a.>"), 10).await.unwrap().len(), 3);
+        assert_eq!(backend.list_events(None, 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_capacity_drops_oldest() {
+        let backend = InMemoryBackend::with_capacity(2);
+        let first = CloudEvent::new("test", "/test");
+        backend.publish("events.test", &first).await.unwrap();
+        for _ in 0..2 {
+            backend.publish("events.test", &CloudEvent::new("test", "/test")).await.unwrap();
+        }
+
+        assert_eq!(backend.event_count().await.unwrap(), 2);
+        assert!(backend.get_event_by_id(&first.id).await.unwrap().is_none());
+    }
+}
@@ -1,8 +1,16 @@
+use crate::format::CONTENT_TYPE_HEADER;
+use crate::StreamConfig;
 use anyhow::{Context, Result};
 use async_nats::jetstream;
 use std::time::Duration;
 use tracing::{info, warn};
 
+/// Default maximum number of messages retained in a stream
+pub const DEFAULT_MAX_MESSAGES: i64 = 100_000;
+
+/// Default maximum age of messages retained in a stream (7 days)
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
 /// NATS JetStream client for event streaming
 pub struct NatsClient {
     client: Option<async_nats::Client>,
@@ -50,37 +58,56 @@ impl NatsClient {
         anyhow::bail!("Failed to connect to NATS after {} retries", max_retries)
     }
 
-    /// Create or get a JetStream stream
-    pub async fn create_stream(&self, stream_name: &str) -> Result<()> {
+    /// Create a JetStream stream, or bring an existing one in line with `config`
+    pub async fn create_stream(&self, config: &StreamConfig) -> Result<()> {
         let jetstream = self.jetstream.as_ref()
             .context("Not connected to NATS")?;
 
+        config.validate()?;
+
         // Check if stream already exists
-        match jetstream.get_stream(stream_name).await {
-            Ok(_) => {
-                info!("Stream '{}' already exists", stream_name);
+        match jetstream.get_stream(&config.name).await {
+            Ok(mut stream) => {
+                let existing = stream
+                    .info()
+                    .await
+                    .context("Failed to get stream info")?
+                    .config
+                    .clone();
+
+                let (updated, incompatible) = config.to_jetstream_update(&existing);
+                for change in &incompatible {
+                    warn!(
+                        "Stream '{}': cannot change {} on an existing stream, keeping the current setting",
+                        config.name, change
+                    );
+                }
+
+                if updated == existing {
+                    info!("Stream '{}' already exists", config.name);
+                    return Ok(());
+                }
+
+                jetstream
+                    .update_stream(&updated)
+                    .await
+                    .context("Failed to update stream")?;
+
+                info!("Stream '{}' updated with new settings", config.name);
                 return Ok(());
             }
             Err(_) => {
-                info!("Creating new stream '{}'", stream_name);
+                info!("Creating new stream '{}'", config.name);
             }
         }
 
         // Create the stream
         jetstream
-            .create_stream(jetstream::stream::Config {
-                name: stream_name.to_string(),
-                subjects: vec![format!("{}.*", stream_name)],
-                retention: jetstream::stream::RetentionPolicy::Limits,
-                max_messages: 100_000,
-                max_age: Duration::from_secs(7 * 24 * 60 * 60), // 7 days
-                storage: jetstream::stream::StorageType::File,
-                ..Default::default()
-            })
+            .create_stream(config.to_jetstream())
             .await
             .context("Failed to create stream")?;
 
-        info!("Stream '{}' created successfully", stream_name);
+        info!("Stream '{}' created successfully", config.name);
         Ok(())
     }
 
@@ -99,6 +126,57 @@ impl NatsClient {
         Ok(())
     }
 
+    /// Publish a message with its content type in the `Content-Type` header
+    pub async fn publish_with_content_type(
+        &self,
+        subject: &str,
+        content_type: &str,
+        payload: Vec<u8>,
+    ) -> Result<()> {
+        let jetstream = self.jetstream.as_ref()
+            .context("Not connected to NATS")?;
+
+        let mut headers = async_nats::HeaderMap::new();
+        headers.insert(CONTENT_TYPE_HEADER, content_type);
+
+        jetstream
+            .publish_with_headers(subject.to_string(), headers, payload.into())
+            .await
+            .context("Failed to publish message")?
+            .await
+            .context("Failed to get publish acknowledgment")?;
+
+        Ok(())
+    }
+
+    /// Publish a message with its content type and a `Nats-Msg-Id` header
+    ///
+    /// JetStream drops a message whose ID it already stored within the
+    /// stream's duplicate window. Returns whether the message was stored.
+    pub async fn publish_with_msg_id(
+        &self,
+        subject: &str,
+        content_type: &str,
+        msg_id: &str,
+        payload: Vec<u8>,
+    ) -> Result<bool> {
+        let jetstream = self.jetstream.as_ref()
+            .context("Not connected to NATS")?;
+
+        let mut headers = async_nats::HeaderMap::new();
+        headers.insert(CONTENT_TYPE_HEADER, content_type);
+        headers.insert(async_nats::header::NATS_MESSAGE_ID, msg_id);
+
+        let ack = jetstream
+            .publish_with_headers(subject.to_string(), headers, payload.into())
+            .await
+            .context("Failed to publish message")?
+            .await
+            .context("Failed to get publish acknowledgment")?;
+
+        Ok(!ack.duplicate)
+    }
+
     /// Get the underlying NATS client
     pub fn client(&self) -> Option<&async_nats::Client> {
         self.client.as_ref()
@@ -0,0 +1,86 @@
+use crate::CloudEvent;
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+/// Media type of event exports and imports
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Leading bytes of every gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// One event as a newline-terminated JSON line
+pub fn encode_line(event: &CloudEvent) -> Result<Vec<u8>> {
+    let mut line = event.to_json_bytes()?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Parse NDJSON into events, gunzipping first if needed
+///
+/// Blank lines are ignored. Every event must be valid CloudEvents v1.0;
+/// errors name the offending line.
+pub fn decode(bytes: &[u8]) -> Result<Vec<CloudEvent>> {
+    let text = if is_gzip(bytes) {
+        let mut text = String::new();
+        GzDecoder::new(bytes)
+            .read_to_string(&mut text)
+            .context("Failed to decompress gzip archive")?;
+        text
+    } else {
+        String::from_utf8(bytes.to_vec()).context("Archive is not UTF-8")?
+    };
+
+    let mut events = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = CloudEvent::from_json(line).with_context(|| format!("Line {}: not a CloudEvent", index + 1))?;
+        event
+            .validate()
+            .with_context(|| format!("Line {}: invalid event '{}'", index + 1, event.id))?;
+        events.push(event);
+    }
+    Ok(events)
+}
+
+pub fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&GZIP_MAGIC)
+}
+
+pub fn gzip(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes)?;
+    Ok(encoder.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_plain_and_gzip() {
+        let events = vec![
+            CloudEvent::new("com.nexus.order.created", "/test").with_data(serde_json::json!({"id": 1})),
+            CloudEvent::new("com.nexus.invoice.received", "/test").with_binary_data(b"<invoice/>", "application/xml"),
+        ];
+        let mut archive = Vec::new();
+        for event in &events {
+            archive.extend(encode_line(event).unwrap());
+        }
+        archive.extend(b"\n");
+
+        for bytes in [archive.clone(), gzip(&archive).unwrap()] {
+            let decoded = decode(&bytes).unwrap();
+            assert_eq!(decoded.len(), 2);
+            assert_eq!(decoded[0].id, events[0].id);
+            assert_eq!(decoded[1].data_bytes().unwrap().unwrap(), b"<invoice/>");
+        }
+
+        let err = decode(b"{\"id\": 1}\n").unwrap_err();
+        assert!(err.to_string().starts_with("Line 1"));
+    }
+}
@@ -1,38 +1,121 @@
-use crate::{CloudEvent, NatsClient};
+use crate::cloudevents::resolve_key;
+use crate::crypto::Encryptor;
+use crate::subject::{partition_of, partitioned_subject_for, subject_for};
+use crate::{CloudEvent, EventBackend};
 use anyhow::Result;
 use std::sync::Arc;
-use tokio::sync::RwLock;
 
-/// Publishes events to NATS JetStream
+/// Publishes events to the configured event backend
 pub struct EventPublisher {
-    nats_client: Arc<RwLock<NatsClient>>,
+    backend: Arc<dyn EventBackend>,
+    encryptor: Option<Arc<Encryptor>>,
+    partitioning: Option<Partitioning>,
+}
+
+/// How events are spread over the partitions of the durable consumers
+struct Partitioning {
+    partitions: u32,
+    /// Path of the partition key in the event, before the `partitionkey` extension
+    key_path: Option<String>,
 }
 
 impl EventPublisher {
-    pub fn new(nats_client: Arc<RwLock<NatsClient>>) -> Self {
-        Self { nats_client }
+    pub fn new(backend: Arc<dyn EventBackend>) -> Self {
+        Self {
+            backend,
+            encryptor: None,
+            partitioning: None,
+        }
+    }
+
+    /// Publish to the subject of the event's partition, see [`partitioned_subject_for`]
+    ///
+    /// The partition key is read from `key_path` if given, then from the
+    /// `partitionkey` extension; events without a key are spread by ID.
+    pub fn with_partitions(mut self, partitions: u32, key_path: Option<String>) -> Self {
+        self.partitioning = (partitions > 1).then_some(Partitioning { partitions, key_path });
+        self
+    }
+
+    /// Encrypt events covered by the encryptor's rules before storing them
+    pub fn with_encryptor(mut self, encryptor: Arc<Encryptor>) -> Self {
+        self.encryptor = Some(encryptor);
+        self
     }
 
-    /// Publish a CloudEvent to NATS
+    /// Publish a CloudEvent
     pub async fn publish(&self, event: &CloudEvent) -> Result<()> {
-        let subject = format!("events.{}", event.event_type.replace('.', "_"));
-        self.publish_to(&subject, event).await
+        self.publish_to(&self.subject(event), event).await
+    }
+
+    /// Publish a CloudEvent unless an event with its ID is already stored
+    ///
+    /// Returns whether the event was published.
+    pub async fn publish_once(&self, event: &CloudEvent) -> Result<bool> {
+        let subject = self.subject(event);
+        let published = match &self.encryptor {
+            Some(encryptor) => self.backend.publish_once(&subject, &encryptor.encrypt(event)?).await?,
+            None => self.backend.publish_once(&subject, event).await?,
+        };
+
+        if published {
+            tracing::info!("Published event {} to {} ({})", event.id, subject, self.backend.name());
+        } else {
+            tracing::debug!("Event {} is already stored, not publishing it again", event.id);
+        }
+        Ok(published)
+    }
+
+    /// Subject of an event, with the partition of its key if partitioned
+    fn subject(&self, event: &CloudEvent) -> String {
+        match &self.partitioning {
+            Some(partitioning) => {
+                let key = partitioning
+                    .key_path
+                    .as_deref()
+                    .and_then(|path| resolve_key(event, path))
+                    .or_else(|| event.partition_key())
+                    .unwrap_or_else(|| event.id.clone());
+                partitioned_subject_for(&event.event_type, partition_of(&key, partitioning.partitions))
+            }
+            None => subject_for(&event.event_type),
+        }
     }
 
     /// Publish to a specific subject/stream
     pub async fn publish_to(&self, subject: &str, event: &CloudEvent) -> Result<()> {
         tracing::debug!("Publishing event {} to subject: {}", event.id, subject);
-        
-        let client = self.nats_client.read().await;
-        
-        if !client.is_connected() {
-            anyhow::bail!("NATS client not connected");
-        }
 
-        let payload = event.to_json_bytes()?;
-        client.publish(subject, payload).await?;
+        match &self.encryptor {
+            Some(encryptor) => self.backend.publish(subject, &encryptor.encrypt(event)?).await?,
+            None => self.backend.publish(subject, event).await?,
+        }
 
-        tracing::info!("Published event {} to {}", event.id, subject);
+        tracing::info!("Published event {} to {} ({})", event.id, subject, self.backend.name());
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryBackend;
+
+    #[tokio::test]
+    async fn test_publish_once_skips_stored_ids_past_the_first_page() {
+        let backend: Arc<dyn EventBackend> = Arc::new(InMemoryBackend::new());
+        let publisher = EventPublisher::new(backend.clone());
+
+        let first = CloudEvent::new("com.nexus.github.push", "/test");
+        assert!(publisher.publish_once(&first).await.unwrap());
+        for _ in 0..crate::backend::PAGE_SIZE + 200 {
+            publisher.publish(&CloudEvent::new("com.nexus.github.push", "/test")).await.unwrap();
+        }
+        let last = CloudEvent::new("com.nexus.github.push", "/test");
+        assert!(publisher.publish_once(&last).await.unwrap());
+
+        assert!(!publisher.publish_once(&first).await.unwrap());
+        assert!(!publisher.publish_once(&last).await.unwrap());
+        assert_eq!(backend.event_count().await.unwrap(), crate::backend::PAGE_SIZE as u64 + 202);
+    }
+}
@@ -0,0 +1,296 @@
+use crate::crypto::{cipher_from_base64, generate_key};
+use aes_gcm::Aes256Gcm;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+/// Prefix of `nexuskeyid` values naming a subject key
+pub const SUBJECT_KEY_PREFIX: &str = "subject:";
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SubjectKey {
+    subject: String,
+    key: String,
+}
+
+/// One encryption key per subject (e.g. a user ID), stored in a JSON file
+///
+/// Erasing a subject deletes its keys, which makes every event encrypted
+/// under them unreadable for good (crypto-shredding). Processes may share the
+/// file: the cached keys are re-read whenever the file changed, so a key
+/// erased elsewhere is neither decrypted with nor encrypted under again, and
+/// changes to the file are made under an advisory lock on `<file>.lock`.
+pub struct SubjectKeys {
+    path: PathBuf,
+    cache: RwLock<Cache>,
+}
+
+/// The keys as last read, with the modification time and size of the file they were read from
+struct Cache {
+    keys: BTreeMap<String, SubjectKey>,
+    stamp: Option<(SystemTime, u64)>,
+}
+
+impl SubjectKeys {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let stamp = file_stamp(&path)?;
+        let keys = read_keys(&path)?;
+        Ok(Self {
+            path,
+            cache: RwLock::new(Cache { keys, stamp }),
+        })
+    }
+
+    /// The key ID and cipher of a subject, creating a key on first use
+    pub(crate) fn key_for(&self, subject: &str) -> Result<(String, Aes256Gcm)> {
+        self.refresh()?;
+        if let Some(found) = find_subject(&self.cache.read().unwrap().keys, subject) {
+            return found;
+        }
+
+        // Another process may create the same subject's key meanwhile; check again under the lock
+        let _lock = lock_keys(&self.path)?;
+        let mut cache = self.cache.write().unwrap();
+        cache.stamp = file_stamp(&self.path)?;
+        cache.keys = read_keys(&self.path)?;
+        if let Some(found) = find_subject(&cache.keys, subject) {
+            return found;
+        }
+
+        let id = format!("{}{}", SUBJECT_KEY_PREFIX, uuid::Uuid::new_v4().simple());
+        let key = generate_key();
+        cache.keys.insert(id.clone(), SubjectKey {
+            subject: subject.to_string(),
+            key: key.clone(),
+        });
+        write_keys(&self.path, &cache.keys)?;
+        cache.stamp = file_stamp(&self.path)?;
+        Ok((id.clone(), cipher_from_base64(&id, &key)?))
+    }
+
+    /// The cipher for a key ID, `None` once the key was erased
+    pub(crate) fn key(&self, id: &str) -> Result<Option<Aes256Gcm>> {
+        self.refresh()?;
+        let cache = self.cache.read().unwrap();
+        cache.keys.get(id).map(|key| cipher_from_base64(id, &key.key)).transpose()
+    }
+
+    /// Destroy all keys of a subject, returning how many were destroyed
+    pub fn erase(&self, subject: &str) -> Result<usize> {
+        let _lock = lock_keys(&self.path)?;
+        let mut cache = self.cache.write().unwrap();
+        cache.stamp = file_stamp(&self.path)?;
+        cache.keys = read_keys(&self.path)?;
+        let before = cache.keys.len();
+        cache.keys.retain(|_, key| key.subject != subject);
+        let destroyed = before - cache.keys.len();
+        if destroyed > 0 {
+            write_keys(&self.path, &cache.keys)?;
+            cache.stamp = file_stamp(&self.path)?;
+        }
+        Ok(destroyed)
+    }
+
+    /// Re-read the keys if the file changed since they were cached
+    fn refresh(&self) -> Result<()> {
+        let stamp = file_stamp(&self.path)?;
+        if self.cache.read().unwrap().stamp == stamp {
+            return Ok(());
+        }
+        let keys = read_keys(&self.path)?;
+        *self.cache.write().unwrap() = Cache { keys, stamp };
+        Ok(())
+    }
+}
+
+fn find_subject(keys: &BTreeMap<String, SubjectKey>, subject: &str) -> Option<Result<(String, Aes256Gcm)>> {
+    keys.iter()
+        .find(|(_, key)| key.subject == subject)
+        .map(|(id, key)| Ok((id.clone(), cipher_from_base64(id, &key.key)?)))
+}
+
+/// Modification time and size of the key file, `None` if there is none yet
+fn file_stamp(path: &Path) -> Result<Option<(SystemTime, u64)>> {
+    match std::fs::metadata(path) {
+        Ok(metadata) => Ok(Some((metadata.modified()?, metadata.len()))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read subject keys {}", path.display())),
+    }
+}
+
+/// Take the exclusive lock of the key file, held until the returned file is dropped
+///
+/// The lock is on a separate file, since writes replace the key file itself.
+fn lock_keys(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let lock_path = path.with_extension("lock");
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+    file.lock()
+        .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+    Ok(file)
+}
+
+fn read_keys(path: &Path) -> Result<BTreeMap<String, SubjectKey>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse subject keys {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read subject keys {}", path.display())),
+    }
+}
+
+/// Replace the file atomically, so an erased key is never left in a stale copy
+fn write_keys(path: &Path, keys: &BTreeMap<String, SubjectKey>) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, serde_json::to_vec_pretty(keys)?)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&partial, std::fs::Permissions::from_mode(0o600))?;
+    }
+    std::fs::rename(&partial, path)
+        .with_context(|| format!("Failed to write subject keys {}", path.display()))
+}
+
+/// An entry of the erasure audit trail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErasureRecord {
+    pub subject: String,
+    pub keys_destroyed: usize,
+    pub erased_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+}
+
+/// Append-only audit trail of erasures, one JSON record per line
+pub struct ErasureLog {
+    path: PathBuf,
+}
+
+impl ErasureLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn append(&self, record: &ErasureRecord) -> Result<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open erasure log {}", self.path.display()))?;
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// All records, oldest first
+    pub fn records(&self) -> Result<Vec<ErasureRecord>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).context("Corrupt erasure log entry"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{is_erased, EncryptionRule, Encryptor, Keyring};
+    use crate::CloudEvent;
+    use serde_json::json;
+
+    #[test]
+    fn test_erasing_a_subject_redacts_its_events() {
+        let dir = std::env::temp_dir().join(format!("nexus-shred-{}", uuid::Uuid::new_v4()));
+        let rules = vec![EncryptionRule {
+            event_type: "com.nexus.user.*".to_string(),
+            paths: vec![],
+            subject_key: Some("$.data.user_id".to_string()),
+        }];
+        let encryptor = Encryptor::new(Keyring::generate(), rules.clone())
+            .with_subject_keys(SubjectKeys::open(dir.join("keys.json")).unwrap());
+
+        let ada = CloudEvent::new("com.nexus.user.created", "/test").with_data(json!({"user_id": "ada", "email": "ada@example.com"}));
+        let bob = CloudEvent::new("com.nexus.user.created", "/test").with_data(json!({"user_id": "bob"}));
+        let ada_stored = encryptor.encrypt(&ada).unwrap();
+        let bob_stored = encryptor.encrypt(&bob).unwrap();
+
+        assert_eq!(encryptor.erase_subject("ada").unwrap(), 1);
+        assert_eq!(encryptor.erase_subject("ada").unwrap(), 0);
+
+        // A second process sharing the key file sees the erasure too
+        let other = Encryptor::new(Keyring::generate(), rules)
+            .with_subject_keys(SubjectKeys::open(dir.join("keys.json")).unwrap());
+        let redacted = other.decrypt(ada_stored).unwrap();
+        assert!(is_erased(&redacted));
+        assert!(redacted.data.is_none());
+        assert_eq!(other.decrypt(bob_stored).unwrap().data, bob.data);
+
+        let log = ErasureLog::new(dir.join("erasures.log"));
+        log.append(&ErasureRecord {
+            subject: "ada".to_string(),
+            keys_destroyed: 1,
+            erased_at: Utc::now(),
+            trace_id: None,
+        })
+        .unwrap();
+        assert_eq!(log.records().unwrap()[0].subject, "ada");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_key_cached_before_erase_in_another_process() {
+        let dir = std::env::temp_dir().join(format!("nexus-shred-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("keys.json");
+        let writer = SubjectKeys::open(&path).unwrap();
+        let reader = SubjectKeys::open(&path).unwrap();
+
+        let (id, _) = writer.key_for("ada").unwrap();
+        assert!(reader.key(&id).unwrap().is_some());
+        assert_eq!(reader.key_for("ada").unwrap().0, id);
+
+        assert_eq!(writer.erase("ada").unwrap(), 1);
+        assert!(reader.key(&id).unwrap().is_none());
+        assert_ne!(reader.key_for("ada").unwrap().0, id);
+
+        // Processes creating keys at once keep each other's keys
+        std::thread::scope(|scope| {
+            for n in 0..8 {
+                let path = &path;
+                scope.spawn(move || SubjectKeys::open(path).unwrap().key_for(&format!("user-{}", n)).unwrap());
+            }
+        });
+        assert_eq!(read_keys(&path).unwrap().len(), 9);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+use crate::format::WireFormat;
+use crate::nats_client::{DEFAULT_MAX_AGE, DEFAULT_MAX_MESSAGES};
+use async_nats::jetstream::stream::{self, DiscardPolicy, RetentionPolicy, StorageType};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::time::Duration;
+
+/// Settings of a JetStream stream, as configured in the `streams` section of nexus.yaml
+///
+/// Limits follow JetStream conventions: `-1` means unlimited for counts and
+/// sizes, `0` means unlimited for `max_age`. Without `subjects` the stream
+/// captures `<name>.>`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamConfig {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subjects: Vec<String>,
+    pub retention: RetentionPolicy,
+    pub max_messages: i64,
+    pub max_bytes: i64,
+    #[serde(
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub max_age: Duration,
+    pub max_message_size: i32,
+    pub replicas: usize,
+    pub storage: StorageType,
+    pub discard: DiscardPolicy,
+    /// Encoding of newly stored events
+    pub format: WireFormat,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self::named("events")
+    }
+}
+
+impl StreamConfig {
+    /// Default settings for a stream capturing `<name>.>`
+    pub fn named(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            subjects: vec![],
+            retention: RetentionPolicy::Limits,
+            max_messages: DEFAULT_MAX_MESSAGES,
+            max_bytes: -1,
+            max_age: DEFAULT_MAX_AGE,
+            max_message_size: -1,
+            replicas: 1,
+            storage: StorageType::File,
+            discard: DiscardPolicy::Old,
+            format: WireFormat::Json,
+        }
+    }
+
+    /// Check the settings for values JetStream would reject
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.name.is_empty() || self.name.contains(['.', '*', '>', ' ']) {
+            anyhow::bail!("Invalid stream name '{}'", self.name);
+        }
+        if self.subjects.iter().any(|s| s.is_empty() || s.contains(' ')) {
+            anyhow::bail!("Stream '{}' has an invalid subject", self.name);
+        }
+        if self.replicas == 0 || self.replicas > 5 {
+            anyhow::bail!(
+                "Stream '{}' replicas must be between 1 and 5, got {}",
+                self.name,
+                self.replicas
+            );
+        }
+        Ok(())
+    }
+
+    /// Subjects captured by the stream
+    pub fn effective_subjects(&self) -> Vec<String> {
+        if self.subjects.is_empty() {
+            vec![format!("{}.>", self.name)]
+        } else {
+            self.subjects.clone()
+        }
+    }
+
+    /// JetStream configuration for creating the stream
+    pub fn to_jetstream(&self) -> stream::Config {
+        stream::Config {
+            name: self.name.clone(),
+            subjects: self.effective_subjects(),
+            retention: self.retention,
+            max_messages: self.max_messages,
+            max_bytes: self.max_bytes,
+            max_age: self.max_age,
+            max_message_size: self.max_message_size,
+            num_replicas: self.replicas,
+            storage: self.storage,
+            discard: self.discard,
+            ..Default::default()
+        }
+    }
+
+    /// JetStream configuration for updating an existing stream
+    ///
+    /// Settings JetStream cannot change in place keep their existing values
+    /// and are reported back so the caller can warn about them.
+    pub fn to_jetstream_update(&self, existing: &stream::Config) -> (stream::Config, Vec<String>) {
+        let mut updated = existing.clone();
+        let mut incompatible = Vec::new();
+
+        if existing.storage != self.storage {
+            incompatible.push(format!(
+                "storage {:?} -> {:?}",
+                existing.storage, self.storage
+            ));
+        }
+        if existing.retention != self.retention {
+            incompatible.push(format!(
+                "retention {:?} -> {:?}",
+                existing.retention, self.retention
+            ));
+        }
+
+        updated.subjects = self.effective_subjects();
+        updated.max_messages = self.max_messages;
+        updated.max_bytes = self.max_bytes;
+        updated.max_age = self.max_age;
+        updated.max_message_size = self.max_message_size;
+        updated.num_replicas = self.replicas;
+        updated.discard = self.discard;
+
+        (updated, incompatible)
+    }
+}
+
+/// Parse a duration such as `7d`, `12h`, `30m`, `45s` or `0`
+pub fn parse_duration(value: &str) -> anyhow::Result<Duration> {
+    let value = value.trim();
+    if value == "0" {
+        return Ok(Duration::ZERO);
+    }
+
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow::anyhow!("Missing unit in duration '{}'", value))?;
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid duration '{}'", value))?;
+
+    let multiplier: u64 = match unit {
+        "ms" => return Ok(Duration::from_millis(amount)),
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => anyhow::bail!("Unknown unit '{}' in duration '{}'", unit, value),
+    };
+    let seconds = amount
+        .checked_mul(multiplier)
+        .ok_or_else(|| anyhow::anyhow!("Duration '{}' is too large", value))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Format a duration using the largest unit that divides it exactly
+pub fn format_duration(duration: &Duration) -> String {
+    let secs = duration.as_secs();
+    if duration.is_zero() {
+        "0".to_string()
+    } else if duration.subsec_nanos() != 0 {
+        format!("{}ms", duration.as_millis())
+    } else if secs.is_multiple_of(86_400) {
+        format!("{}d", secs / 86_400)
+    } else if secs.is_multiple_of(3_600) {
+        format!("{}h", secs / 3_600)
+    } else if secs.is_multiple_of(60) {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+pub fn serialize_duration<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format_duration(duration))
+}
+
+pub fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_duration(&value).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("7d").unwrap(), DEFAULT_MAX_AGE);
+        assert_eq!(parse_duration("90m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_duration("0").unwrap(), Duration::ZERO);
+        assert!(parse_duration("7").is_err());
+        assert!(parse_duration("7w").is_err());
+        assert!(parse_duration("999999999999999d").is_err());
+        assert!(parse_duration("99999999999999999999s").is_err());
+        assert_eq!(format_duration(&Duration::from_secs(5400)), "90m");
+    }
+
+    #[test]
+    fn test_update_keeps_incompatible_settings() {
+        let existing = StreamConfig::default().to_jetstream();
+        let desired = StreamConfig {
+            storage: StorageType::Memory,
+            max_messages: 500,
+            ..Default::default()
+        };
+
+        let (updated, incompatible) = desired.to_jetstream_update(&existing);
+        assert_eq!(updated.storage, StorageType::File);
+        assert_eq!(updated.max_messages, 500);
+        assert_eq!(incompatible.len(), 1);
+    }
+}
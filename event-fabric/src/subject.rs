@@ -0,0 +1,242 @@
+//! Mapping between CloudEvent types and NATS subjects
+//!
+//! Each dot-separated token of an event type becomes a subject token, so
+//! `com.nexus.user.created` is published to `events.com.nexus.user.created`
+//! and consumers can subscribe to `events.com.nexus.user.>`. Characters NATS
+//! does not allow in a token (`%`, whitespace, `*`, `>`, control and non-ASCII
+//! bytes) are percent-encoded and an empty token is written as `%`, which
+//! keeps the encoding reversible.
+//!
+//! With partitioned consumers, events are published to
+//! `events.%p<partition>.<type tokens>` instead, so each partition's consumer
+//! only receives its own events. Encoded type tokens never look like `%p0`.
+
+/// Subject prefix under which all events are published
+pub const SUBJECT_PREFIX: &str = "events";
+
+/// Subject an event of the given type is published to
+pub fn subject_for(event_type: &str) -> String {
+    let tokens: Vec<String> = event_type.split('.').map(encode_token).collect();
+    format!("{}.{}", SUBJECT_PREFIX, tokens.join("."))
+}
+
+/// Subject an event of the given type is published to in one of several partitions
+pub fn partitioned_subject_for(event_type: &str, partition: u32) -> String {
+    let tokens: Vec<String> = event_type.split('.').map(encode_token).collect();
+    format!("{}.{}.{}", SUBJECT_PREFIX, partition_token(partition), tokens.join("."))
+}
+
+/// Filter subject matching all events
+pub fn all_events_filter() -> String {
+    format!("{}.>", SUBJECT_PREFIX)
+}
+
+/// Filter subject matching the events of one partition
+pub fn partition_filter(partition: u32) -> String {
+    format!("{}.{}.>", SUBJECT_PREFIX, partition_token(partition))
+}
+
+/// Partition of a key among `partitions`, the same in every process and release
+pub fn partition_of(key: &str, partitions: u32) -> u32 {
+    (stable_hash(key) % partitions.max(1) as u64) as u32
+}
+
+/// FNV-1a hash of a string, the same in every process and release
+pub(crate) fn stable_hash(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn partition_token(partition: u32) -> String {
+    format!("%p{}", partition)
+}
+
+fn is_partition_token(token: &str) -> bool {
+    token
+        .strip_prefix("%p")
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Filter subject for an event type or a type pattern
+///
+/// Tokens that are exactly `*` or `>` are kept as NATS wildcards, so
+/// `com.nexus.user.>` matches every `com.nexus.user` event.
+pub fn filter_for(type_pattern: &str) -> String {
+    let tokens: Vec<String> = type_pattern
+        .split('.')
+        .map(|token| match token {
+            "*" | ">" => token.to_string(),
+            _ => encode_token(token),
+        })
+        .collect();
+    format!("{}.{}", SUBJECT_PREFIX, tokens.join("."))
+}
+
+/// Filter subjects for a type pattern, in whichever partition the events were published
+///
+/// The filters do not overlap, as JetStream requires of a consumer's filters.
+/// A pattern starting with a wildcard cannot be told apart from a partition
+/// token and yields the filter of all events; callers then match types
+/// themselves with [`type_matches`].
+pub fn filters_for(type_pattern: &str, partitions: u32) -> Vec<String> {
+    if partitions <= 1 {
+        return vec![filter_for(type_pattern)];
+    }
+    if matches!(type_pattern.split('.').next(), Some("*" | ">")) {
+        return vec![all_events_filter()];
+    }
+    let unpartitioned = filter_for(type_pattern);
+    let rest = &unpartitioned[SUBJECT_PREFIX.len() + 1..];
+    std::iter::once(unpartitioned.clone())
+        .chain((0..partitions).map(|partition| format!("{}.{}.{}", SUBJECT_PREFIX, partition_token(partition), rest)))
+        .collect()
+}
+
+/// Recover the event type from a subject produced by [`subject_for`] or [`partitioned_subject_for`]
+pub fn event_type_from_subject(subject: &str) -> Option<String> {
+    let rest = subject.strip_prefix(SUBJECT_PREFIX)?.strip_prefix('.')?;
+    let rest = match rest.split_once('.') {
+        Some((first, types)) if is_partition_token(first) => types,
+        _ => rest,
+    };
+    let tokens: Option<Vec<String>> = rest.split('.').map(decode_token).collect();
+    Some(tokens?.join("."))
+}
+
+/// Whether an event type matches a type pattern as used by [`filter_for`]
+pub fn type_matches(type_pattern: &str, event_type: &str) -> bool {
+    let mut pattern = type_pattern.split('.');
+    let mut tokens = event_type.split('.');
+    loop {
+        match (pattern.next(), tokens.next()) {
+            (Some(">"), Some(_)) => return true,
+            (Some("*"), Some(_)) => {}
+            (Some(p), Some(t)) if p == t => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Subject the old scheme published an event type to, `events.<type with underscores>`
+pub fn legacy_subject_for(event_type: &str) -> String {
+    format!("{}.{}", SUBJECT_PREFIX, event_type.replace('.', "_"))
+}
+
+/// Whether a subject was written by the old `events.<type with underscores>` scheme
+///
+/// Only that exact subject counts: events published to custom subjects are
+/// left alone, as are types without dots, whose subject is the same in both schemes.
+pub fn is_legacy_subject(subject: &str, event_type: &str) -> bool {
+    subject == legacy_subject_for(event_type) && subject != subject_for(event_type)
+}
+
+fn encode_token(token: &str) -> String {
+    if token.is_empty() {
+        return "%".to_string();
+    }
+
+    let mut encoded = String::with_capacity(token.len());
+    for byte in token.bytes() {
+        let escape = matches!(byte, b'%' | b'*' | b'>') || !byte.is_ascii_graphic();
+        if escape {
+            encoded.push_str(&format!("%{:02X}", byte));
+        } else {
+            encoded.push(byte as char);
+        }
+    }
+    encoded
+}
+
+fn decode_token(token: &str) -> Option<String> {
+    if token == "%" {
+        return Some(String::new());
+    }
+
+    let bytes = token.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = token.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dotted_types_keep_hierarchy() {
+        assert_eq!(
+            subject_for("com.nexus.user.created"),
+            "events.com.nexus.user.created"
+        );
+        assert_eq!(
+            event_type_from_subject("events.com.nexus.user.created").unwrap(),
+            "com.nexus.user.created"
+        );
+        assert_eq!(filter_for("com.nexus.user.>"), "events.com.nexus.user.>");
+    }
+
+    #[test]
+    fn test_encoding_is_reversible() {
+        for event_type in [
+            "com.nexus.user_created",
+            "a..b",
+            ".leading",
+            "with space.100%",
+            "wild*.card>",
+            "ünïcode.type",
+        ] {
+            let subject = subject_for(event_type);
+            assert!(!subject.contains(' '));
+            assert!(!subject.contains('*') && !subject.contains('>'));
+            assert!(!subject.split('.').any(str::is_empty));
+            assert_eq!(event_type_from_subject(&subject).unwrap(), event_type);
+        }
+    }
+
+    #[test]
+    fn test_type_patterns_and_legacy_subjects() {
+        assert!(type_matches("com.nexus.user.>", "com.nexus.user.created"));
+        assert!(type_matches("com.*.user.created", "com.nexus.user.created"));
+        assert!(!type_matches("com.nexus.user.>", "com.nexus.user"));
+        assert!(!type_matches("com.nexus.user", "com.nexus.user.created"));
+
+        assert!(is_legacy_subject("events.com_nexus_user_created", "com.nexus.user.created"));
+        assert!(!is_legacy_subject("events.com.nexus.user.created", "com.nexus.user.created"));
+        assert!(!is_legacy_subject("events.tenant-a.user.created", "com.nexus.user.created"));
+        assert!(!is_legacy_subject("orders.priority", "com.nexus.order.placed"));
+        assert!(!is_legacy_subject("events.ping", "ping"));
+    }
+
+    #[test]
+    fn test_partitioned_subjects() {
+        let subject = partitioned_subject_for("com.nexus.user.created", 3);
+        assert_eq!(subject, "events.%p3.com.nexus.user.created");
+        assert_eq!(event_type_from_subject(&subject).unwrap(), "com.nexus.user.created");
+        assert_eq!(partition_filter(3), "events.%p3.>");
+        // A type token that looks like a partition token is encoded
+        assert_eq!(event_type_from_subject(&subject_for("%p3.x")).unwrap(), "%p3.x");
+
+        assert_eq!(filters_for("com.nexus.>", 1), vec!["events.com.nexus.>"]);
+        assert_eq!(
+            filters_for("com.nexus.>", 2),
+            vec!["events.com.nexus.>", "events.%p0.com.nexus.>", "events.%p1.com.nexus.>"]
+        );
+        assert_eq!(filters_for("*.user.created", 2), vec!["events.>"]);
+
+        assert_eq!(partition_of("user-1", 1), 0);
+        let spread: std::collections::HashSet<u32> = (0..100).map(|i| partition_of(&format!("user-{}", i), 4)).collect();
+        assert_eq!(spread.len(), 4);
+    }
+}
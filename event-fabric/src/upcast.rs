@@ -0,0 +1,187 @@
+use crate::cloudevents::schema_urn;
+use crate::CloudEvent;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// A declarative change to event `data`, addressed by dotted paths such as `customer.name`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum UpcastOp {
+    /// Move a field; nothing happens when it is missing
+    Rename { from: String, to: String },
+    /// Copy a field; nothing happens when it is missing
+    Copy { from: String, to: String },
+    /// Set a field, creating parent objects as needed
+    Set { path: String, value: Value },
+    /// Set a field only if it is missing
+    Default { path: String, value: Value },
+    /// Remove a field if present
+    Remove { path: String },
+}
+
+impl UpcastOp {
+    /// Apply the change to a JSON value
+    pub fn apply(&self, data: &mut Value) -> Result<()> {
+        apply(data, self)
+    }
+}
+
+/// Turns `data` of one event type from `from_version` into `from_version + 1`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Upcaster {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub from_version: u32,
+    pub operations: Vec<UpcastOp>,
+}
+
+/// Registered upcasters, chained per event type
+///
+/// An event's version comes from its `dataschema` URN (see
+/// [`crate::cloudevents::schema_urn`]); events without one are treated as
+/// version 1. Upcasting works on a copy, stored events never change.
+#[derive(Debug, Clone, Default)]
+pub struct Upcasters {
+    steps: HashMap<(String, u32), Vec<UpcastOp>>,
+}
+
+impl Upcasters {
+    pub fn new(upcasters: Vec<Upcaster>) -> Result<Self> {
+        let mut steps = HashMap::new();
+        for upcaster in upcasters {
+            if upcaster.from_version == 0 {
+                anyhow::bail!("Upcaster for '{}' has from_version 0, versions start at 1", upcaster.event_type);
+            }
+            let key = (upcaster.event_type, upcaster.from_version);
+            if steps.contains_key(&key) {
+                anyhow::bail!("Duplicate upcaster: {} from version {}", key.0, key.1);
+            }
+            steps.insert(key, upcaster.operations);
+        }
+        Ok(Self { steps })
+    }
+
+    /// Bring an event to the latest version reachable through the registered upcasters
+    pub fn upcast(&self, mut event: CloudEvent) -> Result<CloudEvent> {
+        if self.steps.is_empty() || event.data_base64.is_some() {
+            return Ok(event);
+        }
+
+        let original = event.schema_version().unwrap_or(1);
+        let mut version = original;
+        while let Some(operations) = self.steps.get(&(event.event_type.clone(), version)) {
+            let data = event.data.get_or_insert_with(|| Value::Object(Map::new()));
+            for op in operations {
+                apply(data, op).with_context(|| {
+                    format!("Failed to upcast {} from version {}", event.event_type, version)
+                })?;
+            }
+            version += 1;
+        }
+
+        if version != original {
+            event.dataschema = Some(schema_urn(&event.event_type, version));
+        }
+        Ok(event)
+    }
+}
+
+fn apply(data: &mut Value, op: &UpcastOp) -> Result<()> {
+    match op {
+        UpcastOp::Rename { from, to } => {
+            if let Some(value) = take(data, from) {
+                insert(data, to, value)?;
+            }
+        }
+        UpcastOp::Copy { from, to } => {
+            if let Some(value) = get(data, from).cloned() {
+                insert(data, to, value)?;
+            }
+        }
+        UpcastOp::Set { path, value } => insert(data, path, value.clone())?,
+        UpcastOp::Default { path, value } => {
+            if get(data, path).is_none() {
+                insert(data, path, value.clone())?;
+            }
+        }
+        UpcastOp::Remove { path } => {
+            take(data, path);
+        }
+    }
+    Ok(())
+}
+
+fn split(path: &str) -> (Vec<&str>, &str) {
+    let mut parts: Vec<&str> = path.split('.').collect();
+    let last = parts.pop().unwrap_or_default();
+    (parts, last)
+}
+
+fn get<'a>(data: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(data, |value, key| value.get(key))
+}
+
+fn take(data: &mut Value, path: &str) -> Option<Value> {
+    let (parents, last) = split(path);
+    let parent = parents.into_iter().try_fold(data, |value, key| value.get_mut(key))?;
+    parent.as_object_mut()?.remove(last)
+}
+
+fn insert(data: &mut Value, path: &str, value: Value) -> Result<()> {
+    let (parents, last) = split(path);
+    let mut current = data;
+    for key in parents {
+        let object = current
+            .as_object_mut()
+            .with_context(|| format!("'{}' does not lead through objects", path))?;
+        current = object.entry(key).or_insert_with(|| Value::Object(Map::new()));
+    }
+    current
+        .as_object_mut()
+        .with_context(|| format!("'{}' does not lead through objects", path))?
+        .insert(last.to_string(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_upcast_chains_versions() {
+        let upcasters: Vec<Upcaster> = serde_json::from_value(json!([
+            {
+                "type": "com.nexus.order.created",
+                "from_version": 1,
+                "operations": [
+                    {"op": "rename", "from": "customer_name", "to": "customer.name"},
+                    {"op": "default", "path": "currency", "value": "USD"}
+                ]
+            },
+            {
+                "type": "com.nexus.order.created",
+                "from_version": 2,
+                "operations": [{"op": "remove", "path": "legacy"}]
+            }
+        ]))
+        .unwrap();
+        let upcasters = Upcasters::new(upcasters).unwrap();
+
+        let stored = CloudEvent::new("com.nexus.order.created", "/test")
+            .with_data(json!({"customer_name": "Ada", "legacy": true}));
+        let latest = upcasters.upcast(stored.clone()).unwrap();
+
+        assert_eq!(latest.schema_version(), Some(3));
+        assert_eq!(latest.data.unwrap(), json!({"customer": {"name": "Ada"}, "currency": "USD"}));
+        assert_eq!(stored.data.unwrap()["customer_name"], "Ada");
+
+        // Events already at version 2 only take the second step
+        let mut v2 = CloudEvent::new("com.nexus.order.created", "/test")
+            .with_data(json!({"currency": "EUR", "legacy": 1}));
+        v2.dataschema = Some(schema_urn("com.nexus.order.created", 2));
+        assert_eq!(upcasters.upcast(v2).unwrap().data.unwrap(), json!({"currency": "EUR"}));
+    }
+}
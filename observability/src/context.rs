@@ -1,5 +1,10 @@
+use std::future::Future;
 use uuid::Uuid;
 
+tokio::task_local! {
+    static CURRENT: RequestContext;
+}
+
 /// Request context for distributed tracing
 #[derive(Debug, Clone)]
 pub struct RequestContext {
@@ -17,6 +22,11 @@ impl RequestContext {
         }
     }
 
+    /// The context of the request being served, or a new one outside of [`scope`]
+    pub fn current() -> Self {
+        CURRENT.try_with(Clone::clone).unwrap_or_default()
+    }
+
     pub fn with_event_id(mut self, event_id: String) -> Self {
         self.event_id = Some(event_id);
         self
@@ -36,7 +46,7 @@ impl Default for RequestContext {
 
 /// Add context to current span
 pub fn with_context(ctx: &RequestContext) {
-    tracing::Span::current().record("trace_id", &ctx.trace_id.as_str());
+    tracing::Span::current().record("trace_id", ctx.trace_id.as_str());
     
     if let Some(event_id) = &ctx.event_id {
         tracing::Span::current().record("event_id", event_id.as_str());
@@ -47,9 +57,12 @@ pub fn with_context(ctx: &RequestContext) {
     }
 }
 
-/// Get trace ID from current span (if available)
+/// Run a request's handling with `ctx` as its current context
+pub async fn scope<F: Future>(ctx: RequestContext, f: F) -> F::Output {
+    CURRENT.scope(ctx, f).await
+}
+
+/// Trace ID of the request being served (if any)
 pub fn get_trace_id() -> Option<String> {
-    // In a real implementation, this would extract from span context
-    // For now, return None as we need proper span context extraction
-    None
+    CURRENT.try_with(|ctx| ctx.trace_id.clone()).ok()
 }
@@ -1,10 +1,14 @@
 use anyhow::{Context, Result};
 use std::collections::HashMap;
+use std::io::Cursor;
 use std::sync::Arc;
-use std::sync::Mutex;
+use std::sync::{Mutex, RwLock};
+use wasi_common::pipe::{ReadPipe, WritePipe};
 use wasmtime::*;
 use wasmtime_wasi::WasiCtxBuilder;
 
+type OutputBuffer = Arc<RwLock<Cursor<Vec<u8>>>>;
+
 /// Executes WASM modules with WASI support and module caching
 pub struct WasmExecutor {
     engine: Engine,
@@ -13,8 +17,28 @@ pub struct WasmExecutor {
 
 struct WasmState {
     wasi: wasmtime_wasi::WasiCtx,
-    input: Arc<Mutex<Vec<u8>>>,
-    output: Arc<Mutex<Vec<u8>>>,
+}
+
+/// Build a WASI context that feeds `input` to the guest's stdin, captures
+/// its stdout into `output` and adds `env` to the inherited environment
+fn build_wasi(
+    input: &[u8],
+    output: &OutputBuffer,
+    env: &[(String, String)],
+) -> Result<wasmtime_wasi::WasiCtx> {
+    let wasi = WasiCtxBuilder::new()
+        .stdin(Box::new(ReadPipe::from(input)))
+        .stdout(Box::new(WritePipe::from_shared(output.clone())))
+        .inherit_stderr()
+        .inherit_env()?
+        .envs(env)?
+        .build();
+    Ok(wasi)
+}
+
+/// Take whatever the guest wrote to stdout
+fn take_output(output: &OutputBuffer) -> Vec<u8> {
+    std::mem::take(output.write().unwrap().get_mut())
 }
 
 impl WasmExecutor {
@@ -67,6 +91,16 @@ impl WasmExecutor {
 
     /// Execute a WASM module with input data
     pub async fn execute(&self, module_bytes: &[u8], input: &[u8]) -> Result<Vec<u8>> {
+        self.execute_with_env(module_bytes, input, &[]).await
+    }
+
+    /// Execute a WASM module with input data and extra environment variables
+    pub async fn execute_with_env(
+        &self,
+        module_bytes: &[u8],
+        input: &[u8],
+        env: &[(String, String)],
+    ) -> Result<Vec<u8>> {
         // Create cache key from module hash
         let cache_key = format!("module_{:x}", md5::compute(module_bytes));
         let module = self.get_or_compile_module(module_bytes, &cache_key)?;
@@ -74,24 +108,11 @@ impl WasmExecutor {
         let mut linker = Linker::new(&self.engine);
         wasmtime_wasi::add_to_linker(&mut linker, |s: &mut WasmState| &mut s.wasi)?;
 
-        // Create output buffer
-        let output_buffer = Arc::new(Mutex::new(Vec::new()));
-        let input_data = Arc::new(Mutex::new(input.to_vec()));
-
-        // Create WASI context (simplified for MVP)
-        let wasi = WasiCtxBuilder::new()
-            .inherit_stdio()
-            .inherit_env()?
-            .build();
-
-        let mut store = Store::new(
-            &self.engine,
-            WasmState {
-                wasi,
-                input: input_data.clone(),
-                output: output_buffer.clone(),
-            },
-        );
+        // The input is delivered on stdin, stdout is captured as the output
+        let output_buffer = OutputBuffer::default();
+        let wasi = build_wasi(input, &output_buffer, env)?;
+
+        let mut store = Store::new(&self.engine, WasmState { wasi });
 
         let instance = linker.instantiate_async(&mut store, &module).await
             .context("Failed to instantiate WASM module")?;
@@ -107,8 +128,13 @@ impl WasmExecutor {
             }
         }
 
-        // For MVP, return the input as simulated output
-        // Full I/O handling will be improved in future iterations
+        let output = take_output(&output_buffer);
+        if !output.is_empty() {
+            tracing::info!("WASM execution completed ({} bytes written)", output.len());
+            return Ok(output);
+        }
+
+        // Guests that don't write to stdout get a simulated acknowledgement
         let simulated_output = format!(
             "{{\"status\":\"executed\",\"input_size\":{},\"message\":\"Function executed successfully\"}}",
             input.len()
@@ -132,22 +158,10 @@ impl WasmExecutor {
         let mut linker = Linker::new(&self.engine);
         wasmtime_wasi::add_to_linker(&mut linker, |s: &mut WasmState| &mut s.wasi)?;
 
-        let output_buffer = Arc::new(Mutex::new(Vec::new()));
-        let input_data = Arc::new(Mutex::new(input.to_vec()));
-
-        let wasi = WasiCtxBuilder::new()
-            .inherit_stdio()
-            .inherit_env()?
-            .build();
-
-        let mut store = Store::new(
-            &self.engine,
-            WasmState {
-                wasi,
-                input: input_data,
-                output: output_buffer.clone(),
-            },
-        );
+        let output_buffer = OutputBuffer::default();
+        let wasi = build_wasi(input, &output_buffer, &[])?;
+
+        let mut store = Store::new(&self.engine, WasmState { wasi });
 
         let instance = linker.instantiate_async(&mut store, &module).await
             .context("Failed to instantiate WASM module")?;
@@ -160,6 +174,12 @@ impl WasmExecutor {
             anyhow::bail!("Function '{}' not found in module", func_name);
         }
 
+        let output = take_output(&output_buffer);
+        if !output.is_empty() {
+            tracing::info!("Function '{}' completed ({} bytes written)", func_name, output.len());
+            return Ok(output);
+        }
+
         let simulated_output = format!(
             "{{\"status\":\"executed\",\"function\":\"{}\",\"input_size\":{}}}",
             func_name,
@@ -180,4 +200,25 @@ mod tests {
         let executor = WasmExecutor::new();
         assert!(executor.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_guest_receives_input_on_stdin() {
+        // Echoes stdin back to stdout
+        let echo = r#"
+            (module
+              (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+              (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+              (memory (export "memory") 1)
+              (func (export "_start")
+                (i32.store (i32.const 0) (i32.const 100))
+                (i32.store (i32.const 4) (i32.const 1000))
+                (drop (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+                (i32.store (i32.const 4) (i32.load (i32.const 8)))
+                (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))
+        "#;
+
+        let executor = WasmExecutor::new().unwrap();
+        let output = executor.execute(echo.as_bytes(), b"{\"nexusreplay\":true}").await.unwrap();
+        assert_eq!(output, b"{\"nexusreplay\":true}");
+    }
 }